@@ -226,9 +226,14 @@ try {
     Write-Host ""
     Write-Section "[BUILD] Running npm run tauri build..." "Yellow"
     # TAURI_DEVTOOLS_ENABLED=true 时必须带上 devtools feature，否则 release 无法打开 WebView 控制台
-    if ($envMap["TAURI_DEVTOOLS_ENABLED"] -eq "true") {
-        Write-Host "   [INFO] Extra args: --features devtools" -ForegroundColor DarkGray
-        npm run tauri build -- --features devtools
+    # TAURI_PAC_ENABLED=true 时带上 pac feature（内置 JavaScript 引擎，默认不编译）
+    $cargoFeatures = @()
+    if ($envMap["TAURI_DEVTOOLS_ENABLED"] -eq "true") { $cargoFeatures += "devtools" }
+    if ($envMap["TAURI_PAC_ENABLED"] -eq "true") { $cargoFeatures += "pac" }
+    if ($cargoFeatures.Count -gt 0) {
+        $featureList = $cargoFeatures -join ","
+        Write-Host "   [INFO] Extra args: --features $featureList" -ForegroundColor DarkGray
+        npm run tauri build -- --features $featureList
     } else {
         npm run tauri build
     }
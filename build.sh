@@ -114,9 +114,13 @@ else
     rm -rf src-tauri/target/release/bundle/
     
     # TAURI_DEVTOOLS_ENABLED=true 时必须带上 devtools feature，否则 release 无法打开 WebView 控制台
-    if [ "$TAURI_DEVTOOLS_ENABLED" = "true" ]; then
-        echo -e "${YELLOW}🔧 构建参数: --features devtools${NC}"
-        npm run tauri build -- --features devtools
+    # TAURI_PAC_ENABLED=true 时带上 pac feature（内置 JavaScript 引擎，默认不编译）
+    CARGO_FEATURES=""
+    [ "$TAURI_DEVTOOLS_ENABLED" = "true" ] && CARGO_FEATURES="devtools"
+    [ "$TAURI_PAC_ENABLED" = "true" ] && CARGO_FEATURES="${CARGO_FEATURES:+$CARGO_FEATURES,}pac"
+    if [ -n "$CARGO_FEATURES" ]; then
+        echo -e "${YELLOW}🔧 构建参数: --features $CARGO_FEATURES${NC}"
+        npm run tauri build -- --features "$CARGO_FEATURES"
     else
         npm run tauri build
     fi
@@ -6,5 +6,49 @@ fn main() {
     println!("cargo:rerun-if-env-changed=TAURI_PRODUCT_NAME");
     println!("cargo:rerun-if-env-changed=TAURI_BUNDLE_IDENTIFIER");
     println!("cargo:rerun-if-env-changed=TAURI_ENABLE_LOGS");
+    println!("cargo:rerun-if-env-changed=TAURI_LOCALE");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_MAX_PER_HOST");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_CONNECT_TIMEOUT_MS");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_READ_TIMEOUT_MS");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_TOTAL_TIMEOUT_MS");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_MAX_RETRIES");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_MAX_REDIRECTS");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_SIGNED_ORIGINS");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_UNSIGNED_ORIGINS");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_CACHE_MAX_MB");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_FALLBACK_CHARSET");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_UPSTREAM");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_NO_PROXY");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_PAC_URL");
+    println!("cargo:rerun-if-env-changed=TAURI_PROXY_EXTRA_HEADERS");
+    println!("cargo:rerun-if-env-changed=TAURI_SIGNED_HEADERS");
+    println!("cargo:rerun-if-env-changed=TAURI_SIGN_CHALLENGE_PATH");
+    println!("cargo:rerun-if-env-changed=TAURI_SIGN_SESSION_PATH");
+    println!("cargo:rerun-if-env-changed=TAURI_SIGNATURE_VERSION");
+    println!("cargo:rerun-if-env-changed=TAURI_CLOCK_SKEW_WARN_SECS");
+    println!("cargo:rerun-if-env-changed=TAURI_TLS_CA_BUNDLE");
+    println!("cargo:rerun-if-env-changed=TAURI_TLS_PINS");
+    embed_ca_bundle();
     tauri_build::build()
 }
+
+/// 把 TAURI_TLS_CA_BUNDLE 指定的 PEM 文件（逗号分隔，相对 src-tauri）合并写入 OUT_DIR，
+/// 由 tls 模块 include_bytes! 嵌入程序；未设置时写入空文件
+fn embed_ca_bundle() {
+    let mut bundle = Vec::new();
+    for path in std::env::var("TAURI_TLS_CA_BUNDLE")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+    {
+        println!("cargo:rerun-if-changed={}", path);
+        let pem = std::fs::read(path)
+            .unwrap_or_else(|e| panic!("无法读取 TAURI_TLS_CA_BUNDLE 中的 {}: {}", path, e));
+        bundle.extend_from_slice(&pem);
+        bundle.push(b'\n');
+    }
+    let out_dir = std::env::var("OUT_DIR").expect("cargo sets OUT_DIR for build scripts");
+    std::fs::write(std::path::Path::new(&out_dir).join("tls_ca_bundle.pem"), bundle)
+        .expect("Failed to write CA bundle");
+}
@@ -0,0 +1,158 @@
+/// 代理缓存的内存索引
+/// 记录磁盘上每个条目的大小与最近访问时间，按总大小做 LRU 淘汰；
+/// 只维护索引本身，条目文件的读写与删除由 http_cache 负责
+use std::collections::HashMap;
+use std::path::Path;
+
+struct IndexEntry {
+    size: u64,
+    /// 最近访问时间，Unix 毫秒
+    last_access: i64,
+}
+
+#[derive(Default)]
+pub(crate) struct CacheIndex {
+    entries: HashMap<String, IndexEntry>,
+    total: u64,
+}
+
+impl CacheIndex {
+    /// 扫描缓存目录建立索引（以文件修改时间作为初始访问时间）
+    pub async fn load(dir: &Path) -> Self {
+        let mut index = Self::default();
+        if let Ok(mut entries) = tokio::fs::read_dir(dir).await {
+            while let Ok(Some(entry)) = entries.next_entry().await {
+                let path = entry.path();
+                if path.extension().and_then(|e| e.to_str()) != Some("body") {
+                    continue;
+                }
+                let (Some(key), Ok(metadata)) = (
+                    path.file_stem()
+                        .and_then(|s| s.to_str())
+                        .map(str::to_string),
+                    entry.metadata().await,
+                ) else {
+                    continue;
+                };
+                let last_access = metadata
+                    .modified()
+                    .ok()
+                    .map(|t| chrono::DateTime::<chrono::Utc>::from(t).timestamp_millis())
+                    .unwrap_or(0);
+                index.insert_at(key, metadata.len(), last_access);
+            }
+        }
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.keys()
+    }
+
+    /// 记录一次命中
+    pub fn touch(&mut self, key: &str) {
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.last_access = chrono::Utc::now().timestamp_millis();
+        }
+    }
+
+    /// 新增或替换条目
+    pub fn insert(&mut self, key: String, size: u64) {
+        self.insert_at(key, size, chrono::Utc::now().timestamp_millis());
+    }
+
+    fn insert_at(&mut self, key: String, size: u64, last_access: i64) {
+        if let Some(previous) = self.entries.insert(key, IndexEntry { size, last_access }) {
+            self.total -= previous.size;
+        }
+        self.total += size;
+    }
+
+    /// 移除条目，返回它的大小
+    pub fn remove(&mut self, key: &str) -> Option<u64> {
+        let entry = self.entries.remove(key)?;
+        self.total -= entry.size;
+        Some(entry.size)
+    }
+
+    /// 超出 max_bytes 时按最近访问时间从旧到新移除条目，返回被淘汰的键与大小（调用方删除文件）
+    pub fn evict(&mut self, max_bytes: u64) -> Vec<(String, u64)> {
+        if self.total <= max_bytes {
+            return Vec::new();
+        }
+
+        let mut by_access: Vec<(String, i64)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.last_access))
+            .collect();
+        by_access.sort_by_key(|(_, last_access)| *last_access);
+
+        let mut evicted = Vec::new();
+        for (key, _) in by_access {
+            if self.total <= max_bytes {
+                break;
+            }
+            if let Some(size) = self.remove(&key) {
+                evicted.push((key, size));
+            }
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evicted_keys(index: &mut CacheIndex, max_bytes: u64) -> Vec<String> {
+        index
+            .evict(max_bytes)
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut index = CacheIndex::default();
+        index.insert_at("a".to_string(), 40, 1);
+        index.insert_at("b".to_string(), 40, 2);
+        index.insert_at("c".to_string(), 40, 3);
+        assert_eq!(index.total(), 120);
+
+        // 未超出上限时不淘汰
+        assert!(index.evict(120).is_empty());
+
+        // a 最近被访问过，淘汰最久未访问的 b
+        index.touch("a");
+        assert_eq!(evicted_keys(&mut index, 100), vec!["b"]);
+        assert_eq!(index.total(), 80);
+
+        // 一次淘汰多个，直到回到上限以内
+        index.insert_at("d".to_string(), 50, 4);
+        assert_eq!(evicted_keys(&mut index, 60), vec!["c", "d"]);
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.total(), 40);
+    }
+
+    #[test]
+    fn test_replace_and_remove() {
+        let mut index = CacheIndex::default();
+        index.insert("a".to_string(), 10);
+        index.insert("a".to_string(), 25);
+        assert_eq!((index.len(), index.total()), (1, 25));
+
+        assert_eq!(index.remove("a"), Some(25));
+        assert_eq!(index.remove("a"), None);
+        assert_eq!(index.total(), 0);
+    }
+}
@@ -0,0 +1,224 @@
+/// HTTP 缓存语义
+/// 代理缓存（http_cache）用到的 Cache-Control / Expires / Vary 规则：
+/// 响应能否存储、新鲜期到何时、请求是否要求跳过新鲜副本，以及 Vary 请求头的记录与比对
+use crate::headers::HeaderList;
+use crate::proxy::ProxyRequest;
+
+/// 请求是否要求跳过新鲜副本、强制重新验证（Cache-Control: no-cache / max-age=0，Pragma: no-cache）
+pub(crate) fn forces_revalidation(request: &ProxyRequest) -> bool {
+    let cache_control = request.headers.get("cache-control").unwrap_or_default();
+    has_directive(cache_control, "no-cache")
+        || directive_value(cache_control, "max-age") == Some(0)
+        || request
+            .headers
+            .get("pragma")
+            .is_some_and(|v| has_directive(v, "no-cache"))
+}
+
+/// 响应是否允许写入私有缓存
+pub(crate) fn is_storable(status: u16, headers: &HeaderList) -> bool {
+    let cache_control = headers.get("cache-control").unwrap_or_default();
+    status == 200
+        && !has_directive(cache_control, "no-store")
+        && !headers.contains("set-cookie")
+        && !headers.get_all("vary").any(|vary| vary.trim() == "*")
+        && (fresh_until(headers).is_some()
+            || headers.contains("etag")
+            || headers.contains("last-modified"))
+}
+
+/// 按 Cache-Control: max-age（扣除 Age）或 Expires 计算新鲜期截止时间（Unix 秒）
+pub(crate) fn fresh_until(headers: &HeaderList) -> Option<i64> {
+    let now = chrono::Utc::now().timestamp();
+    let cache_control = headers
+        .get_all("cache-control")
+        .collect::<Vec<_>>()
+        .join(",");
+    let cache_control = cache_control.as_str();
+    if has_directive(cache_control, "no-cache") {
+        return None;
+    }
+
+    if let Some(max_age) = directive_value(cache_control, "max-age") {
+        let age = headers
+            .get("age")
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .unwrap_or(0);
+        return Some(now + max_age - age).filter(|until| *until > now);
+    }
+
+    let expires = headers.get("expires")?;
+    let expires = chrono::DateTime::parse_from_rfc2822(expires.trim()).ok()?;
+    // 以服务器 Date 为基准计算剩余时间，避免本地时钟偏差
+    let date = headers
+        .get("date")
+        .and_then(|d| chrono::DateTime::parse_from_rfc2822(d.trim()).ok())
+        .map(|d| d.timestamp())
+        .unwrap_or(now);
+    Some(now + (expires.timestamp() - date)).filter(|until| *until > now)
+}
+
+/// 响应 Vary 指定的请求头及其在本次请求中的取值，随条目一起存储
+pub(crate) fn vary_values(
+    response_headers: &HeaderList,
+    request_headers: &HeaderList,
+) -> Vec<(String, Option<String>)> {
+    response_headers
+        .get_all("vary")
+        .flat_map(|vary| vary.split(','))
+        .map(|name| name.trim().to_lowercase())
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            let value = request_headers.get(&name).map(str::to_string);
+            (name, value)
+        })
+        .collect()
+}
+
+/// 请求的 Vary 请求头是否与存储时一致（缺失的头只与缺失匹配）
+pub(crate) fn vary_matches(
+    vary: &[(String, Option<String>)],
+    request_headers: &HeaderList,
+) -> bool {
+    vary.iter()
+        .all(|(name, value)| request_headers.get(name).map(str::to_string) == *value)
+}
+
+pub(crate) fn has_directive(value: &str, directive: &str) -> bool {
+    value.split(',').any(|d| {
+        d.trim()
+            .split('=')
+            .next()
+            .unwrap_or("")
+            .eq_ignore_ascii_case(directive)
+    })
+}
+
+fn directive_value(value: &str, directive: &str) -> Option<i64> {
+    value.split(',').find_map(|d| {
+        let (name, value) = d.trim().split_once('=')?;
+        name.trim()
+            .eq_ignore_ascii_case(directive)
+            .then(|| value.trim().trim_matches('"').parse::<i64>().ok())?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> HeaderList {
+        pairs.iter().copied().collect()
+    }
+
+    fn http_date(offset_secs: i64) -> String {
+        (chrono::Utc::now() + chrono::Duration::seconds(offset_secs)).to_rfc2822()
+    }
+
+    fn remaining(headers: &HeaderList) -> Option<i64> {
+        fresh_until(headers).map(|until| until - chrono::Utc::now().timestamp())
+    }
+
+    #[test]
+    fn test_max_age_freshness() {
+        let fresh = remaining(&headers(&[("cache-control", "public, max-age=60")])).unwrap();
+        assert!((59..=60).contains(&fresh), "{}", fresh);
+
+        // 扣除上游缓存已经持有的时间
+        let aged = headers(&[("cache-control", "max-age=60"), ("age", "50")]);
+        assert!((9..=10).contains(&remaining(&aged).unwrap()));
+        let stale = headers(&[("cache-control", "max-age=60"), ("age", "60")]);
+        assert_eq!(fresh_until(&stale), None);
+
+        // 多个 Cache-Control 头合并；no-cache 总是需要重新验证
+        let split = headers(&[
+            ("cache-control", "private"),
+            ("cache-control", "max-age=30"),
+        ]);
+        assert!(remaining(&split).is_some());
+        assert_eq!(
+            fresh_until(&headers(&[("cache-control", "no-cache, max-age=60")])),
+            None
+        );
+        assert_eq!(
+            fresh_until(&headers(&[("cache-control", "max-age=\"0\"")])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_expires_freshness() {
+        // 按服务器 Date 计算剩余时间：本机时钟偏差不影响结果
+        let server_date = chrono::Utc::now() - chrono::Duration::hours(3);
+        let expires = server_date + chrono::Duration::seconds(120);
+        let skewed = headers(&[
+            ("date", &server_date.to_rfc2822()),
+            ("expires", &expires.to_rfc2822()),
+        ]);
+        assert!((119..=120).contains(&remaining(&skewed).unwrap()));
+
+        assert!(remaining(&headers(&[("expires", &http_date(30))])).is_some());
+        assert_eq!(fresh_until(&headers(&[("expires", &http_date(-30))])), None);
+        assert_eq!(fresh_until(&headers(&[("expires", "0")])), None);
+
+        // max-age 优先于 Expires
+        let both = headers(&[
+            ("cache-control", "max-age=5"),
+            ("expires", &http_date(3600)),
+        ]);
+        assert!(remaining(&both).unwrap() <= 5);
+    }
+
+    #[test]
+    fn test_storable() {
+        assert!(is_storable(
+            200,
+            &headers(&[("cache-control", "max-age=60")])
+        ));
+        assert!(is_storable(200, &headers(&[("etag", "\"v1\"")])));
+        assert!(!is_storable(200, &headers(&[])));
+        assert!(!is_storable(
+            404,
+            &headers(&[("cache-control", "max-age=60")])
+        ));
+        assert!(!is_storable(
+            200,
+            &headers(&[("cache-control", "no-store, max-age=60")])
+        ));
+        assert!(!is_storable(
+            200,
+            &headers(&[("cache-control", "max-age=60"), ("set-cookie", "a=1")])
+        ));
+        assert!(!is_storable(
+            200,
+            &headers(&[("etag", "\"v1\""), ("vary", "*")])
+        ));
+    }
+
+    #[test]
+    fn test_vary_matching() {
+        let response = headers(&[("vary", "Accept-Language, X-Tenant"), ("vary", "accept")]);
+        let request = headers(&[("Accept-Language", "zh-CN"), ("Accept", "application/json")]);
+        let vary = vary_values(&response, &request);
+        assert_eq!(
+            vary,
+            vec![
+                ("accept-language".to_string(), Some("zh-CN".to_string())),
+                ("x-tenant".to_string(), None),
+                ("accept".to_string(), Some("application/json".to_string())),
+            ]
+        );
+
+        assert!(vary_matches(&vary, &request));
+        let other_language = headers(&[("accept-language", "en"), ("accept", "application/json")]);
+        assert!(!vary_matches(&vary, &other_language));
+        // 存储时缺失的头，请求带上了也不匹配
+        let with_tenant = headers(&[
+            ("accept-language", "zh-CN"),
+            ("accept", "application/json"),
+            ("x-tenant", "t1"),
+        ]);
+        assert!(!vary_matches(&vary, &with_tenant));
+        assert!(vary_matches(&[], &with_tenant));
+    }
+}
@@ -0,0 +1,114 @@
+/// 请求取消模块
+/// 每个带 id 的代理请求登记一个 CancellationToken，
+/// 前端 AbortController 触发时调用 cancel_proxy_request 中断底层 reqwest future，
+/// 被中断的命令返回 code 为 CANCELLED 的错误，前端据此抛出 DOMException('AbortError')
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::State;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::AppError;
+use crate::proxy::AppState;
+
+#[derive(Clone, Default)]
+pub struct InflightRequests {
+    // id -> (登记序号, token)；序号用于注销时确认是同一次登记
+    tokens: Arc<Mutex<HashMap<String, (u64, CancellationToken)>>>,
+    next_generation: Arc<AtomicU64>,
+}
+
+impl InflightRequests {
+    /// 登记请求；返回的 guard 被 drop 时自动注销
+    pub fn register(&self, id: &str) -> InflightGuard {
+        let token = CancellationToken::new();
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut tokens) = self.tokens.lock() {
+            // 同一 id 重复登记时，旧请求视为被新请求取代
+            if let Some((_, previous)) = tokens.insert(id.to_string(), (generation, token.clone()))
+            {
+                previous.cancel();
+            }
+        }
+        InflightGuard {
+            id: id.to_string(),
+            generation,
+            token,
+            registry: self.clone(),
+        }
+    }
+
+    pub fn cancel(&self, id: &str) -> bool {
+        let token = match self.tokens.lock() {
+            Ok(mut tokens) => tokens.remove(id),
+            Err(_) => None,
+        };
+        match token {
+            Some((_, token)) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn unregister(&self, id: &str, generation: u64) {
+        if let Ok(mut tokens) = self.tokens.lock() {
+            // 只移除自己登记的 token，避免误删同 id 的新请求
+            if tokens.get(id).map(|(current, _)| *current) == Some(generation) {
+                tokens.remove(id);
+            }
+        }
+    }
+}
+
+pub struct InflightGuard {
+    id: String,
+    generation: u64,
+    token: CancellationToken,
+    registry: InflightRequests,
+}
+
+impl InflightGuard {
+    pub fn token(&self) -> CancellationToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.registry.unregister(&self.id, self.generation);
+    }
+}
+
+/// 在取消信号与实际 future 之间竞争，取消时返回 AppError::Cancelled
+pub async fn run_cancellable<T, F>(token: Option<CancellationToken>, fut: F) -> Result<T, AppError>
+where
+    F: Future<Output = Result<T, AppError>>,
+{
+    match token {
+        Some(token) => tokio::select! {
+            _ = token.cancelled() => Err(AppError::Cancelled),
+            result = fut => result,
+        },
+        None => fut.await,
+    }
+}
+
+/// 等待取消信号；没有 token 的请求永远不会被取消
+pub async fn cancelled(token: Option<&CancellationToken>) {
+    match token {
+        Some(token) => token.cancelled().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// 取消一个进行中的代理请求；请求已结束时返回 false
+#[tauri::command]
+pub fn cancel_proxy_request(
+    id: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, AppError> {
+    Ok(state.inflight.cancel(&id))
+}
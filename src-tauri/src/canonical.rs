@@ -0,0 +1,379 @@
+/// 规范请求（canonical request）
+/// 签名不再只覆盖路径：方法、规范化路径、排序后的查询参数、选定请求头与请求体哈希
+/// 按固定格式拼成规范请求，其 SHA-256 进入 RSA 签名数据，换方法、改查询参数或请求体都会导致验签失败。
+/// 格式与测试向量见 docs/CANONICAL_REQUEST.md 和 docs/signing-test-vectors.json（Java 验签端共用）：
+///
+/// ```text
+/// METHOD\n
+/// 规范路径\n
+/// 规范查询串\n
+/// 规范请求头（每行 name:value\n）\n
+/// 签名头列表（name;name）\n
+/// 请求体 SHA-256（十六进制）
+/// ```
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::multipart::hash_source;
+use crate::proxy::ProxyRequest;
+use crate::upload::{FormPart, UploadStore};
+
+/// 默认参与签名的请求头；TAURI_SIGNED_HEADERS 可覆盖（逗号分隔）
+const DEFAULT_SIGNED_HEADERS: &str = "authorization,content-type";
+
+/// 空请求体的 SHA-256
+pub const EMPTY_BODY_HASH: &str =
+    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+/// 参与签名的请求头名称（小写、去重）
+pub fn signed_header_names() -> Vec<String> {
+    let mut names: Vec<String> = option_env!("TAURI_SIGNED_HEADERS")
+        .unwrap_or(DEFAULT_SIGNED_HEADERS)
+        .split(',')
+        .map(|name| name.trim().to_ascii_lowercase())
+        .filter(|name| !name.is_empty())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// 一次请求的规范形式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CanonicalRequest {
+    pub method: String,
+    pub path: String,
+    pub query: String,
+    /// 实际参与签名的请求头（按名称排序）
+    pub headers: Vec<(String, String)>,
+    pub body_hash: String,
+}
+
+impl CanonicalRequest {
+    /// headers 为实际发出的请求头（页面头在前、签名链追加的在后）；
+    /// names 中请求里不存在的头不参与签名，也不出现在签名头列表里
+    pub fn new(
+        method: &str,
+        url: &str,
+        headers: &HeaderList,
+        names: &[String],
+        body_hash: &str,
+    ) -> Result<Self, AppError> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| AppError::InvalidRequest(format!("无效的 URL {}: {}", url, e)))?;
+
+        let mut selected: Vec<(String, String)> = names
+            .iter()
+            .filter_map(|name| {
+                let values: Vec<String> =
+                    headers.get_all(name).map(normalize_header_value).collect();
+                (!values.is_empty()).then(|| (name.clone(), values.join(",")))
+            })
+            .collect();
+        selected.sort();
+
+        Ok(Self {
+            method: method.to_ascii_uppercase(),
+            path: canonical_path(parsed.path()),
+            query: canonical_query(&parsed),
+            headers: selected,
+            body_hash: body_hash.to_string(),
+        })
+    }
+
+    /// 签名头列表，同时作为 X-Signed-Headers 发给后端
+    pub fn signed_headers(&self) -> String {
+        self.headers
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+
+    /// 规范请求文本
+    pub fn to_canonical_string(&self) -> String {
+        let mut out = String::new();
+        out.push_str(&self.method);
+        out.push('\n');
+        out.push_str(&self.path);
+        out.push('\n');
+        out.push_str(&self.query);
+        out.push('\n');
+        for (name, value) in &self.headers {
+            out.push_str(name);
+            out.push(':');
+            out.push_str(value);
+            out.push('\n');
+        }
+        out.push('\n');
+        out.push_str(&self.signed_headers());
+        out.push('\n');
+        out.push_str(&self.body_hash);
+        out
+    }
+
+    /// 规范请求的 SHA-256（十六进制），进入签名数据
+    pub fn hash(&self) -> String {
+        sha256_hex(self.to_canonical_string().as_bytes())
+    }
+}
+
+/// 后端看到的路径：去掉 Nginx 剥离的 /base_api 前缀，逐段百分号解码后按 RFC 3986 重新编码
+pub fn canonical_path(path: &str) -> String {
+    let path = match path.find("/base_api/") {
+        Some(idx) => &path[idx + "/base_api".len()..],
+        None if path.ends_with("/base_api") => "/",
+        None => path,
+    };
+    let encoded: Vec<String> = path
+        .split('/')
+        .map(|segment| {
+            urlencoding::encode_binary(&urlencoding::decode_binary(segment.as_bytes())).into_owned()
+        })
+        .collect();
+    let joined = encoded.join("/");
+    if joined.starts_with('/') {
+        joined
+    } else {
+        format!("/{}", joined)
+    }
+}
+
+/// 查询参数按表单规则解码（+ 视为空格），重新编码后按名称、值排序
+pub fn canonical_query(url: &reqwest::Url) -> String {
+    let mut pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .map(|(name, value)| {
+            (
+                urlencoding::encode(&name).into_owned(),
+                urlencoding::encode(&value).into_owned(),
+            )
+        })
+        .collect();
+    pairs.sort();
+    pairs
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// 去掉首尾空白，内部连续空白压缩为一个空格
+fn normalize_header_value(value: &str) -> String {
+    value.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// multipart 表单的哈希：边界由客户端随机生成，因此按表单项而不是原始字节计算。
+/// 每项一行：text <name> <sha256(value)> 或 file <name> <file_name> <sha256(内容)>，
+/// 名称按 RFC 3986 编码，所有行拼接后再取 SHA-256
+pub fn multipart_hash(parts: &[(MultipartEntry, String)]) -> String {
+    let mut hasher = Sha256::new();
+    for (entry, content_hash) in parts {
+        let line = match entry {
+            MultipartEntry::Text { name } => {
+                format!("text {} {}\n", urlencoding::encode(name), content_hash)
+            }
+            MultipartEntry::File { name, file_name } => format!(
+                "file {} {} {}\n",
+                urlencoding::encode(name),
+                urlencoding::encode(file_name),
+                content_hash
+            ),
+        };
+        hasher.update(line.as_bytes());
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// 参与 multipart 哈希的表单项描述
+#[derive(Debug, Clone)]
+pub enum MultipartEntry {
+    Text { name: String },
+    File { name: String, file_name: String },
+}
+
+/// 请求体哈希：文本请求体按 UTF-8 字节计算，multipart 见 multipart_hash，无请求体为空串的哈希
+pub async fn body_hash(uploads: &UploadStore, request: &ProxyRequest) -> Result<String, AppError> {
+    if let Some(parts) = request.multipart_parts() {
+        let mut hashed = Vec::with_capacity(parts.len());
+        for part in parts.iter() {
+            hashed.push(match part {
+                FormPart::Text { name, value } => (
+                    MultipartEntry::Text { name: name.clone() },
+                    sha256_hex(value.as_bytes()),
+                ),
+                FormPart::File {
+                    name,
+                    file_name,
+                    source,
+                    ..
+                } => (
+                    MultipartEntry::File {
+                        name: name.clone(),
+                        file_name: file_name.clone(),
+                    },
+                    hash_source(
+                        uploads,
+                        request.webview.as_deref().unwrap_or_default(),
+                        source,
+                    )
+                    .await?,
+                ),
+            });
+        }
+        return Ok(multipart_hash(&hashed));
+    }
+    Ok(match &request.body {
+        Some(body) => sha256_hex(body.as_bytes()),
+        None => EMPTY_BODY_HASH.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// 与 Java 验签端共用的测试向量
+    const VECTORS: &str = include_str!("../../docs/signing-test-vectors.json");
+
+    #[derive(Deserialize)]
+    struct Vector {
+        name: String,
+        method: String,
+        url: String,
+        headers: HeaderList,
+        signed_headers: Vec<String>,
+        body: VectorBody,
+        body_hash: String,
+        canonical_request: String,
+        canonical_hash: String,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(tag = "type", rename_all = "snake_case")]
+    enum VectorBody {
+        None,
+        Text { text: String },
+        Multipart { parts: Vec<VectorPart> },
+    }
+
+    #[derive(Deserialize)]
+    #[serde(tag = "kind", rename_all = "snake_case")]
+    enum VectorPart {
+        Text {
+            name: String,
+            value: String,
+        },
+        File {
+            name: String,
+            file_name: String,
+            content: String,
+        },
+    }
+
+    impl VectorBody {
+        fn hash(&self) -> String {
+            match self {
+                VectorBody::None => EMPTY_BODY_HASH.to_string(),
+                VectorBody::Text { text } => sha256_hex(text.as_bytes()),
+                VectorBody::Multipart { parts } => multipart_hash(
+                    &parts
+                        .iter()
+                        .map(|part| match part {
+                            VectorPart::Text { name, value } => (
+                                MultipartEntry::Text { name: name.clone() },
+                                sha256_hex(value.as_bytes()),
+                            ),
+                            VectorPart::File {
+                                name,
+                                file_name,
+                                content,
+                            } => (
+                                MultipartEntry::File {
+                                    name: name.clone(),
+                                    file_name: file_name.clone(),
+                                },
+                                sha256_hex(content.as_bytes()),
+                            ),
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_shared_vectors() {
+        let vectors: Vec<Vector> = serde_json::from_str(VECTORS).unwrap();
+        assert!(!vectors.is_empty());
+        for vector in vectors {
+            let body_hash = vector.body.hash();
+            assert_eq!(body_hash, vector.body_hash, "{}: 请求体哈希", vector.name);
+
+            let canonical = CanonicalRequest::new(
+                &vector.method,
+                &vector.url,
+                &vector.headers,
+                &vector.signed_headers,
+                &body_hash,
+            )
+            .unwrap();
+            assert_eq!(
+                canonical.to_canonical_string(),
+                vector.canonical_request,
+                "{}: 规范请求",
+                vector.name
+            );
+            assert_eq!(
+                canonical.hash(),
+                vector.canonical_hash,
+                "{}: 规范请求哈希",
+                vector.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_body_hash() {
+        assert_eq!(sha256_hex(b""), EMPTY_BODY_HASH);
+    }
+
+    #[test]
+    fn test_method_query_and_body_change_hash() {
+        let headers = HeaderList::default();
+        let names = signed_header_names();
+        let base = CanonicalRequest::new(
+            "GET",
+            "https://a.test/base_api/x?a=1",
+            &headers,
+            &names,
+            EMPTY_BODY_HASH,
+        )
+        .unwrap()
+        .hash();
+        for (method, url, body) in [
+            (
+                "POST",
+                "https://a.test/base_api/x?a=1",
+                EMPTY_BODY_HASH.to_string(),
+            ),
+            (
+                "GET",
+                "https://a.test/base_api/x?a=2",
+                EMPTY_BODY_HASH.to_string(),
+            ),
+            ("GET", "https://a.test/base_api/x?a=1", sha256_hex(b"{}")),
+        ] {
+            let other = CanonicalRequest::new(method, url, &headers, &names, &body)
+                .unwrap()
+                .hash();
+            assert_ne!(base, other, "{} {}", method, url);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+/// 服务端挑战（challenge/response）
+/// 签名只靠本机时钟的时间戳和随机 nonce 证明新鲜度；配置 TAURI_SIGN_CHALLENGE_PATH 后，
+/// 客户端先向后端领取一个短期有效的挑战值，之后签名来源的请求都带上 X-Challenge（参与规范请求签名），
+/// 后端可据此确认签名是在挑战签发之后生成的。
+/// - 挑战接口：GET <TAURI_ENV_URL 的来源><TAURI_SIGN_CHALLENGE_PATH>，本身按普通签名请求发送，
+///   返回 {"challenge": "...", "expires_in": 秒}，或在响应头 X-Challenge / X-Challenge-Expires-In 中给出
+/// - 后端返回 428 或带 X-Challenge-Required 头时视为挑战失效：丢弃当前挑战
+///   （响应头里带了新挑战则直接采用），重新领取后自动重发一次
+use serde::Deserialize;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::proxy::AppState;
+use crate::signing::SigningRequest;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 携带挑战值的请求头（也是后端下发新挑战时使用的响应头）
+pub const CHALLENGE_HEADER: &str = "X-Challenge";
+
+/// 后端要求重新领取挑战时返回的响应头
+const CHALLENGE_REQUIRED_HEADER: &str = "x-challenge-required";
+
+/// 挑战有效期的响应头（秒）
+const CHALLENGE_EXPIRES_HEADER: &str = "x-challenge-expires-in";
+
+/// 后端要求重新领取挑战时返回的状态码（428 Precondition Required）
+const CHALLENGE_REQUIRED_STATUS: u16 = 428;
+
+/// 后端未给出有效期时的默认值
+const DEFAULT_TTL_SECS: u64 = 60;
+
+/// 提前视为过期的余量，避免请求在途中挑战过期
+const EXPIRY_MARGIN: Duration = Duration::from_secs(5);
+
+#[derive(Deserialize)]
+struct ChallengeResponse {
+    challenge: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+struct Challenge {
+    value: String,
+    expires_at: Instant,
+}
+
+struct Inner {
+    /// 挑战接口地址；未配置时整个机制关闭
+    endpoint: Option<String>,
+    current: RwLock<Option<Challenge>>,
+    /// 同一时刻只领取一次，其余请求等待结果
+    refresh: tokio::sync::Mutex<()>,
+}
+
+/// 当前挑战；签名链与发送逻辑共用
+#[derive(Clone)]
+pub struct ChallengeStore(Arc<Inner>);
+
+impl ChallengeStore {
+    pub fn from_env(env_url: &str) -> Self {
+        let endpoint = option_env!("TAURI_SIGN_CHALLENGE_PATH")
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .and_then(|path| reqwest::Url::parse(env_url).ok()?.join(path).ok())
+            .map(|url| url.to_string());
+        if let Some(endpoint) = &endpoint {
+            log!("🎫 [CHALLENGE] 挑战接口: {}", endpoint);
+        }
+        Self::new(endpoint)
+    }
+
+    fn new(endpoint: Option<String>) -> Self {
+        Self(Arc::new(Inner {
+            endpoint,
+            current: RwLock::new(None),
+            refresh: tokio::sync::Mutex::new(()),
+        }))
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.0.endpoint.is_some()
+    }
+
+    /// 未过期的挑战值
+    pub fn current(&self) -> Option<String> {
+        let current = self.0.current.read().ok()?;
+        current
+            .as_ref()
+            .filter(|challenge| Instant::now() + EXPIRY_MARGIN < challenge.expires_at)
+            .map(|challenge| challenge.value.clone())
+    }
+
+    fn accept(&self, value: String, ttl_secs: Option<u64>) {
+        let ttl = Duration::from_secs(ttl_secs.unwrap_or(DEFAULT_TTL_SECS));
+        log!("🎫 [CHALLENGE] 已更新，有效期 {} 秒", ttl.as_secs());
+        if let Ok(mut current) = self.0.current.write() {
+            *current = Some(Challenge {
+                value,
+                expires_at: Instant::now() + ttl,
+            });
+        }
+    }
+
+    fn invalidate(&self) {
+        if let Ok(mut current) = self.0.current.write() {
+            *current = None;
+        }
+    }
+
+    /// 是否是要求重新领取挑战的响应
+    pub fn requires_rechallenge(&self, status: u16, headers: &reqwest::header::HeaderMap) -> bool {
+        self.enabled()
+            && (status == CHALLENGE_REQUIRED_STATUS
+                || headers.contains_key(CHALLENGE_REQUIRED_HEADER))
+    }
+
+    /// 处理挑战失效的响应：响应头带了新挑战则直接采用，否则丢弃当前挑战，下次签名前重新领取
+    pub fn on_rechallenge(&self, headers: &reqwest::header::HeaderMap) {
+        log!("🎫 [CHALLENGE] 后端要求重新挑战");
+        match challenge_from_headers(headers) {
+            Some((value, ttl)) => self.accept(value, ttl),
+            None => self.invalidate(),
+        }
+    }
+
+    /// 确保有可用的挑战；未启用或仍在有效期内时直接返回
+    pub async fn ensure(&self, state: &AppState) -> Result<(), AppError> {
+        let Some(endpoint) = self.0.endpoint.as_deref() else {
+            return Ok(());
+        };
+        // 签名数据不覆盖挑战值时不发送挑战（见 device_signature），也就不必领取
+        if self.current().is_some() || !state.schemes.active().covers_request() {
+            return Ok(());
+        }
+        let _refreshing = self.0.refresh.lock().await;
+        // 等锁期间其他请求可能已经领到
+        if self.current().is_some() {
+            return Ok(());
+        }
+
+        log!("🎫 [CHALLENGE] 领取挑战: {}", endpoint);
+        let headers = HeaderList::default();
+        let signed_headers = state.signers.sign(&SigningRequest {
+            id: None,
+            method: "GET",
+            url: endpoint,
+            headers: &headers,
+            body_hash: crate::canonical::EMPTY_BODY_HASH,
+            signed: true,
+        })?;
+        // 整个请求（含读取响应体）共用总超时
+        let deadline = tokio::time::Instant::now() + state.policy.effective(None).total;
+        state.http.prepare_proxy(endpoint).await;
+        let send = signed_headers
+            .apply_to(state.http.client().get(endpoint))
+            .send();
+        let sent_at = chrono::Utc::now();
+        let response = tokio::time::timeout_at(deadline, send)
+            .await
+            .map_err(|_| AppError::Timeout(format!("领取挑战超时: {}", endpoint)))??;
+        state
+            .clock
+            .observe(response.headers(), sent_at, chrono::Utc::now());
+
+        let status = response.status().as_u16();
+        if !response.status().is_success() {
+            return Err(AppError::Challenge(format!("挑战接口返回 {}", status)));
+        }
+        let from_headers = challenge_from_headers(response.headers());
+        let (value, ttl) = match from_headers {
+            Some(challenge) => challenge,
+            None => {
+                let body: ChallengeResponse = tokio::time::timeout_at(deadline, response.json())
+                    .await
+                    .map_err(|_| AppError::Timeout(format!("领取挑战超时: {}", endpoint)))?
+                    .map_err(|e| AppError::Challenge(format!("挑战响应无法解析: {}", e)))?;
+                (body.challenge, body.expires_in)
+            }
+        };
+        if value.trim().is_empty() {
+            return Err(AppError::Challenge("挑战接口返回空值".to_string()));
+        }
+        self.accept(value, ttl);
+        Ok(())
+    }
+}
+
+fn challenge_from_headers(headers: &reqwest::header::HeaderMap) -> Option<(String, Option<u64>)> {
+    let value = headers
+        .get(CHALLENGE_HEADER)?
+        .to_str()
+        .ok()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())?
+        .to_string();
+    let ttl = headers
+        .get(CHALLENGE_EXPIRES_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse().ok());
+    Some((value, ttl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    fn enabled() -> ChallengeStore {
+        ChallengeStore::new(Some("https://example.com/auth/challenge".to_string()))
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_challenge_from_headers() {
+        assert_eq!(
+            challenge_from_headers(&headers(&[
+                ("x-challenge", " c-1 "),
+                ("x-challenge-expires-in", "30")
+            ])),
+            Some(("c-1".to_string(), Some(30)))
+        );
+        // 有效期缺失或无法解析时交给默认值
+        assert_eq!(
+            challenge_from_headers(&headers(&[
+                ("x-challenge", "c-2"),
+                ("x-challenge-expires-in", "soon")
+            ])),
+            Some(("c-2".to_string(), None))
+        );
+        assert_eq!(
+            challenge_from_headers(&headers(&[("x-challenge", "  ")])),
+            None
+        );
+        assert_eq!(challenge_from_headers(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn test_expiry_margin() {
+        let store = enabled();
+        assert_eq!(store.current(), None);
+
+        // 剩余有效期不超过 EXPIRY_MARGIN 时视为已过期，避免请求在途中过期
+        store.accept("short".to_string(), Some(EXPIRY_MARGIN.as_secs()));
+        assert_eq!(store.current(), None);
+
+        store.accept("long".to_string(), Some(EXPIRY_MARGIN.as_secs() + 30));
+        assert_eq!(store.current().as_deref(), Some("long"));
+
+        // 未给出有效期时使用默认值
+        store.accept("default".to_string(), None);
+        assert_eq!(store.current().as_deref(), Some("default"));
+    }
+
+    #[test]
+    fn test_requires_rechallenge() {
+        let store = enabled();
+        assert!(store.requires_rechallenge(428, &HeaderMap::new()));
+        assert!(store.requires_rechallenge(401, &headers(&[("x-challenge-required", "1")])));
+        assert!(!store.requires_rechallenge(401, &HeaderMap::new()));
+        assert!(!store.requires_rechallenge(200, &headers(&[("x-challenge", "c-1")])));
+
+        // 未配置挑战接口时不处理
+        let disabled = ChallengeStore::new(None);
+        assert!(!disabled.requires_rechallenge(428, &headers(&[("x-challenge-required", "1")])));
+    }
+
+    #[test]
+    fn test_on_rechallenge() {
+        let store = enabled();
+        store.accept("old".to_string(), Some(60));
+
+        // 响应头带了新挑战则直接采用
+        store.on_rechallenge(&headers(&[
+            ("x-challenge", "new"),
+            ("x-challenge-expires-in", "60"),
+        ]));
+        assert_eq!(store.current().as_deref(), Some("new"));
+
+        // 否则丢弃当前挑战，下次签名前重新领取
+        store.on_rechallenge(&HeaderMap::new());
+        assert_eq!(store.current(), None);
+    }
+}
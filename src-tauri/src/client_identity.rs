@@ -0,0 +1,256 @@
+/// 客户端证书（mTLS）
+/// 生产环境除 X-Client-Signature 外还要求设备绑定的客户端证书。来源有三种：
+/// - 导入 PKCS#12（.p12/.pfx，经上传登记读取，不接受本地路径）
+/// - 导入 PEM 证书链 + 私钥
+/// - 设备上生成密钥和 CSR（create_client_csr），由 CA 签发后只导入证书，私钥从不离开设备
+///
+/// 这里负责解析与校验；存储与命令见 identity_store。
+/// 生效后代理、下载和 WebSocket 连接都会出示该证书（见 http_client / tls）
+use base64::{engine::general_purpose, Engine as _};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::EncodePrivateKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use x509_cert::der::{Decode, Encode};
+
+use crate::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdentityOrigin {
+    Pkcs12,
+    Pem,
+    /// 设备上生成的密钥 + CA 签发的证书
+    Enrollment,
+}
+
+/// 客户端身份：证书链（DER，叶子证书在前）与 PKCS#8 私钥（DER）
+/// 序列化时省略私钥（私钥由 identity_store 存入系统钥匙串）；读取旧文件时仍接受私钥字段
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ClientIdentity {
+    chain: Vec<Vec<u8>>,
+    #[serde(default, skip_serializing)]
+    key: Vec<u8>,
+    origin: IdentityOrigin,
+}
+
+impl ClientIdentity {
+    /// 校验证书与私钥匹配
+    fn new(chain: Vec<Vec<u8>>, key: Vec<u8>, origin: IdentityOrigin) -> Result<Self, AppError> {
+        let leaf = chain
+            .first()
+            .ok_or_else(|| AppError::InvalidRequest("没有找到证书".to_string()))?;
+        let cert = parse_certificate(leaf)?;
+        let key_pair = rcgen::KeyPair::from_der(&key)
+            .map_err(|e| AppError::InvalidRequest(format!("不支持的私钥: {}", e)))?;
+        let spki = cert
+            .tbs_certificate
+            .subject_public_key_info
+            .to_der()
+            .map_err(|e| AppError::InvalidRequest(format!("无效的证书: {}", e)))?;
+        if key_pair.public_key_der() != spki {
+            return Err(AppError::InvalidRequest("证书与私钥不匹配".to_string()));
+        }
+        Ok(Self { chain, key, origin })
+    }
+
+    /// 换上从钥匙串取回的私钥，重新校验
+    pub fn with_key(self, key: Vec<u8>) -> Result<Self, AppError> {
+        Self::new(self.chain, key, self.origin)
+    }
+
+    pub fn origin(&self) -> IdentityOrigin {
+        self.origin
+    }
+
+    pub fn chain(&self) -> &[Vec<u8>] {
+        &self.chain
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    /// native-tls 需要的 PEM 格式：(证书链, PKCS#8 私钥)
+    pub fn to_pem(&self) -> (String, String) {
+        let chain = self
+            .chain
+            .iter()
+            .map(|der| pem_encode("CERTIFICATE", der))
+            .collect::<String>();
+        (chain, pem_encode("PRIVATE KEY", &self.key))
+    }
+
+    pub fn info(&self) -> Option<ClientIdentityInfo> {
+        let leaf = self.chain.first()?;
+        let cert = parse_certificate(leaf).ok()?;
+        let tbs = &cert.tbs_certificate;
+        let not_before = tbs.validity.not_before.to_unix_duration().as_secs() as i64;
+        let not_after = tbs.validity.not_after.to_unix_duration().as_secs() as i64;
+        let now = chrono::Utc::now().timestamp();
+        Some(ClientIdentityInfo {
+            subject: tbs.subject.to_string(),
+            issuer: tbs.issuer.to_string(),
+            serial_number: hex::encode(tbs.serial_number.as_bytes()),
+            fingerprint: hex::encode(Sha256::digest(leaf)),
+            not_before,
+            not_after,
+            days_remaining: (not_after - now).div_euclid(86_400),
+            expired: now >= not_after,
+            origin: self.origin,
+        })
+    }
+}
+
+/// 前端可见的证书信息（不含私钥）
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientIdentityInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub serial_number: String,
+    /// 叶子证书的 SHA-256 指纹（hex）
+    pub fingerprint: String,
+    /// Unix 秒
+    pub not_before: i64,
+    pub not_after: i64,
+    /// 距过期的天数，已过期为负数
+    pub days_remaining: i64,
+    pub expired: bool,
+    pub origin: IdentityOrigin,
+}
+
+fn parse_certificate(der: &[u8]) -> Result<x509_cert::Certificate, AppError> {
+    x509_cert::Certificate::from_der(der)
+        .map_err(|e| AppError::InvalidRequest(format!("无效的证书: {}", e)))
+}
+
+fn pem_encode(label: &str, der: &[u8]) -> String {
+    let encoded = general_purpose::STANDARD.encode(der);
+    let mut pem = format!("-----BEGIN {}-----\n", label);
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(&String::from_utf8_lossy(line));
+        pem.push('\n');
+    }
+    pem.push_str(&format!("-----END {}-----\n", label));
+    pem
+}
+
+pub(crate) fn from_pkcs12(data: &[u8], password: &str) -> Result<ClientIdentity, AppError> {
+    let keystore = p12_keystore::KeyStore::from_pkcs12(data, password)
+        .map_err(|e| AppError::InvalidRequest(format!("无法读取 PKCS#12（密码错误或格式不支持）: {}", e)))?;
+    let (_, chain) = keystore
+        .private_key_chain()
+        .ok_or_else(|| AppError::InvalidRequest("PKCS#12 中没有私钥".to_string()))?;
+    let certs = chain.chain().iter().map(|cert| cert.as_der().to_vec()).collect();
+    ClientIdentity::new(certs, chain.key().to_vec(), IdentityOrigin::Pkcs12)
+}
+
+fn rsa_to_pkcs8(der: &[u8]) -> Result<Vec<u8>, AppError> {
+    let key = rsa::RsaPrivateKey::from_pkcs1_der(der)
+        .map_err(|e| AppError::InvalidRequest(format!("无效的 RSA 私钥: {}", e)))?;
+    let pkcs8 = key
+        .to_pkcs8_der()
+        .map_err(|e| AppError::Internal(format!("转换 RSA 私钥失败: {}", e)))?;
+    Ok(pkcs8.as_bytes().to_vec())
+}
+
+/// 解析 PEM 证书链；私钥支持 PKCS#8 与 PKCS#1（RSA），统一转为 PKCS#8
+pub(crate) fn from_pem(certificate: &str, private_key: Option<&str>, pending_key: Option<&[u8]>) -> Result<ClientIdentity, AppError> {
+    let certs = rustls_pemfile::certs(&mut certificate.as_bytes())
+        .map_err(|e| AppError::InvalidRequest(format!("无效的 PEM 证书: {}", e)))?;
+
+    let Some(private_key) = private_key.filter(|key| !key.trim().is_empty()) else {
+        let key = pending_key.ok_or_else(|| {
+            AppError::InvalidRequest("缺少私钥，且没有待完成的证书申请".to_string())
+        })?;
+        return ClientIdentity::new(certs, key.to_vec(), IdentityOrigin::Enrollment);
+    };
+
+    let key = rustls_pemfile::read_all(&mut private_key.as_bytes())
+        .map_err(|e| AppError::InvalidRequest(format!("无效的 PEM 私钥: {}", e)))?
+        .into_iter()
+        .find_map(|item| match item {
+            rustls_pemfile::Item::PKCS8Key(der) => Some(Ok(der)),
+            rustls_pemfile::Item::RSAKey(der) => Some(rsa_to_pkcs8(&der)),
+            rustls_pemfile::Item::ECKey(_) => Some(Err(AppError::InvalidRequest(
+                "请将 EC 私钥转换为 PKCS#8（openssl pkcs8 -topk8 -nocrypt）".to_string(),
+            ))),
+            _ => None,
+        })
+        .ok_or_else(|| AppError::InvalidRequest("没有找到私钥".to_string()))??;
+    ClientIdentity::new(certs, key, IdentityOrigin::Pem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs1::EncodeRsaPrivateKey;
+
+    /// 自签名证书（PEM）与对应的 rcgen 证书；not_after 决定有效期
+    fn self_signed(key_pair: Option<rcgen::KeyPair>, not_after: (i32, u8, u8)) -> (String, rcgen::Certificate) {
+        let mut params = rcgen::CertificateParams::new(vec!["client.test".to_string()]);
+        if let Some(key_pair) = key_pair {
+            params.alg = &rcgen::PKCS_RSA_SHA256;
+            params.key_pair = Some(key_pair);
+        }
+        params.not_before = rcgen::date_time_ymd(2000, 1, 1);
+        params.not_after = rcgen::date_time_ymd(not_after.0, not_after.1, not_after.2);
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        (cert.serialize_pem().unwrap(), cert)
+    }
+
+    #[test]
+    fn test_from_pem_pkcs8_and_pkcs1() {
+        let (pem, cert) = self_signed(None, (2099, 1, 1));
+        let identity = from_pem(&pem, Some(&cert.serialize_private_key_pem()), None).unwrap();
+        assert_eq!(identity.origin(), IdentityOrigin::Pem);
+        assert_eq!(identity.key(), cert.serialize_private_key_der());
+
+        // RSA PKCS#1（BEGIN RSA PRIVATE KEY）统一转为 PKCS#8
+        let rsa_key = rsa::RsaPrivateKey::new(&mut rand::thread_rng(), 2048).unwrap();
+        let pkcs8 = rsa_key.to_pkcs8_der().unwrap().as_bytes().to_vec();
+        let (pem, _) = self_signed(Some(rcgen::KeyPair::from_der(&pkcs8).unwrap()), (2099, 1, 1));
+        let pkcs1 = rsa_key.to_pkcs1_pem(rsa::pkcs8::LineEnding::LF).unwrap();
+        let identity = from_pem(&pem, Some(&pkcs1), None).unwrap();
+        assert_eq!(identity.key(), pkcs8);
+    }
+
+    #[test]
+    fn test_key_mismatch_and_pending_key() {
+        let (pem, cert) = self_signed(None, (2099, 1, 1));
+        let (_, other) = self_signed(None, (2099, 1, 1));
+        let result = from_pem(&pem, Some(&other.serialize_private_key_pem()), None);
+        assert!(matches!(result, Err(AppError::InvalidRequest(msg)) if msg.contains("不匹配")));
+
+        // 省略私钥时使用证书申请的私钥，同样校验匹配
+        let identity = from_pem(&pem, None, Some(&cert.serialize_private_key_der())).unwrap();
+        assert_eq!(identity.origin(), IdentityOrigin::Enrollment);
+        assert!(from_pem(&pem, None, Some(&other.serialize_private_key_der())).is_err());
+        assert!(from_pem(&pem, None, None).is_err());
+
+        // 从钥匙串取回的私钥同样校验
+        assert!(identity.clone().with_key(other.serialize_private_key_der()).is_err());
+        assert!(identity.with_key(cert.serialize_private_key_der()).is_ok());
+    }
+
+    #[test]
+    fn test_info_expiry() {
+        let (pem, cert) = self_signed(None, (2099, 1, 1));
+        let info = from_pem(&pem, Some(&cert.serialize_private_key_pem()), None)
+            .unwrap()
+            .info()
+            .unwrap();
+        assert!(!info.expired);
+        assert!(info.days_remaining > 365);
+        assert_eq!(info.not_before, 946_684_800);
+
+        let (pem, cert) = self_signed(None, (2001, 1, 1));
+        let info = from_pem(&pem, Some(&cert.serialize_private_key_pem()), None)
+            .unwrap()
+            .info()
+            .unwrap();
+        assert!(info.expired);
+        assert!(info.days_remaining < 0);
+        assert_eq!(info.not_after, 978_307_200);
+    }
+}
@@ -0,0 +1,251 @@
+/// 服务器时间校正
+/// 签名的 X-Timestamp 原本取本机时钟，本机时间偏差超出后端允许的窗口时，所有签名请求都会被 403 拒绝。
+/// 这里根据后端响应的 Date 头估算本机与服务器的时差，签名改用校正后的时间：
+/// - 签名来源的响应参与估算（取请求往返的中点作为本机时间），时差变化不足 1 秒时不更新（Date 只精确到秒）；
+///   带非零 Age 的响应来自中间缓存，其 Date 是缓存生成响应的时间，不参与估算
+/// - 时差超过 TAURI_CLOCK_SKEW_WARN_SECS（默认 30 秒）时发出 clock-skew 事件，恢复正常时再发一次
+/// - 签名来源返回签名过期（X-Signature-Expired 头，或 401/403 且时差在签名之后被明显修正）时，
+///   用校正后的时间重新签名并自动重发一次
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 时差超过阈值或恢复正常时发给前端的事件
+pub const CLOCK_SKEW_EVENT: &str = "clock-skew";
+
+/// 后端明确告知签名时间戳过期时返回的响应头
+const SIGNATURE_EXPIRED_HEADER: &str = "x-signature-expired";
+
+/// 默认告警阈值（秒）
+const DEFAULT_WARN_SECS: i64 = 30;
+
+/// Date 头只精确到秒，变化不足这个值的估算视为抖动
+const RESOLUTION_MS: i64 = 1000;
+
+/// 时差事件负载
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClockSkew {
+    /// 服务器时间减本机时间（毫秒），正数表示本机时钟偏慢
+    pub offset_ms: i64,
+    pub threshold_secs: i64,
+    /// 是否超过阈值；false 表示已恢复正常
+    pub skewed: bool,
+}
+
+type SkewListener = Box<dyn Fn(&ClockSkew) + Send + Sync>;
+
+struct Inner {
+    offset_ms: AtomicI64,
+    warn_ms: i64,
+    skewed: AtomicBool,
+    listener: OnceLock<SkewListener>,
+}
+
+/// 本机与服务器的时差；签名链与发送逻辑共用
+#[derive(Clone)]
+pub struct ServerClock(Arc<Inner>);
+
+impl ServerClock {
+    pub fn from_env() -> Self {
+        let warn_secs = option_env!("TAURI_CLOCK_SKEW_WARN_SECS")
+            .and_then(|v| v.trim().parse::<i64>().ok())
+            .filter(|secs| *secs > 0)
+            .unwrap_or(DEFAULT_WARN_SECS);
+        Self::new(warn_secs)
+    }
+
+    pub fn new(warn_secs: i64) -> Self {
+        Self(Arc::new(Inner {
+            offset_ms: AtomicI64::new(0),
+            warn_ms: warn_secs * 1000,
+            skewed: AtomicBool::new(false),
+            listener: OnceLock::new(),
+        }))
+    }
+
+    /// 注册时差告警的回调（用于通知前端）
+    pub fn set_listener(&self, listener: impl Fn(&ClockSkew) + Send + Sync + 'static) {
+        let _ = self.0.listener.set(Box::new(listener));
+    }
+
+    /// 当前时差（毫秒）
+    pub fn offset_ms(&self) -> i64 {
+        self.0.offset_ms.load(Ordering::Relaxed)
+    }
+
+    /// 校正后的当前时间，用于签名时间戳
+    pub fn now(&self) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::milliseconds(self.offset_ms())
+    }
+
+    /// 根据响应的 Date 头更新时差；sent_at / received_at 为本机发出请求与收到响应头的时间。
+    /// 调用方只对签名来源的响应调用
+    pub fn observe(
+        &self,
+        headers: &reqwest::header::HeaderMap,
+        sent_at: DateTime<Utc>,
+        received_at: DateTime<Utc>,
+    ) {
+        let cached = headers.get(reqwest::header::AGE).is_some_and(|age| {
+            age.to_str().ok().and_then(|v| v.trim().parse::<u64>().ok()) != Some(0)
+        });
+        if cached {
+            return;
+        }
+        let Some(server) = headers
+            .get(reqwest::header::DATE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| DateTime::parse_from_rfc2822(v.trim()).ok())
+        else {
+            return;
+        };
+        // Date 向下取整到秒，取这一秒的中点；本机时间取往返中点
+        let server_ms = server.timestamp_millis() + RESOLUTION_MS / 2;
+        let local_ms = sent_at.timestamp_millis()
+            + (received_at.timestamp_millis() - sent_at.timestamp_millis()) / 2;
+        self.update(server_ms - local_ms);
+    }
+
+    fn update(&self, offset_ms: i64) {
+        let previous = self.offset_ms();
+        if (offset_ms - previous).abs() < RESOLUTION_MS {
+            return;
+        }
+        self.0.offset_ms.store(offset_ms, Ordering::Relaxed);
+        log!(
+            "🕒 [CLOCK] 服务器时差 {} ms（之前 {} ms）",
+            offset_ms,
+            previous
+        );
+
+        let skewed = offset_ms.abs() > self.0.warn_ms;
+        let was_skewed = self.0.skewed.swap(skewed, Ordering::Relaxed);
+        if skewed || was_skewed {
+            if skewed {
+                log!("⚠️  [CLOCK] 本机时钟偏差超过 {} 秒", self.0.warn_ms / 1000);
+            }
+            if let Some(listener) = self.0.listener.get() {
+                listener(&ClockSkew {
+                    offset_ms,
+                    threshold_secs: self.0.warn_ms / 1000,
+                    skewed,
+                });
+            }
+        }
+    }
+
+    /// 是否是时间戳过期导致的签名失败：后端明确返回 X-Signature-Expired，
+    /// 或 401/403 且时差自签名时起已被修正超过告警阈值。signed_offset_ms 为签名时的时差
+    pub fn signature_expired(
+        &self,
+        status: u16,
+        headers: &reqwest::header::HeaderMap,
+        signed_offset_ms: i64,
+    ) -> bool {
+        headers.contains_key(SIGNATURE_EXPIRED_HEADER)
+            || (matches!(status, 401 | 403)
+                && (self.offset_ms() - signed_offset_ms).abs() > self.0.warn_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, DATE};
+
+    fn date_headers(date: DateTime<Utc>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(DATE, HeaderValue::from_str(&date.to_rfc2822()).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_observe_corrects_offset() {
+        let clock = ServerClock::new(30);
+        let sent_at = Utc::now();
+        let received_at = sent_at + chrono::Duration::milliseconds(200);
+        let server = sent_at + chrono::Duration::seconds(120);
+        clock.observe(&date_headers(server), sent_at, received_at);
+        let offset = clock.offset_ms();
+        assert!((119_000..=121_000).contains(&offset), "offset {}", offset);
+        assert!((clock.now() - Utc::now()).num_seconds() >= 118);
+
+        // 不足 1 秒的变化视为抖动
+        clock.observe(
+            &date_headers(server),
+            sent_at,
+            received_at + chrono::Duration::milliseconds(600),
+        );
+        assert_eq!(clock.offset_ms(), offset);
+    }
+
+    #[test]
+    fn test_observe_skips_cached_responses() {
+        let clock = ServerClock::new(30);
+        let sent_at = Utc::now();
+        let server = sent_at + chrono::Duration::seconds(120);
+
+        let mut cached = date_headers(server);
+        cached.insert(reqwest::header::AGE, HeaderValue::from_static("300"));
+        clock.observe(&cached, sent_at, sent_at);
+        assert_eq!(clock.offset_ms(), 0);
+
+        // Age: 0 表示刚由源站生成，照常参与估算
+        cached.insert(reqwest::header::AGE, HeaderValue::from_static("0"));
+        clock.observe(&cached, sent_at, sent_at);
+        assert!(clock.offset_ms() > 100_000);
+    }
+
+    #[test]
+    fn test_skew_events() {
+        let clock = ServerClock::new(30);
+        let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        clock.set_listener(move |skew| sink.lock().unwrap().push(skew.skewed));
+
+        clock.update(5_000);
+        clock.update(-60_000);
+        clock.update(-62_000);
+        clock.update(2_000);
+        clock.update(4_000);
+        assert_eq!(*events.lock().unwrap(), vec![true, true, false]);
+    }
+
+    #[test]
+    fn test_signature_expired() {
+        let clock = ServerClock::new(30);
+        let mut headers = HeaderMap::new();
+        assert!(!clock.signature_expired(403, &headers, 0));
+
+        clock.update(90_000);
+        assert!(clock.signature_expired(403, &headers, 0));
+        assert!(!clock.signature_expired(403, &headers, 90_000));
+        assert!(!clock.signature_expired(500, &headers, 0));
+
+        headers.insert(SIGNATURE_EXPIRED_HEADER, HeaderValue::from_static("1"));
+        assert!(clock.signature_expired(401, &headers, 90_000));
+    }
+}
@@ -0,0 +1,115 @@
+/// 相同 GET 请求合并
+/// 页面上多个组件常在同一时刻各自请求同一个 /base_api 字典接口，
+/// 每个请求都要签名、RSA 加密并往返一次后端。这里把正在进行中的、完全相同的 GET
+/// （相同 URL、相同重定向模式、相同的转发请求头）合并为一次上游请求，所有等待方共享同一个响应。
+/// - 只合并 proxy_request（整体缓冲的响应）；流式响应体无法同时推送给多个 Channel
+/// - 请求带 coalesce: false 或 Cache-Control: no-store 时不参与合并
+/// - 每个等待方各自响应取消；所有等待方都放弃后，上游请求随之中断
+use futures_util::future::{BoxFuture, FutureExt, Shared, WeakShared};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::cache_policy::has_directive;
+use crate::error::AppError;
+use crate::proxy::{ProxyRequest, ProxyResponse};
+
+/// 标记响应来自合并的请求（值为 true），便于在开发者工具里区分
+pub const COALESCED_HEADER: &str = "x-proxy-coalesced";
+
+type ResponseFuture = BoxFuture<'static, Result<ProxyResponse, AppError>>;
+type SharedResponse = Shared<ResponseFuture>;
+// key -> (登记序号, 共享的请求)；只持有弱引用，等待方全部放弃时请求随之释放
+type Entries = HashMap<String, (u64, WeakShared<ResponseFuture>)>;
+
+#[derive(Clone, Default)]
+pub struct InflightGets {
+    entries: Arc<Mutex<Entries>>,
+    next_generation: Arc<AtomicU64>,
+}
+
+/// 加入合并的结果
+pub enum Joined {
+    /// 发起方：由本次调用实际发出请求
+    Leader(SharedResponse),
+    /// 跟随方：复用已在进行中的请求
+    Follower(SharedResponse),
+}
+
+impl InflightGets {
+    /// 可合并请求的键；不可合并时返回 None
+    pub fn key_for(&self, request: &ProxyRequest) -> Option<String> {
+        if !request.coalesce
+            || !request.method.eq_ignore_ascii_case("GET")
+            || request.body.is_some()
+            || request.multipart_parts().is_some()
+            || request
+                .headers
+                .get("cache-control")
+                .is_some_and(|v| has_directive(v, "no-store"))
+        {
+            return None;
+        }
+
+        // 请求头按名称（小写）排序，同名头保留原有先后，避免页面设置顺序不同导致无法合并
+        let forwarded = request.headers.forwardable_request();
+        let mut headers: Vec<(String, &str)> = forwarded
+            .iter()
+            .map(|(name, value)| (name.to_ascii_lowercase(), value))
+            .collect();
+        headers.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut hasher = Sha256::new();
+        hasher.update(format!("GET|{:?}|", request.redirect).as_bytes());
+        hasher.update(request.url.as_bytes());
+        for (name, value) in headers {
+            hasher.update(b"\n");
+            hasher.update(name.as_bytes());
+            hasher.update(b":");
+            hasher.update(value.as_bytes());
+        }
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    /// 加入同键的进行中请求；没有时用 start 发起一个新请求并登记
+    pub fn join<F>(&self, key: &str, start: impl FnOnce() -> F) -> Joined
+    where
+        F: Future<Output = Result<ProxyResponse, AppError>> + Send + 'static,
+    {
+        let Ok(mut entries) = self.entries.lock() else {
+            return Joined::Leader(start().boxed().shared());
+        };
+        if let Some(shared) = entries.get(key).and_then(|(_, weak)| weak.upgrade()) {
+            return Joined::Follower(shared);
+        }
+        // 清理等待方已全部放弃的旧条目
+        entries.retain(|_, (_, weak)| weak.upgrade().is_some());
+
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let registry = self.clone();
+        let owned_key = key.to_string();
+        let request = start();
+        let shared = async move {
+            let result = request.await;
+            registry.unregister(&owned_key, generation);
+            result
+        }
+        .boxed()
+        .shared();
+        if let Some(weak) = shared.downgrade() {
+            entries.insert(key.to_string(), (generation, weak));
+        }
+        Joined::Leader(shared)
+    }
+
+    fn unregister(&self, key: &str, generation: u64) {
+        if let Ok(mut entries) = self.entries.lock() {
+            // 只移除自己登记的条目，避免误删同键的新请求
+            if entries.get(key).map(|(current, _)| *current) == Some(generation) {
+                entries.remove(key);
+            }
+        }
+    }
+}
@@ -0,0 +1,320 @@
+/// 代理 Cookie 持久化模块
+/// reqwest 自带的 cookie_store(true) 只在内存里，应用重启后后端会话全部丢失。
+/// 这里实现 reqwest::cookie::CookieStore：Cookie 变化后加密写入应用数据目录，
+/// 启动时读回。文件按环境（env_key）区分，以系统钥匙串保管的密钥加密（AES-256-GCM，见 crypto::seal），
+/// 拷到其他机器上无法解密。会话 Cookie 也一并保留，与 WebView 重启后保持登录的行为一致。
+/// Cookie 变化只标记待保存，由后台任务合并后写盘，不在请求线程上做文件 IO；退出前 flush 一次
+use cookie_store::{Cookie, CookieExpiration, CookieStore, RawCookie};
+use reqwest::header::HeaderValue;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+use tauri::{Manager, State};
+use tokio::sync::Notify;
+
+use crate::crypto::{needs_reseal, seal, unseal};
+use crate::error::AppError;
+use crate::proxy::AppState;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 加密用途标识（参与密钥派生）
+const SEAL_PURPOSE: &str = "cookie-jar";
+
+/// Cookie 变化后等待这么久再写盘，期间的其他变化合并为一次写入
+const SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// 代理响应写入 Cookie 后的回调，参数为按响应地址解析后的 Cookie（含已过期的删除指令）
+type ChangeListener = Box<dyn Fn(&[Cookie<'static>]) + Send + Sync>;
+
+/// 前端可见的 Cookie 信息（不返回值，避免页面脚本读到 HttpOnly 会话 Cookie）
+#[derive(Debug, Serialize)]
+pub struct CookieInfo {
+    pub name: String,
+    pub domain: String,
+    pub path: String,
+    /// 过期时间（Unix 秒），会话 Cookie 为 None
+    pub expires: Option<i64>,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+pub struct PersistentCookieJar {
+    env_key: String,
+    store: RwLock<CookieStore>,
+    /// 启动时在 setup 中确定，确定之前不落盘
+    path: OnceLock<PathBuf>,
+    /// 有未保存的变化时通知后台写盘任务
+    dirty: Notify,
+    /// 串行化写文件（后台任务与退出时的 flush）
+    save_lock: Mutex<()>,
+    listener: OnceLock<ChangeListener>,
+}
+
+impl PersistentCookieJar {
+    pub fn new(env_key: &str) -> Self {
+        Self {
+            env_key: env_key.to_string(),
+            store: RwLock::new(CookieStore::default()),
+            path: OnceLock::new(),
+            dirty: Notify::new(),
+            save_lock: Mutex::new(()),
+            listener: OnceLock::new(),
+        }
+    }
+
+    /// 注册代理响应 Set-Cookie 的回调（用于同步到 WebView）
+    pub fn set_listener(&self, listener: impl Fn(&[Cookie<'static>]) + Send + Sync + 'static) {
+        let _ = self.listener.set(Box::new(listener));
+    }
+
+    pub(crate) fn read<T>(&self, f: impl FnOnce(&CookieStore) -> T) -> Option<T> {
+        self.store.read().ok().map(|store| f(&store))
+    }
+
+    /// 修改后落盘；不触发回调，避免与 WebView 互相同步形成回环
+    pub(crate) fn update<T>(&self, f: impl FnOnce(&mut CookieStore) -> T) -> Option<T> {
+        let result = self.store.write().ok().map(|mut store| f(&mut store));
+        self.save();
+        result
+    }
+
+    /// 确定存储路径并读回上次保存的 Cookie，然后启动后台写盘任务；
+    /// 文件损坏或无法解密（设备变化）时从空开始
+    pub fn load(self: &Arc<Self>, app: &tauri::AppHandle) {
+        let Ok(dir) = app.path().app_data_dir() else {
+            log!("⚠️  [Cookie] 无法获取应用数据目录，Cookie 不会持久化");
+            return;
+        };
+        let path = dir
+            .join("cookies")
+            .join(format!("{}.bin", sanitize(&self.env_key)));
+
+        match std::fs::read(&path) {
+            Ok(raw) => match self.decrypt(&raw).and_then(|plain| parse_store(&plain)) {
+                Some(store) => {
+                    if let Ok(mut current) = self.store.write() {
+                        *current = store;
+                    }
+                    if needs_reseal(&raw) {
+                        self.save();
+                    }
+                    log!("🍪 [Cookie] 已恢复: {}", path.display());
+                }
+                None => log!("⚠️  [Cookie] 无法解密或解析，忽略旧文件: {}", path.display()),
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => log!("⚠️  [Cookie] 读取失败: {}", e),
+        }
+
+        let _ = self.path.set(path);
+
+        let jar = Arc::clone(self);
+        tauri::async_runtime::spawn(async move {
+            loop {
+                jar.dirty.notified().await;
+                tokio::time::sleep(SAVE_DEBOUNCE).await;
+                let writer = Arc::clone(&jar);
+                let _ = tauri::async_runtime::spawn_blocking(move || writer.flush()).await;
+            }
+        });
+    }
+
+    pub fn list(&self) -> Vec<CookieInfo> {
+        let Ok(store) = self.store.read() else {
+            return Vec::new();
+        };
+        store
+            .iter_unexpired()
+            .map(|cookie| CookieInfo {
+                name: cookie.name().to_string(),
+                domain: String::from(&cookie.domain),
+                path: String::from(&cookie.path),
+                expires: match &cookie.expires {
+                    CookieExpiration::AtUtc(at) => Some(at.unix_timestamp()),
+                    CookieExpiration::SessionEnd => None,
+                },
+                secure: cookie.secure().unwrap_or(false),
+                http_only: cookie.http_only().unwrap_or(false),
+            })
+            .collect()
+    }
+
+    pub fn delete(&self, domain: &str, path: &str, name: &str) -> bool {
+        let removed = self
+            .store
+            .write()
+            .map(|mut store| store.remove(domain, path, name).is_some())
+            .unwrap_or(false);
+        if removed {
+            self.save();
+        }
+        removed
+    }
+
+    /// 清空当前环境的全部 Cookie，返回清除的数量
+    pub fn clear(&self) -> usize {
+        let count = self
+            .store
+            .write()
+            .map(|mut store| {
+                let count = store.iter_any().count();
+                store.clear();
+                count
+            })
+            .unwrap_or(0);
+        self.save();
+        count
+    }
+
+    /// 标记有变化，由后台任务稍后写盘
+    fn save(&self) {
+        self.dirty.notify_one();
+    }
+
+    /// 立即加密写入（先写临时文件再改名，避免写到一半崩溃留下损坏文件）
+    pub fn flush(&self) {
+        let Some(path) = self.path.get() else {
+            return;
+        };
+        let Ok(_lock) = self.save_lock.lock() else {
+            return;
+        };
+        let plain = match self.store.read() {
+            Ok(store) => serde_json::to_vec(&store.iter_unexpired().collect::<Vec<_>>()),
+            Err(_) => return,
+        };
+        let Some(encrypted) = plain.ok().and_then(|plain| self.encrypt(&plain)) else {
+            log!("⚠️  [Cookie] 加密失败，未保存");
+            return;
+        };
+
+        let result = (|| -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            let tmp = path.with_extension("bin.tmp");
+            std::fs::write(&tmp, encrypted)?;
+            std::fs::rename(&tmp, path)
+        })();
+        if let Err(e) = result {
+            log!("⚠️  [Cookie] 保存失败: {}", e);
+        }
+    }
+
+    /// 密钥与设备和环境绑定
+    fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+        seal(SEAL_PURPOSE, &self.env_key, plain)
+    }
+
+    fn decrypt(&self, raw: &[u8]) -> Option<Vec<u8>> {
+        unseal(SEAL_PURPOSE, &self.env_key, raw)
+    }
+}
+
+impl reqwest::cookie::CookieStore for PersistentCookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &reqwest::Url) {
+        let cookies: Vec<RawCookie<'static>> = cookie_headers
+            .filter_map(|value| std::str::from_utf8(value.as_bytes()).ok())
+            .filter_map(|value| RawCookie::parse(value).ok())
+            .map(RawCookie::into_owned)
+            .collect();
+        if cookies.is_empty() {
+            return;
+        }
+
+        let resolved: Vec<Cookie<'static>> = cookies
+            .iter()
+            .filter_map(|raw| Cookie::try_from_raw_cookie(raw, url).ok())
+            .collect();
+
+        if let Ok(mut store) = self.store.write() {
+            store.store_response_cookies(cookies.into_iter(), url);
+        }
+        self.save();
+
+        if let Some(listener) = self.listener.get() {
+            listener(&resolved);
+        }
+    }
+
+    fn cookies(&self, url: &reqwest::Url) -> Option<HeaderValue> {
+        let value = self
+            .store
+            .read()
+            .ok()?
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if value.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&value).ok()
+    }
+}
+
+fn parse_store(plain: &[u8]) -> Option<CookieStore> {
+    let cookies: Vec<cookie_store::Cookie<'static>> = serde_json::from_slice(plain).ok()?;
+    CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, ()>), false).ok()
+}
+
+/// env_key 用作文件名
+/// 环境标识转为安全的文件名
+pub(crate) fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// 列出代理 Cookie（不含值）
+#[tauri::command]
+pub fn list_proxy_cookies(state: State<'_, Arc<AppState>>) -> Result<Vec<CookieInfo>, AppError> {
+    Ok(state.cookies.list())
+}
+
+/// 删除一个代理 Cookie，返回是否存在
+#[tauri::command]
+pub fn delete_proxy_cookie(
+    domain: String,
+    path: String,
+    name: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<bool, AppError> {
+    let removed = state.cookies.delete(&domain, &path, &name);
+    log!("🍪 [Cookie] 删除 {} {} {}: {}", domain, path, name, removed);
+    Ok(removed)
+}
+
+/// 清空当前环境的代理 Cookie 与 HTTP 缓存，返回清除的 Cookie 数量
+#[tauri::command]
+pub async fn clear_proxy_cookies(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, AppError> {
+    let count = state.cookies.clear();
+    let freed = state.cache.purge(&app, None).await;
+    log!("🍪 [Cookie] 已清空 {} 个, 缓存 {} bytes", count, freed);
+    Ok(count)
+}
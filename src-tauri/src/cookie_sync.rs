@@ -0,0 +1,280 @@
+/// WebView 与代理 Cookie 双向同步
+/// 页面导航设置的 Cookie 存在 WebView 里，代理 XHR 响应设置的 Cookie 存在 reqwest 的 Cookie 罐里，
+/// 两边不同步会出现“页面已登录但接口 401”。
+/// WebView 的 Cookie 接口在 Windows 上于同步上下文调用会死锁，全部放到阻塞线程池执行。
+/// 只同步属于环境来源（TAURI_ENV_URL）的 Cookie：
+/// - 启动时：双向合并（WebView 优先），两边取并集
+/// - 代理响应带 Set-Cookie 时：写入（或删除）WebView
+/// - 页面导航完成后：以 WebView 为准覆盖代理侧（导航登出后代理侧也随之清空）
+/// - 登出时：两边一起清空（logout_proxy_cookies）
+use cookie_store::{Cookie, CookieExpiration, RawCookie};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tauri::webview::{PageLoadEvent, PageLoadPayload};
+use tauri::{Manager, State};
+
+use crate::cookie_jar::PersistentCookieJar;
+use crate::error::AppError;
+use crate::proxy::AppState;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+pub struct CookieSync {
+    env_url: Option<reqwest::Url>,
+    /// 启动合并完成前，页面导航只合并不删除，避免覆盖掉尚未同步到 WebView 的持久化 Cookie
+    ready: AtomicBool,
+}
+
+impl CookieSync {
+    pub fn new(env_url: &str) -> Self {
+        Self {
+            env_url: reqwest::Url::parse(env_url).ok(),
+            ready: AtomicBool::new(false),
+        }
+    }
+}
+
+/// 启动同步：注册 Set-Cookie 回调，并在后台做一次双向合并
+pub fn start(app: &tauri::AppHandle, state: &Arc<AppState>) {
+    let Some(env_url) = state.cookie_sync.env_url.clone() else {
+        return;
+    };
+
+    let handle = app.clone();
+    let url = env_url.clone();
+    state.cookies.set_listener(move |cookies| {
+        let changed: Vec<Cookie<'static>> = cookies
+            .iter()
+            .filter(|cookie| cookie.domain.matches(&url))
+            .cloned()
+            .collect();
+        if !changed.is_empty() {
+            let handle = handle.clone();
+            tauri::async_runtime::spawn_blocking(move || push_to_webview(&handle, &changed));
+        }
+    });
+
+    let handle = app.clone();
+    let state = Arc::clone(state);
+    tauri::async_runtime::spawn_blocking(move || {
+        pull_from_webview(&handle, &state.cookies, &env_url, false);
+        let cookies = env_cookies(&state.cookies, &env_url);
+        push_to_webview(&handle, &cookies);
+        state.cookie_sync.ready.store(true, Ordering::SeqCst);
+        log!("🍪 [CookieSync] 启动同步完成: {} 个", cookies.len());
+    });
+}
+
+/// 页面导航完成后以 WebView 为准更新代理侧（挂在各窗口的 on_page_load 上）
+pub fn on_page_load(window: tauri::WebviewWindow, payload: PageLoadPayload<'_>) {
+    if !matches!(payload.event(), PageLoadEvent::Finished) {
+        return;
+    }
+    let app = window.app_handle();
+    let state = Arc::clone(app.state::<Arc<AppState>>().inner());
+    let Some(env_url) = state.cookie_sync.env_url.clone() else {
+        return;
+    };
+    let handle = app.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let mirror = state.cookie_sync.ready.load(Ordering::SeqCst);
+        pull_from_webview(&handle, &state.cookies, &env_url, mirror);
+    });
+}
+
+/// 环境来源下、代理侧仍有效的 Cookie
+fn env_cookies(jar: &PersistentCookieJar, env_url: &reqwest::Url) -> Vec<Cookie<'static>> {
+    jar.read(|store| {
+        store
+            .iter_unexpired()
+            .filter(|cookie| cookie.domain.matches(env_url))
+            .cloned()
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// 任取一个 WebView 窗口：所有窗口共用同一个 Cookie 存储
+fn any_webview(app: &tauri::AppHandle) -> Option<tauri::WebviewWindow> {
+    app.get_webview_window("main")
+        .or_else(|| app.webview_windows().into_values().next())
+}
+
+/// WebView → 代理；mirror 为 true 时删除 WebView 中已不存在的环境 Cookie
+fn pull_from_webview(
+    app: &tauri::AppHandle,
+    jar: &PersistentCookieJar,
+    env_url: &reqwest::Url,
+    mirror: bool,
+) {
+    let Some(webview) = any_webview(app) else {
+        return;
+    };
+    let cookies = match webview.cookies() {
+        Ok(cookies) => cookies,
+        Err(e) => {
+            log!("⚠️  [CookieSync] 读取 WebView Cookie 失败: {}", e);
+            return;
+        }
+    };
+    let host = env_url.host_str().unwrap_or_default().to_ascii_lowercase();
+    let raw: Vec<RawCookie<'static>> = cookies
+        .iter()
+        .filter(|cookie| cookie.domain().is_some_and(|domain| domain_matches(&host, domain)))
+        .filter_map(|cookie| RawCookie::parse(cookie.to_string()).ok())
+        .collect();
+
+    jar.update(|store| {
+        let mut seen = HashSet::new();
+        for cookie in &raw {
+            if let Ok(cookie) = Cookie::try_from_raw_cookie(cookie, env_url) {
+                seen.insert(cookie_key(&cookie));
+                let _ = store.insert(cookie.into_owned(), env_url);
+            }
+        }
+        if mirror {
+            let stale: Vec<(String, String, String)> = store
+                .iter_any()
+                .filter(|cookie| cookie.domain.matches(env_url))
+                .map(cookie_key)
+                .filter(|key| !seen.contains(key))
+                .collect();
+            for (domain, path, name) in stale {
+                log!("🍪 [CookieSync] WebView 已删除，同步删除: {} {} {}", domain, path, name);
+                store.remove(&domain, &path, &name);
+            }
+        }
+    });
+}
+
+/// 代理 → WebView：有效的写入，已过期的删除
+fn push_to_webview(app: &tauri::AppHandle, cookies: &[Cookie<'static>]) {
+    let Some(webview) = any_webview(app) else {
+        return;
+    };
+    for cookie in cookies {
+        let converted = to_webview_cookie(cookie);
+        let result = if cookie.is_expired() {
+            webview.delete_cookie(converted)
+        } else {
+            webview.set_cookie(converted)
+        };
+        if let Err(e) = result {
+            log!("⚠️  [CookieSync] 同步 Cookie {} 失败: {}", cookie.name(), e);
+        }
+    }
+}
+
+fn to_webview_cookie(cookie: &Cookie<'static>) -> tauri::webview::Cookie<'static> {
+    let mut builder =
+        tauri::webview::Cookie::build((cookie.name().to_string(), cookie.value().to_string()))
+            .domain(String::from(&cookie.domain))
+            .path(String::from(&cookie.path))
+            .secure(cookie.secure().unwrap_or(false))
+            .http_only(cookie.http_only().unwrap_or(false));
+    if let CookieExpiration::AtUtc(at) = &cookie.expires {
+        builder = builder.expires(*at);
+    }
+    builder.build()
+}
+
+fn cookie_key(cookie: &Cookie<'_>) -> (String, String, String) {
+    (
+        String::from(&cookie.domain),
+        String::from(&cookie.path),
+        cookie.name().to_string(),
+    )
+}
+
+/// host 是否属于 Cookie 的 domain（domain 可能带前导点）
+fn domain_matches(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// 手动触发一次双向合并（例如页面通过 fetch 完成登录后）
+#[tauri::command]
+pub async fn sync_proxy_cookies(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, AppError> {
+    let Some(env_url) = state.cookie_sync.env_url.clone() else {
+        return Ok(0);
+    };
+    let state = Arc::clone(state.inner());
+    let count = tauri::async_runtime::spawn_blocking(move || {
+        pull_from_webview(&app, &state.cookies, &env_url, false);
+        let cookies = env_cookies(&state.cookies, &env_url);
+        push_to_webview(&app, &cookies);
+        cookies.len()
+    })
+    .await?;
+    Ok(count)
+}
+
+/// 登出：同时清空 WebView 与代理侧的环境 Cookie 以及代理 HTTP 缓存，返回清除的 Cookie 数量
+#[tauri::command]
+pub async fn logout_proxy_cookies(
+    app: tauri::AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, AppError> {
+    let Some(env_url) = state.cookie_sync.env_url.clone() else {
+        return Ok(0);
+    };
+    let state = Arc::clone(state.inner());
+    let (webview_app, jar_state) = (app.clone(), Arc::clone(&state));
+    let count = tauri::async_runtime::spawn_blocking(move || {
+        let (app, state) = (webview_app, jar_state);
+        let host = env_url.host_str().unwrap_or_default().to_ascii_lowercase();
+        if let Some(webview) = any_webview(&app) {
+            let cookies = webview.cookies().unwrap_or_default();
+            for cookie in cookies
+                .into_iter()
+                .filter(|cookie| cookie.domain().is_some_and(|domain| domain_matches(&host, domain)))
+            {
+                let _ = webview.delete_cookie(cookie);
+            }
+        }
+
+        state
+            .cookies
+            .update(|store| {
+                let keys: Vec<(String, String, String)> = store
+                    .iter_any()
+                    .filter(|cookie| cookie.domain.matches(&env_url))
+                    .map(cookie_key)
+                    .collect();
+                for (domain, path, name) in &keys {
+                    store.remove(domain, path, name);
+                }
+                keys.len()
+            })
+            .unwrap_or(0)
+    })
+    .await?;
+
+    // 缓存条目按身份划分，登出后旧会话的条目不会再命中，直接清掉
+    let freed = state.cache.purge(&app, None).await;
+    log!("🍪 [CookieSync] 登出，已清除 {} 个代理 Cookie、{} bytes 缓存", count, freed);
+    Ok(count)
+}
@@ -1,6 +1,14 @@
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
 use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
 use rsa::pkcs8::DecodePublicKey;
 use rsa::{Pkcs1v15Encrypt, RsaPublicKey};
+use sha2::{Digest, Sha256};
+use std::sync::OnceLock;
+
+use crate::fingerprint::get_device_fingerprint;
 
 // 编译时判断是否启用日志（使用字节比较避免 const 限制）
 #[cfg(debug_assertions)]
@@ -85,14 +93,26 @@ fn select_public_key() -> &'static str {
     }
 }
 
+/// 解析按环境选择的公钥；安全评分与解析结果在进程内只计算一次
+pub(crate) fn load_public_key() -> Result<&'static RsaPublicKey, String> {
+    static PUBLIC_KEY: OnceLock<Result<RsaPublicKey, String>> = OnceLock::new();
+
+    PUBLIC_KEY
+        .get_or_init(|| {
+            // 动态选择公钥
+            let public_key_pem = select_public_key();
+
+            // 解析公钥
+            RsaPublicKey::from_public_key_pem(public_key_pem)
+                .map_err(|e| format!("Failed to parse public key: {}", e))
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
 /// 使用 RSA 公钥加密签名数据（动态选择公钥）
 pub fn encrypt_signature(data: &str) -> Result<String, String> {
-    // 动态选择公钥
-    let public_key_pem = select_public_key();
-
-    // 解析公钥
-    let public_key = RsaPublicKey::from_public_key_pem(public_key_pem)
-        .map_err(|e| format!("Failed to parse public key: {}", e))?;
+    let public_key = load_public_key()?;
 
     // 加密数据
     let mut rng = rand::thread_rng();
@@ -104,12 +124,15 @@ pub fn encrypt_signature(data: &str) -> Result<String, String> {
     Ok(general_purpose::STANDARD.encode(&encrypted))
 }
 
-/// 生成验证签名数据
+/// 生成 v1 验证签名数据（最初的格式，未配置签名版本的后端按它验签）
 /// 格式：timestamp|device_fingerprint|device_info_hash|path_hash
 /// 注意：Nginx 会去掉 /base_api 前缀，所以我们也要去掉再哈希
-pub fn generate_signature_data(timestamp: &str, fingerprint: &str, device_info_json: &str, url: &str) -> String {
-    use sha2::{Digest, Sha256};
-
+pub fn generate_legacy_signature_data(
+    timestamp: &str,
+    fingerprint: &str,
+    device_info_json: &str,
+    url: &str,
+) -> String {
     // 提取路径并去掉 /base_api 前缀（因为 Nginx 会去掉）
     let path_to_hash = if let Some(idx) = url.find("/base_api/") {
         // 找到 /base_api/，取后面的部分（包括开头的 /）
@@ -154,5 +177,164 @@ pub fn generate_signature_data(timestamp: &str, fingerprint: &str, device_info_j
     log!("   📝 Device info hash: {}", &device_info_hash[..16]);
 
     // 组合签名数据：timestamp|fingerprint|device_info_hash|path_hash
-    format!("{}|{}|{}|{}", timestamp, fingerprint, &device_info_hash[..16], &path_hash[..16])
+    format!(
+        "{}|{}|{}|{}",
+        timestamp,
+        fingerprint,
+        &device_info_hash[..16],
+        &path_hash[..16]
+    )
+}
+
+/// 本地加密文件格式版本，改动格式或密钥来源时递增
+/// v1 的密钥由设备指纹派生（指纹本身会随请求发出，不能当作秘密），只读不写，用于迁移
+const SEALED_VERSION: u8 = 2;
+const LEGACY_SEALED_VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+
+/// 系统钥匙串（macOS Keychain / Windows 凭据管理器 / Secret Service）中保存主密钥的条目
+const KEYCHAIN_SERVICE: &str = "backstage68";
+const KEYCHAIN_ACCOUNT: &str = "local-data-key";
+
+/// 本地数据加密主密钥：首次使用时随机生成并交给系统钥匙串保管，由系统负责访问控制。
+/// 钥匙串不可用时为 None，此时本地数据不落盘
+fn master_key() -> Option<&'static [u8; 32]> {
+    static MASTER_KEY: OnceLock<Option<[u8; 32]>> = OnceLock::new();
+    MASTER_KEY
+        .get_or_init(|| match load_or_create_master_key() {
+            Ok(key) => Some(key),
+            Err(e) => {
+                log!("⚠️  [Crypto] 系统钥匙串不可用，本地数据不会持久化: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
+
+fn load_or_create_master_key() -> Result<[u8; 32], keyring::Error> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)?;
+    match entry.get_secret() {
+        Ok(secret) => {
+            if let Ok(key) = <[u8; 32]>::try_from(secret.as_slice()) {
+                return Ok(key);
+            }
+            log!("⚠️  [Crypto] 钥匙串中的主密钥长度不符，重新生成");
+        }
+        Err(keyring::Error::NoEntry) => {}
+        Err(e) => return Err(e),
+    }
+    let mut key = [0u8; 32];
+    OsRng.fill_bytes(&mut key);
+    entry.set_secret(&key)?;
+    log!("🔑 [Crypto] 已生成本地数据主密钥并存入系统钥匙串");
+    Ok(key)
+}
+
+/// 按用途和作用域（通常是环境）从主密钥派生的数据密钥
+fn sealing_cipher(master: &[u8; 32], purpose: &str, scope: &str) -> Aes256Gcm {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(master).expect("HMAC 接受任意长度的密钥");
+    mac.update(format!("backstage68/{}|", purpose).as_bytes());
+    mac.update(scope.as_bytes());
+    let digest = mac.finalize().into_bytes();
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&digest))
+}
+
+/// v1 的指纹派生密钥，仅用于读取旧文件
+fn legacy_cipher(purpose: &str, scope: &str) -> Aes256Gcm {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("backstage68/{}|", purpose).as_bytes());
+    hasher.update(get_device_fingerprint().as_bytes());
+    hasher.update(b"|");
+    hasher.update(scope.as_bytes());
+    let digest = hasher.finalize();
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&digest))
+}
+
+/// AES-256-GCM 加密落盘数据，格式：版本(1) | nonce(12) | 密文；钥匙串不可用时返回 None
+pub fn seal(purpose: &str, scope: &str, plain: &[u8]) -> Option<Vec<u8>> {
+    seal_with(&sealing_cipher(master_key()?, purpose, scope), plain)
+}
+
+/// seal 的逆操作；版本不符、数据损坏或密钥不可用时返回 None
+pub fn unseal(purpose: &str, scope: &str, raw: &[u8]) -> Option<Vec<u8>> {
+    match raw.first() {
+        Some(&SEALED_VERSION) => unseal_with(&sealing_cipher(master_key()?, purpose, scope), raw),
+        Some(&LEGACY_SEALED_VERSION) => unseal_with(&legacy_cipher(purpose, scope), raw),
+        _ => None,
+    }
+}
+
+/// 数据是否为旧格式：调用方读取成功后应立即用新密钥重新保存
+pub fn needs_reseal(raw: &[u8]) -> bool {
+    raw.first() == Some(&LEGACY_SEALED_VERSION)
+}
+
+/// 直接交给系统钥匙串保管的机密（如客户端证书私钥），不经过本地文件；
+/// account 在 KEYCHAIN_SERVICE 下区分条目，secret 为 None 时删除条目
+pub fn store_secret(account: &str, secret: Option<&[u8]>) -> Result<(), keyring::Error> {
+    let entry = keyring::Entry::new(KEYCHAIN_SERVICE, account)?;
+    match secret {
+        Some(secret) => entry.set_secret(secret),
+        None => match entry.delete_credential() {
+            Err(keyring::Error::NoEntry) => Ok(()),
+            result => result,
+        },
+    }
+}
+
+/// 读取 store_secret 保存的机密；条目不存在或钥匙串不可用时返回 None
+pub fn load_secret(account: &str) -> Option<Vec<u8>> {
+    keyring::Entry::new(KEYCHAIN_SERVICE, account)
+        .and_then(|entry| entry.get_secret())
+        .inspect_err(|e| {
+            if !matches!(e, keyring::Error::NoEntry) {
+                log!("⚠️  [Crypto] 读取钥匙串条目 {} 失败: {}", account, e);
+            }
+        })
+        .ok()
+}
+
+fn seal_with(cipher: &Aes256Gcm, plain: &[u8]) -> Option<Vec<u8>> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher.encrypt(&nonce, plain).ok()?;
+    let mut out = Vec::with_capacity(1 + NONCE_LEN + ciphertext.len());
+    out.push(SEALED_VERSION);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Some(out)
+}
+
+fn unseal_with(cipher: &Aes256Gcm, raw: &[u8]) -> Option<Vec<u8>> {
+    let rest = raw.get(1..).filter(|rest| rest.len() >= NONCE_LEN)?;
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    cipher.decrypt(Nonce::from_slice(nonce), ciphertext).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sealing_keys_are_scoped() {
+        let master = [7u8; 32];
+        let cipher = sealing_cipher(&master, "cookie-jar", "prod");
+        let sealed = seal_with(&cipher, b"session=1").unwrap();
+        assert_eq!(sealed[0], SEALED_VERSION);
+        assert!(!needs_reseal(&sealed));
+        assert_eq!(unseal_with(&cipher, &sealed).unwrap(), b"session=1");
+
+        // 用途、作用域或主密钥不同都无法解密
+        for other in [
+            sealing_cipher(&master, "network-proxy", "prod"),
+            sealing_cipher(&master, "cookie-jar", "test"),
+            sealing_cipher(&[8u8; 32], "cookie-jar", "prod"),
+        ] {
+            assert!(unseal_with(&other, &sealed).is_none());
+        }
+        assert!(unseal_with(&cipher, &sealed[..NONCE_LEN]).is_none());
+
+        let mut legacy = sealed.clone();
+        legacy[0] = LEGACY_SEALED_VERSION;
+        assert!(needs_reseal(&legacy));
+    }
 }
@@ -0,0 +1,405 @@
+/// 签名链中与设备相关的阶段
+/// - ServerChallenge：附加服务端签发的挑战值（见 challenge）
+/// - DeviceSignature：时间戳、nonce、设备指纹与签名；签名链的最后一个阶段（见 signing）
+use crate::canonical::CanonicalRequest;
+use crate::challenge::{ChallengeStore, CHALLENGE_HEADER};
+use crate::clock::ServerClock;
+use crate::error::AppError;
+use crate::fingerprint::{get_device_fingerprint, get_device_info_json};
+use crate::headers::HeaderList;
+use crate::session_key::{SessionKeys, SESSION_ID_HEADER, SESSION_KEY_HEADER};
+use crate::signature_scheme::{SignatureFields, SignatureSchemes, SIGNATURE_VERSION_HEADER};
+use crate::signing::{RequestSigner, SigningRequest};
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 设备签名：时间戳、设备指纹、设备信息哈希、规范请求哈希与 nonce 按当前签名方案
+/// （见 signature_scheme，版本通过 X-Signature-Version 发送）组成签名数据并用公钥加密，
+/// 有会话时改为会话密钥的 HMAC-SHA256（见 session_key）。
+/// 参与签名的请求头列表通过 X-Signed-Headers 告知后端，nonce 通过 X-Nonce 发送。
+/// 后端在时间戳窗口内记录已用过的 nonce，重复出现即视为重放。
+/// 当前方案的签名数据不含规范请求与 nonce（v1）时，nonce、会话与挑战都不发送：
+/// 它们不受签名保护，可以被随意替换，发出去只会给后端错误的保证
+pub struct DeviceSignature {
+    /// 参与签名的请求头名称（小写）
+    header_names: Vec<String>,
+    clock: ServerClock,
+    sessions: SessionKeys,
+    schemes: SignatureSchemes,
+}
+
+impl DeviceSignature {
+    /// 挑战值、会话 ID 与交换请求中的会话密钥总是参与签名，不受配置影响
+    pub fn new(
+        mut header_names: Vec<String>,
+        clock: ServerClock,
+        sessions: SessionKeys,
+        schemes: SignatureSchemes,
+    ) -> Self {
+        for name in [CHALLENGE_HEADER, SESSION_ID_HEADER, SESSION_KEY_HEADER] {
+            let name = name.to_ascii_lowercase();
+            if !header_names.contains(&name) {
+                header_names.push(name);
+            }
+        }
+        header_names.sort();
+        Self {
+            header_names,
+            clock,
+            sessions,
+            schemes,
+        }
+    }
+}
+
+/// 每个请求一个的随机数（128 位，十六进制）
+fn generate_nonce() -> String {
+    hex::encode(rand::random::<[u8; 16]>())
+}
+
+impl RequestSigner for DeviceSignature {
+    fn name(&self) -> &'static str {
+        "device-signature"
+    }
+
+    fn sign(&self, request: &SigningRequest<'_>, out: &mut HeaderList) -> Result<(), AppError> {
+        if !request.signed {
+            log!("🔓 非签名来源，不附加验证头: {}", request.url);
+            return Ok(());
+        }
+
+        // 生成时间戳（校正到服务器时间）与一次性随机数
+        let timestamp = self.clock.now().to_rfc3339();
+        self.sign_with(request, out, timestamp, generate_nonce())
+    }
+}
+
+impl DeviceSignature {
+    fn sign_with(
+        &self,
+        request: &SigningRequest<'_>,
+        out: &mut HeaderList,
+        timestamp: String,
+        nonce: String,
+    ) -> Result<(), AppError> {
+        let scheme = self.schemes.active();
+        let covered = scheme.covers_request();
+
+        // 有会话时改用会话密钥签名，会话 ID 参与规范请求
+        let session = self.sessions.current().filter(|_| covered);
+        if let Some(session) = &session {
+            out.set(SESSION_ID_HEADER, session.id.clone());
+        }
+
+        // 获取设备指纹
+        let device_fingerprint = get_device_fingerprint();
+
+        // 获取设备详细信息（原始 JSON 用于签名计算）
+        let device_info = get_device_info_json();
+
+        // URL 编码后用于 HTTP 头传输（确保特殊字符不被改变）
+        let device_info_encoded = urlencoding::encode(&device_info).to_string();
+
+        // 规范请求覆盖实际发出的请求头：页面头在前，前面阶段追加的在后
+        let sent: HeaderList = request
+            .headers
+            .forwardable_request()
+            .iter()
+            .chain(out.iter())
+            .collect();
+        let canonical = CanonicalRequest::new(
+            request.method,
+            request.url,
+            &sent,
+            &self.header_names,
+            request.body_hash,
+        )?;
+        let canonical_hash = canonical.hash();
+
+        // 按当前方案生成签名数据
+        // 注意：签名使用原始 JSON，HTTP 头使用编码后的 JSON
+        let signature_data = scheme.payload(&SignatureFields {
+            timestamp: &timestamp,
+            fingerprint: &device_fingerprint,
+            device_info_json: &device_info,
+            url: request.url,
+            canonical_hash: &canonical_hash,
+            nonce: &nonce,
+        });
+
+        log!("\n🔐 安全验证信息 (v{}):", scheme.version());
+        log!(
+            "   📝 Canonical Request:\n{}",
+            canonical.to_canonical_string()
+        );
+        log!("   ⏰ Timestamp: {}", timestamp);
+        log!("   🎲 Nonce: {}", nonce);
+        log!("   🖥️  Device Fingerprint: {}", device_fingerprint);
+        log!("   📝 Signature Data: {}", signature_data);
+
+        let client_signature = match &session {
+            // 会话模式：HMAC-SHA256（服务端用交换时收到的会话密钥验证）
+            Some(session) => {
+                log!("   🔑 Session: {}", session.id);
+                session.sign(&signature_data)
+            }
+            // 使用公钥加密签名（服务端用私钥解密验证）
+            None => scheme.seal(&signature_data)?,
+        };
+
+        log!("   🔒 Client Signature: {}", client_signature);
+
+        out.set("X-Client-Signature", client_signature);
+        out.set(SIGNATURE_VERSION_HEADER, scheme.version().to_string());
+        out.set("X-Timestamp", timestamp);
+        if covered {
+            out.set("X-Nonce", nonce);
+        }
+        out.set("X-Device-Fingerprint", device_fingerprint);
+        out.set("X-Device-Info", device_info_encoded);
+        out.set("X-Signed-Headers", canonical.signed_headers());
+        Ok(())
+    }
+}
+
+/// 附加服务端签发的挑战值（未启用、尚未领取或当前方案不覆盖规范请求时不附加）
+pub struct ServerChallenge {
+    challenge: ChallengeStore,
+    schemes: SignatureSchemes,
+}
+
+impl ServerChallenge {
+    pub fn new(challenge: ChallengeStore, schemes: SignatureSchemes) -> Self {
+        Self { challenge, schemes }
+    }
+}
+
+impl RequestSigner for ServerChallenge {
+    fn name(&self) -> &'static str {
+        "server-challenge"
+    }
+
+    fn sign(&self, request: &SigningRequest<'_>, out: &mut HeaderList) -> Result<(), AppError> {
+        if !request.signed || !self.schemes.active().covers_request() {
+            return Ok(());
+        }
+        if let Some(challenge) = self.challenge.current() {
+            out.set(CHALLENGE_HEADER, challenge);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::{signed_header_names, EMPTY_BODY_HASH};
+    use crate::signature_scheme::{SchemeV1, SchemeV2};
+    use sha2::{Digest, Sha256};
+
+    fn request<'a>(headers: &'a HeaderList, signed: bool) -> SigningRequest<'a> {
+        SigningRequest {
+            id: Some("req-1"),
+            method: "GET",
+            url: "https://example.com/base_api/dict/list?type=1",
+            headers,
+            body_hash: EMPTY_BODY_HASH,
+            signed,
+        }
+    }
+
+    #[test]
+    fn test_device_signature_headers() {
+        let stage = DeviceSignature::new(
+            vec!["authorization".into(), "content-type".into()],
+            ServerClock::new(30),
+            SessionKeys::from_env("https://example.com"),
+            SignatureSchemes::from_env(),
+        );
+        let headers: HeaderList = [("Content-Type", "application/json")].into_iter().collect();
+        let mut out: HeaderList = [("Authorization", "Bearer abc")].into_iter().collect();
+        stage.sign(&request(&headers, true), &mut out).unwrap();
+
+        for name in [
+            "X-Client-Signature",
+            "X-Signature-Version",
+            "X-Timestamp",
+            "X-Device-Fingerprint",
+            "X-Device-Info",
+        ] {
+            assert!(
+                out.get(name).is_some_and(|v| !v.is_empty()),
+                "缺少 {}",
+                name
+            );
+        }
+        assert!(chrono::DateTime::parse_from_rfc3339(out.get("X-Timestamp").unwrap()).is_ok());
+        assert_eq!(
+            out.get("X-Device-Fingerprint"),
+            Some(get_device_fingerprint().as_str())
+        );
+        // 前面阶段追加的 Authorization 与页面的 Content-Type 都参与签名
+        assert_eq!(
+            out.get("X-Signed-Headers"),
+            Some("authorization;content-type")
+        );
+
+        // 每次签名的 nonce 都不同
+        let mut again = HeaderList::default();
+        stage.sign(&request(&headers, true), &mut again).unwrap();
+        assert_eq!(out.get("X-Nonce").map(str::len), Some(32));
+        assert_ne!(out.get("X-Nonce"), again.get("X-Nonce"));
+    }
+
+    #[test]
+    fn test_device_signature_session_mode() {
+        let stage = DeviceSignature::new(
+            vec!["authorization".into()],
+            ServerClock::new(30),
+            SessionKeys::with_session("s1", [7; 32]),
+            SignatureSchemes::new(vec![Box::new(SchemeV1), Box::new(SchemeV2)], 2),
+        );
+        let headers = HeaderList::default();
+        let mut out = HeaderList::default();
+        stage.sign(&request(&headers, true), &mut out).unwrap();
+
+        assert_eq!(out.get("X-Session-Id"), Some("s1"));
+        assert_eq!(out.get("X-Signature-Version"), Some("2"));
+        assert_eq!(out.get("X-Signed-Headers"), Some("x-session-id"));
+        // HMAC-SHA256 为 32 字节，Base64 后 44 个字符
+        assert_eq!(out.get("X-Client-Signature").map(str::len), Some(44));
+    }
+
+    #[test]
+    fn test_default_scheme_covers_body_and_query() {
+        // 默认签名方案；会话模式的 HMAC 是确定的，便于比较
+        let stage = DeviceSignature::new(
+            signed_header_names(),
+            ServerClock::new(30),
+            SessionKeys::with_session("s1", [7; 32]),
+            SignatureSchemes::from_env(),
+        );
+        let headers = HeaderList::default();
+        let signature = |url: &str, body_hash: &str| {
+            let mut out = HeaderList::default();
+            let request = SigningRequest {
+                url,
+                body_hash,
+                ..request(&headers, true)
+            };
+            stage
+                .sign_with(
+                    &request,
+                    &mut out,
+                    "2024-01-01T00:00:00+00:00".into(),
+                    "n1".into(),
+                )
+                .unwrap();
+            out.get("X-Client-Signature").unwrap().to_string()
+        };
+
+        let base = "https://example.com/base_api/dict/list?type=1";
+        let original = signature(base, EMPTY_BODY_HASH);
+        assert_eq!(original, signature(base, EMPTY_BODY_HASH));
+        assert_ne!(
+            original,
+            signature(
+                "https://example.com/base_api/dict/list?type=2",
+                EMPTY_BODY_HASH
+            ),
+            "查询串应受签名保护"
+        );
+        assert_ne!(
+            original,
+            signature(base, &hex::encode(Sha256::digest(b"{}"))),
+            "请求体应受签名保护"
+        );
+    }
+
+    #[test]
+    fn test_device_signature_skips_unsigned_origin() {
+        let headers = HeaderList::default();
+        let mut out = HeaderList::default();
+        DeviceSignature::new(
+            signed_header_names(),
+            ServerClock::new(30),
+            SessionKeys::from_env("https://example.com"),
+            SignatureSchemes::from_env(),
+        )
+        .sign(&request(&headers, false), &mut out)
+        .unwrap();
+        assert_eq!(out, HeaderList::default());
+    }
+
+    #[test]
+    fn test_v1_omits_unsigned_replay_headers() {
+        // v1 的签名数据不含 nonce 与规范请求：nonce、会话 ID 与挑战都不应发出
+        let schemes = SignatureSchemes::new(vec![Box::new(SchemeV1), Box::new(SchemeV2)], 1);
+        let store = ChallengeStore::from_env("https://example.com");
+        let mut issued = reqwest::header::HeaderMap::new();
+        issued.insert(CHALLENGE_HEADER, "c-1".parse().unwrap());
+        store.on_rechallenge(&issued);
+
+        let headers = HeaderList::default();
+        let mut out = HeaderList::default();
+        ServerChallenge::new(store, schemes.clone())
+            .sign(&request(&headers, true), &mut out)
+            .unwrap();
+        DeviceSignature::new(
+            signed_header_names(),
+            ServerClock::new(30),
+            SessionKeys::with_session("s1", [7; 32]),
+            schemes,
+        )
+        .sign(&request(&headers, true), &mut out)
+        .unwrap();
+
+        assert_eq!(out.get("X-Signature-Version"), Some("1"));
+        assert!(out.get("X-Client-Signature").is_some());
+        for name in ["X-Nonce", "X-Session-Id", CHALLENGE_HEADER] {
+            assert_eq!(out.get(name), None, "{} 不受 v1 签名保护", name);
+        }
+    }
+
+    #[test]
+    fn test_server_challenge() {
+        let store = ChallengeStore::from_env("https://example.com");
+        let stage = ServerChallenge::new(store.clone(), SignatureSchemes::from_env());
+        let empty = HeaderList::default();
+
+        let mut out = HeaderList::default();
+        stage.sign(&request(&empty, true), &mut out).unwrap();
+        assert_eq!(out.get(CHALLENGE_HEADER), None, "尚未领取挑战时不应附加");
+
+        let mut issued = reqwest::header::HeaderMap::new();
+        issued.insert(CHALLENGE_HEADER, "c-1".parse().unwrap());
+        store.on_rechallenge(&issued);
+
+        let mut out = HeaderList::default();
+        stage.sign(&request(&empty, true), &mut out).unwrap();
+        assert_eq!(out.get(CHALLENGE_HEADER), Some("c-1"));
+
+        let mut out = HeaderList::default();
+        stage.sign(&request(&empty, false), &mut out).unwrap();
+        assert_eq!(out.get(CHALLENGE_HEADER), None, "非签名来源不应收到挑战");
+    }
+}
@@ -0,0 +1,345 @@
+/// 代理请求发送模块
+/// 构建请求（复制请求头、运行签名链、写入请求体），再按策略发送：熔断检查、按主机限流、
+/// 等待响应头超时与幂等方法的退避重试；挑战失效、会话被拒、签名版本切换或时间戳过期时
+/// 修正后立即重发一次。proxy / stream / sse 以及重定向的每一跳都经过这里
+use std::time::{Duration, Instant};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::sync::OwnedSemaphorePermit;
+
+use crate::canonical::{body_hash, EMPTY_BODY_HASH};
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::limiter::host_key;
+use crate::multipart::build_multipart;
+use crate::origin_policy::OriginAccess;
+use crate::proxy::{AppState, ProxyRequest};
+use crate::resilience::{
+    backoff_delay, is_backend_failure_status, is_idempotent, is_retryable_error,
+    is_retryable_status, parse_retry_after, retry_after_too_long, EffectiveTimeouts,
+};
+use crate::signature_scheme::rejected_by_version;
+use crate::signing::SigningRequest;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 开发模式下随响应回显的完整请求信息（包括签名链追加的安全头）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyDebugInfo {
+    pub request_method: String,
+    pub request_url: String,
+    pub request_headers: HeaderList,
+    pub request_body: Option<String>,
+    pub response_status: u16,
+    pub response_headers: HeaderList,
+}
+
+/// 待发送的请求
+pub(crate) struct PreparedRequest {
+    pub builder: reqwest::RequestBuilder,
+    /// 签名链追加的请求头（用于调试信息回显）
+    pub signed_headers: HeaderList,
+}
+
+/// 构建请求：复制原始请求头、附加验证头、设置请求体
+/// body_hash 为签名覆盖的请求体哈希，由调用方计算一次后在各次尝试间复用
+pub(crate) async fn prepare_request(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    request: &ProxyRequest,
+    body_hash: &str,
+) -> Result<PreparedRequest, AppError> {
+    let method = parse_method(&request.method)?;
+    // 不在允许范围内的来源直接拒绝（重定向的每一跳都会经过这里）
+    let sign = state.origins.check(&request.url).inspect_err(|_| {
+        log!("⛔ 来源不在代理允许范围内: {}", request.url);
+    })?;
+
+    // 1. Build the request
+    state.http.prepare_proxy(&request.url).await;
+    let mut req_builder = state.http.client().request(method, &request.url);
+
+    // 2. Copy headers（保留顺序与重复项，跳过逐跳头和 Tauri 内部头）
+    log!("📤 原始请求头:");
+    let forwarded = request.headers.forwardable_request();
+    for (k, v) in forwarded.iter() {
+        log!("   {} : {}", k, v);
+    }
+    req_builder = forwarded.apply_to(req_builder);
+
+    // 3. 运行签名链：验证头只发给签名来源，其余允许的来源原样转发
+    if sign {
+        state.challenge.ensure(state).await?;
+        state.sessions.ensure(state).await;
+    }
+    let signed_headers = state.signers.sign(&SigningRequest {
+        id: request.id.as_deref(),
+        method: &request.method,
+        url: &request.url,
+        headers: &request.headers,
+        body_hash,
+        signed: sign,
+    })?;
+    log!("\n✅ 签名链追加的请求头:");
+    for (k, v) in signed_headers.iter() {
+        log!("   {} : {}", k, v);
+    }
+    req_builder = signed_headers.apply_to(req_builder);
+
+    // 4. Set body (优先处理 multipart，其次是普通 body)
+    if let Some(parts) = request.multipart_parts() {
+        // 文件上传请求，使用 multipart/form-data，文件内容以流的形式写入
+        log!("\n📦 文件上传请求，构建 multipart/form-data");
+        log!("   表单项数量: {}", parts.len());
+
+        let form = build_multipart(
+            app,
+            &state.uploads,
+            request.webview.as_deref(),
+            request.id.as_deref(),
+            &parts,
+        ).await?;
+        req_builder = req_builder.multipart(form);
+    } else if let Some(body) = &request.body {
+        // 普通请求体（JSON、文本等）
+        log!("\n📦 请求体: {} bytes", body.len());
+        req_builder = req_builder.body(body.clone());
+    }
+
+    Ok(PreparedRequest {
+        builder: req_builder,
+        signed_headers,
+    })
+}
+
+/// 一次成功拿到响应头的发送结果
+pub(crate) struct SentRequest {
+    pub response: reqwest::Response,
+    pub permit: OwnedSemaphorePermit,
+    pub signed_headers: HeaderList,
+}
+
+/// 按策略发送请求：熔断检查、等待响应头超时、幂等方法的退避重试
+/// 每次尝试都重新签名（时间戳随之更新）并重新获取并发许可；许可在签名完成后、发送前才获取，
+/// 领取挑战、交换密钥与退避期间都不占用许可
+pub(crate) async fn send_with_policy(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    request: &ProxyRequest,
+    timeouts: EffectiveTimeouts,
+    deadline: Instant,
+) -> Result<SentRequest, AppError> {
+    let method = parse_method(&request.method)?;
+    let can_retry = is_idempotent(&method);
+    let max_retries = request.max_retries.unwrap_or(state.policy.max_retries);
+    let host = host_key(&request.url);
+    let signed = state.origins.classify(&request.url) == OriginAccess::Signed;
+    let mut attempt: u32 = 0;
+    let mut rechallenged = false;
+    let mut resynced = false;
+    let mut rekeyed = false;
+    let mut renegotiated = false;
+    // 签名覆盖请求体：哈希只算一次（multipart 需要读完所有文件），各次尝试共用
+    let body_hash = if signed {
+        body_hash(&state.uploads, request).await?
+    } else {
+        EMPTY_BODY_HASH.to_string()
+    };
+
+    loop {
+        if let Err(secs) = state.breakers.check(&host) {
+            log!("⛔ 后端熔断中，快速失败: {} ({} 秒后重试)", host, secs);
+            return Err(AppError::CircuitOpen {
+                host,
+                retry_after_secs: secs,
+            });
+        }
+
+        let PreparedRequest {
+            builder,
+            signed_headers,
+        } = prepare_request(app, state, request, &body_hash).await?;
+        let signed_offset = state.clock.offset_ms();
+        let permit = state.limiter.acquire(&request.url).await?;
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let wait = timeouts.connect.min(remaining);
+        let retries_left = can_retry && attempt < max_retries;
+
+        let sent_at = chrono::Utc::now();
+        let delay = match tokio::time::timeout(wait, builder.send()).await {
+            Ok(Ok(response)) => {
+                let status = response.status().as_u16();
+                if signed {
+                    state
+                        .clock
+                        .observe(response.headers(), sent_at, chrono::Utc::now());
+                }
+                if is_backend_failure_status(status) {
+                    state.breakers.record_failure(&host);
+                } else {
+                    state.breakers.record_success(&host);
+                }
+
+                let headers = response.headers();
+                // 挑战失效：重新领取后立即重发一次（请求未被后端处理，不占重试次数）
+                if signed
+                    && !rechallenged
+                    && state.challenge.requires_rechallenge(status, headers)
+                {
+                    state.challenge.on_rechallenge(headers);
+                    rechallenged = true;
+                    drop(permit);
+                    continue;
+                }
+                // 会话被拒绝：重新交换密钥后立即重发一次
+                if signed && !rekeyed && state.sessions.requires_rekey(headers) {
+                    state.sessions.on_rekey();
+                    rekeyed = true;
+                    drop(permit);
+                    continue;
+                }
+                // 签名方案版本协商：版本切换且请求因此被拒绝时，用新版本立即重发一次
+                if signed
+                    && !renegotiated
+                    && state.schemes.negotiate(headers)
+                    && rejected_by_version(status)
+                {
+                    renegotiated = true;
+                    drop(permit);
+                    continue;
+                }
+                // 签名时间戳过期：用校正后的服务器时间重新签名，立即重发一次
+                if signed
+                    && !resynced
+                    && state.clock.signature_expired(status, headers, signed_offset)
+                {
+                    log!("🕒 签名时间戳过期，按服务器时间重新签名");
+                    resynced = true;
+                    drop(permit);
+                    continue;
+                }
+                let delay = parse_retry_after(headers).unwrap_or_else(|| backoff_delay(attempt));
+                if !retries_left
+                    || !is_retryable_status(status)
+                    || retry_after_too_long(headers)
+                    || Instant::now() + delay >= deadline
+                {
+                    return Ok(SentRequest {
+                        response,
+                        permit,
+                        signed_headers,
+                    });
+                }
+                log!("🔁 收到 {}，{} ms 后重试 ({}/{})", status, delay.as_millis(), attempt + 1, max_retries);
+                delay
+            }
+            Ok(Err(e)) => {
+                log!("❌ 请求失败: {}", e);
+                if !is_retryable_error(&e) {
+                    return Err(e.into());
+                }
+                state.breakers.record_failure(&host);
+                let delay = backoff_delay(attempt);
+                if !retries_left || Instant::now() + delay >= deadline {
+                    return Err(e.into());
+                }
+                log!("🔁 {} ms 后重试 ({}/{})", delay.as_millis(), attempt + 1, max_retries);
+                delay
+            }
+            Err(_) => {
+                log!("⏱️  等待响应头超时: {} ms", wait.as_millis());
+                state.breakers.record_failure(&host);
+                let delay = backoff_delay(attempt);
+                if !retries_left || Instant::now() + delay >= deadline {
+                    return Err(AppError::Timeout(format!(
+                        "{} ms 内未收到响应",
+                        wait.as_millis()
+                    )));
+                }
+                log!("🔁 {} ms 后重试 ({}/{})", delay.as_millis(), attempt + 1, max_retries);
+                delay
+            }
+        };
+
+        drop(permit);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// 读取完整响应体；两个数据块间隔超过 read_timeout 或超过总截止时间即失败
+pub(crate) async fn read_body(
+    resp: reqwest::Response,
+    read_timeout: Duration,
+    deadline: Instant,
+) -> Result<Vec<u8>, AppError> {
+    let mut stream = resp.bytes_stream();
+    let mut body = Vec::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let wait = read_timeout.min(remaining);
+        match tokio::time::timeout(wait, stream.next()).await {
+            Ok(Some(chunk)) => body.extend_from_slice(&chunk?),
+            Ok(None) => return Ok(body),
+            Err(_) => {
+                return Err(AppError::Timeout(format!(
+                    "读取响应体时 {} ms 内未收到数据",
+                    wait.as_millis()
+                )))
+            }
+        }
+    }
+}
+
+pub(crate) fn parse_method(method: &str) -> Result<reqwest::Method, AppError> {
+    method
+        .parse::<reqwest::Method>()
+        .map_err(|e| AppError::InvalidRequest(format!("Invalid method {}: {}", method, e)))
+}
+
+/// 开发模式下回显完整的请求信息（包括安全头），生产环境返回 None
+pub(crate) fn build_debug_info(
+    request: &ProxyRequest,
+    signed_headers: &HeaderList,
+    status: u16,
+    response_headers: &HeaderList,
+) -> Option<ProxyDebugInfo> {
+    if !ENABLE_LOGS {
+        return None;
+    }
+
+    // 收集所有请求头（包括安全头）
+    let mut all_request_headers = request.headers.forwardable_request();
+    all_request_headers.merge_from(signed_headers, |_| false);
+
+    Some(ProxyDebugInfo {
+        request_method: request.method.clone(),
+        request_url: request.url.clone(),
+        request_headers: all_request_headers,
+        request_body: request.body.clone(),
+        response_status: status,
+        response_headers: response_headers.clone(),
+    })
+}
@@ -0,0 +1,69 @@
+/// v2 签名信封
+/// 随机 AES-256-GCM 密钥加密签名数据，密钥用 RSA-OAEP-SHA256 包裹；
+/// 公钥与 v1 签名共用 crypto::load_public_key 按环境选择的结果
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::Aes256Gcm;
+use base64::{engine::general_purpose, Engine as _};
+use rsa::{Oaep, RsaPublicKey};
+use sha2::Sha256;
+
+use crate::crypto::load_public_key;
+
+/// AES-GCM nonce 长度，验签端按它切分信封
+const NONCE_LEN: usize = 12;
+
+/// 混合加密：不受 RSA 单块长度限制。
+/// 格式：Base64(包裹后的密钥 | nonce(12) | 密文与 tag)
+pub fn encrypt_envelope(data: &str) -> Result<String, String> {
+    let public_key = load_public_key()?;
+    let envelope = seal_envelope(public_key, data.as_bytes())?;
+    Ok(general_purpose::STANDARD.encode(envelope))
+}
+
+fn seal_envelope(public_key: &RsaPublicKey, data: &[u8]) -> Result<Vec<u8>, String> {
+    let key = Aes256Gcm::generate_key(&mut OsRng);
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = Aes256Gcm::new(&key)
+        .encrypt(&nonce, data)
+        .map_err(|e| format!("Failed to encrypt payload: {}", e))?;
+
+    let mut rng = rand::thread_rng();
+    let wrapped_key = public_key
+        .encrypt(&mut rng, Oaep::new::<Sha256>(), key.as_slice())
+        .map_err(|e| format!("Failed to wrap key: {}", e))?;
+
+    let mut out = Vec::with_capacity(wrapped_key.len() + NONCE_LEN + ciphertext.len());
+    out.extend_from_slice(&wrapped_key);
+    out.extend_from_slice(&nonce);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes_gcm::{Key, Nonce};
+    use rsa::traits::PublicKeyParts;
+    use rsa::RsaPrivateKey;
+
+    #[test]
+    fn test_envelope_round_trip() {
+        let mut rng = rand::thread_rng();
+        let private_key = RsaPrivateKey::new(&mut rng, 1024).unwrap();
+        let public_key = RsaPublicKey::from(&private_key);
+        // 超过 RSA 单块上限的数据也能加密
+        let payload = "x".repeat(1000);
+        let envelope = seal_envelope(&public_key, payload.as_bytes()).unwrap();
+
+        // 与验签端相同的解法：先用私钥解出 AES 密钥，再解密数据
+        let (wrapped_key, rest) = envelope.split_at(public_key.size());
+        let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+        let key = private_key
+            .decrypt(Oaep::new::<Sha256>(), wrapped_key)
+            .unwrap();
+        let plain = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key))
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .unwrap();
+        assert_eq!(plain, payload.as_bytes());
+    }
+}
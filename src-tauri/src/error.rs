@@ -0,0 +1,333 @@
+/// 统一错误模型
+/// 所有 Tauri 命令返回 AppError，前端收到的是结构化对象：
+/// { code, message, retryable, status?, retry_after_secs?, detail? }
+/// code 为稳定的机器码，message 为面向用户的提示（按当前语言，见 Locale），detail 保留底层错误原文便于排查
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::error::Error;
+use std::fmt;
+
+pub use crate::error_message::Locale;
+use crate::tls::{find_pin_mismatch, PinMismatch};
+
+#[derive(Debug, Clone)]
+pub enum AppError {
+    /// 连接、等待响应头或读取响应体超时
+    Timeout(String),
+    /// 域名解析失败
+    Dns(String),
+    /// TLS 握手或证书校验失败
+    Tls(String),
+    /// 服务端证书公钥与环境配置的固定值不符（可能存在中间人）
+    PinMismatch(String),
+    /// 目标端口拒绝连接
+    ConnectionRefused(String),
+    /// 其他网络错误（连接被重置、响应体中断等）
+    Network(String),
+    /// 生成或加密请求签名失败
+    Signature(String),
+    /// 领取服务端签名挑战失败（挑战接口返回错误或格式不对）
+    Challenge(String),
+    /// 后端返回非成功状态（仅用于需要把状态码视为错误的命令，如 download_file）
+    HttpStatus(u16),
+    /// 文件读写失败
+    Io(String),
+    /// URL 无法解析
+    InvalidUrl(String),
+    /// 请求参数不合法（方法、请求头、Content-Type、上传句柄等）
+    InvalidRequest(String),
+    /// 文件或目录不存在
+    NotFound(String),
+    /// 重定向不被允许（redirect: "error"）、次数过多或目标无效
+    Redirect(String),
+    /// 目标来源不在代理允许范围内
+    OriginDenied(String),
+    /// 请求被前端取消
+    Cancelled,
+    /// 后端连续失败，按主机熔断中
+    CircuitOpen { host: String, retry_after_secs: u64 },
+    /// 其他内部错误（窗口操作失败、锁中毒等）
+    Internal(String),
+}
+
+impl AppError {
+    /// 稳定的机器码，前端据此分支处理，不要随意改动
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Timeout(_) => "TIMEOUT",
+            AppError::Dns(_) => "DNS",
+            AppError::Tls(_) => "TLS",
+            AppError::PinMismatch(_) => "TLS_PIN_MISMATCH",
+            AppError::ConnectionRefused(_) => "CONNECTION_REFUSED",
+            AppError::Network(_) => "NETWORK",
+            AppError::Signature(_) => "SIGNATURE",
+            AppError::Challenge(_) => "CHALLENGE",
+            AppError::HttpStatus(_) => "HTTP_STATUS",
+            AppError::Io(_) => "IO",
+            AppError::InvalidUrl(_) => "INVALID_URL",
+            AppError::InvalidRequest(_) => "INVALID_REQUEST",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::Redirect(_) => "REDIRECT",
+            AppError::OriginDenied(_) => "ORIGIN_DENIED",
+            AppError::Cancelled => "CANCELLED",
+            AppError::CircuitOpen { .. } => "CIRCUIT_OPEN",
+            AppError::Internal(_) => "INTERNAL",
+        }
+    }
+
+    /// 稍后重试是否可能成功
+    pub fn retryable(&self) -> bool {
+        match self {
+            AppError::Timeout(_)
+            | AppError::Dns(_)
+            | AppError::ConnectionRefused(_)
+            | AppError::Network(_)
+            | AppError::Challenge(_)
+            | AppError::CircuitOpen { .. } => true,
+            AppError::HttpStatus(status) => matches!(status, 408 | 429 | 502..=504),
+            _ => false,
+        }
+    }
+
+    /// 底层错误原文
+    pub fn detail(&self) -> Option<&str> {
+        match self {
+            AppError::Timeout(detail)
+            | AppError::Dns(detail)
+            | AppError::Tls(detail)
+            | AppError::PinMismatch(detail)
+            | AppError::ConnectionRefused(detail)
+            | AppError::Network(detail)
+            | AppError::Signature(detail)
+            | AppError::Challenge(detail)
+            | AppError::Io(detail)
+            | AppError::InvalidUrl(detail)
+            | AppError::InvalidRequest(detail)
+            | AppError::NotFound(detail)
+            | AppError::Redirect(detail)
+            | AppError::OriginDenied(detail)
+            | AppError::Internal(detail) => Some(detail),
+            AppError::CircuitOpen { host, .. } => Some(host),
+            AppError::HttpStatus(_) | AppError::Cancelled => None,
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, AppError::Cancelled)
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.detail() {
+            Some(detail) => write!(f, "[{}] {}: {}", self.code(), self.message(), detail),
+            None => write!(f, "[{}] {}", self.code(), self.message()),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl Serialize for AppError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("AppError", 6)?;
+        s.serialize_field("code", self.code())?;
+        s.serialize_field("message", &self.message())?;
+        s.serialize_field("retryable", &self.retryable())?;
+        match self {
+            AppError::HttpStatus(status) => s.serialize_field("status", status)?,
+            _ => s.skip_field("status")?,
+        }
+        match self {
+            AppError::CircuitOpen {
+                retry_after_secs, ..
+            } => s.serialize_field("retry_after_secs", retry_after_secs)?,
+            _ => s.skip_field("retry_after_secs")?,
+        }
+        match self.detail() {
+            Some(detail) => s.serialize_field("detail", detail)?,
+            None => s.skip_field("detail")?,
+        }
+        s.end()
+    }
+}
+
+impl From<reqwest::Error> for AppError {
+    /// 按错误来源链细分网络错误
+    fn from(error: reqwest::Error) -> Self {
+        let detail = error.to_string();
+
+        if error.is_timeout() {
+            return AppError::Timeout(detail);
+        }
+        if error.is_builder() && error.url().is_none() {
+            return AppError::InvalidUrl(detail);
+        }
+
+        if let Some(mismatch) = find_pin_mismatch(&error) {
+            return mismatch.into();
+        }
+
+        let mut source = error.source();
+        while let Some(cause) = source {
+            if let Some(kind) = classify(cause) {
+                return kind(detail);
+            }
+            source = cause.source();
+        }
+
+        if error.is_builder() {
+            return AppError::InvalidRequest(detail);
+        }
+        AppError::Network(detail)
+    }
+}
+
+/// 按错误类型识别来源链中的一环；无法识别时返回 None，继续检查下一环
+fn classify(cause: &(dyn Error + 'static)) -> Option<fn(String) -> AppError> {
+    if cause.is::<DnsError>() {
+        return Some(AppError::Dns);
+    }
+    if cause.is::<rustls::Error>() || cause.is::<native_tls::Error>() {
+        return Some(AppError::Tls);
+    }
+    if let Some(io) = cause.downcast_ref::<std::io::Error>() {
+        match io.kind() {
+            std::io::ErrorKind::ConnectionRefused => return Some(AppError::ConnectionRefused),
+            std::io::ErrorKind::TimedOut => return Some(AppError::Timeout),
+            _ => {}
+        }
+        // io::Error::source() 跳过了它包装的错误本身（TLS 握手错误就包装在这里），单独检查
+        if let Some(inner) = io.get_ref() {
+            return classify(inner);
+        }
+    }
+    if let Some(hyper) = cause.downcast_ref::<hyper::Error>() {
+        if hyper.is_timeout() {
+            return Some(AppError::Timeout);
+        }
+    }
+    None
+}
+
+/// 域名解析失败；出站客户端的 DNS 解析器（http_client::SystemResolver）返回这个类型，
+/// 错误分类据此识别，不依赖错误文本
+#[derive(Debug)]
+pub struct DnsError {
+    pub host: String,
+    pub source: std::io::Error,
+}
+
+impl fmt::Display for DnsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to resolve {}: {}", self.host, self.source)
+    }
+}
+
+impl Error for DnsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<DnsError> for AppError {
+    fn from(error: DnsError) -> Self {
+        AppError::Dns(error.to_string())
+    }
+}
+
+impl From<PinMismatch> for AppError {
+    fn from(mismatch: PinMismatch) -> Self {
+        AppError::PinMismatch(mismatch.to_string())
+    }
+}
+
+impl From<std::io::Error> for AppError {
+    fn from(error: std::io::Error) -> Self {
+        match error.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound(error.to_string()),
+            _ => AppError::Io(error.to_string()),
+        }
+    }
+}
+
+impl From<tauri::Error> for AppError {
+    fn from(error: tauri::Error) -> Self {
+        AppError::Internal(error.to_string())
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for AppError {
+    /// WebSocket 握手与收发错误；I/O 错误与 reqwest 一样按类型分类（域名解析由 ws_transport 自行完成）
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        use tokio_tungstenite::tungstenite::Error as WsError;
+
+        let detail = error.to_string();
+        match error {
+            WsError::Io(io) => match classify(&io) {
+                Some(kind) => kind(detail),
+                None => AppError::Network(detail),
+            },
+            WsError::Tls(_) => AppError::Tls(detail),
+            WsError::Http(response) => AppError::HttpStatus(response.status().as_u16()),
+            WsError::Url(_) => AppError::InvalidUrl(detail),
+            WsError::HttpFormat(_) => AppError::InvalidRequest(detail),
+            _ => AppError::Network(detail),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn kind_of(cause: &(dyn Error + 'static)) -> Option<&'static str> {
+        classify(cause).map(|kind| kind(String::new()).code())
+    }
+
+    #[test]
+    fn test_classify_by_type() {
+        let dns = DnsError {
+            host: "example.invalid".to_string(),
+            source: io::Error::other("no such host"),
+        };
+        assert_eq!(kind_of(&dns), Some("DNS"));
+
+        let refused = io::Error::from(io::ErrorKind::ConnectionRefused);
+        assert_eq!(kind_of(&refused), Some("CONNECTION_REFUSED"));
+        assert_eq!(
+            kind_of(&io::Error::from(io::ErrorKind::TimedOut)),
+            Some("TIMEOUT")
+        );
+
+        // TLS 握手错误包装在 io::Error 中
+        let tls = io::Error::new(
+            io::ErrorKind::InvalidData,
+            rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer),
+        );
+        assert_eq!(kind_of(&tls), Some("TLS"));
+
+        // 文本里带 tls 字样不再被当作证书错误
+        let reset = io::Error::new(io::ErrorKind::ConnectionReset, "tls connection reset");
+        assert_eq!(kind_of(&reset), None);
+    }
+
+    #[test]
+    fn test_websocket_errors_by_type() {
+        use tokio_tungstenite::tungstenite::Error as WsError;
+
+        let refused = WsError::Io(io::Error::from(io::ErrorKind::ConnectionRefused));
+        assert_eq!(AppError::from(refused).code(), "CONNECTION_REFUSED");
+
+        let tls = WsError::Io(io::Error::new(
+            io::ErrorKind::InvalidData,
+            rustls::Error::InvalidCertificate(rustls::CertificateError::Expired),
+        ));
+        assert_eq!(AppError::from(tls).code(), "TLS");
+
+        // 只看类型，不看文本
+        let text = WsError::Io(io::Error::other("failed to lookup address information"));
+        assert_eq!(AppError::from(text).code(), "NETWORK");
+    }
+}
@@ -0,0 +1,139 @@
+/// 错误提示文案
+/// AppError::message 面向用户展示，按当前语言从下面的文案表中选取；code 与 detail 不随语言变化。
+/// 新增 AppError 变体时两张表都要补上
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::error::AppError;
+
+/// 面向用户提示的语言；页面启动时按 navigator.language 设置（set_locale），
+/// 之前使用 TAURI_LOCALE（默认中文）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Locale {
+    Zh,
+    En,
+}
+
+/// 0 表示尚未设置
+static CURRENT_LOCALE: AtomicU8 = AtomicU8::new(0);
+
+impl Locale {
+    /// 按 BCP 47 语言标签（zh-CN、en-US 等）选择；没有对应文案的语言使用英文
+    pub fn from_tag(tag: &str) -> Self {
+        if tag.trim().to_ascii_lowercase().starts_with("zh") {
+            Locale::Zh
+        } else {
+            Locale::En
+        }
+    }
+
+    pub fn current() -> Self {
+        match CURRENT_LOCALE.load(Ordering::Relaxed) {
+            1 => Locale::Zh,
+            2 => Locale::En,
+            _ => option_env!("TAURI_LOCALE").map_or(Locale::Zh, Locale::from_tag),
+        }
+    }
+
+    pub fn set_current(self) {
+        let value = match self {
+            Locale::Zh => 1,
+            Locale::En => 2,
+        };
+        CURRENT_LOCALE.store(value, Ordering::Relaxed);
+    }
+}
+
+impl AppError {
+    /// 面向用户的提示（不含底层细节），使用当前语言
+    pub fn message(&self) -> String {
+        self.message_in(Locale::current())
+    }
+
+    pub fn message_in(&self, locale: Locale) -> String {
+        match locale {
+            Locale::Zh => self.message_zh(),
+            Locale::En => self.message_en(),
+        }
+    }
+
+    fn message_zh(&self) -> String {
+        match self {
+            AppError::Timeout(_) => "请求超时，请稍后重试".to_string(),
+            AppError::Dns(_) => "无法解析服务器地址，请检查网络".to_string(),
+            AppError::Tls(_) => "安全连接失败（证书校验未通过）".to_string(),
+            AppError::PinMismatch(_) => {
+                "服务器证书与预置的公钥不一致，连接已中止，请检查网络环境".to_string()
+            }
+            AppError::ConnectionRefused(_) => "服务器拒绝连接".to_string(),
+            AppError::Network(_) => "网络连接异常".to_string(),
+            AppError::Signature(_) => "请求签名失败".to_string(),
+            AppError::Challenge(_) => "无法获取服务端签名挑战，请稍后重试".to_string(),
+            AppError::HttpStatus(status) => format!("服务器返回错误状态 HTTP {}", status),
+            AppError::Io(_) => "文件读写失败".to_string(),
+            AppError::InvalidUrl(_) => "无效的地址".to_string(),
+            AppError::InvalidRequest(_) => "请求参数不合法".to_string(),
+            AppError::NotFound(_) => "文件或目录不存在".to_string(),
+            AppError::Redirect(_) => "请求被重定向，未能完成".to_string(),
+            AppError::OriginDenied(_) => "目标地址不在允许代理的范围内".to_string(),
+            AppError::Cancelled => "请求已取消".to_string(),
+            AppError::CircuitOpen {
+                retry_after_secs, ..
+            } => format!("服务暂时不可用，请 {} 秒后重试", retry_after_secs),
+            AppError::Internal(_) => "内部错误".to_string(),
+        }
+    }
+
+    fn message_en(&self) -> String {
+        match self {
+            AppError::Timeout(_) => "The request timed out, please try again later".to_string(),
+            AppError::Dns(_) => {
+                "Could not resolve the server address, please check your network".to_string()
+            }
+            AppError::Tls(_) => "Secure connection failed (certificate check failed)".to_string(),
+            AppError::PinMismatch(_) => "The server certificate does not match the pinned key, \
+                the connection was aborted; please check your network"
+                .to_string(),
+            AppError::ConnectionRefused(_) => "The server refused the connection".to_string(),
+            AppError::Network(_) => "Network connection error".to_string(),
+            AppError::Signature(_) => "Failed to sign the request".to_string(),
+            AppError::Challenge(_) => {
+                "Could not get the server signature challenge, please try again later".to_string()
+            }
+            AppError::HttpStatus(status) => format!("The server returned HTTP {}", status),
+            AppError::Io(_) => "Failed to read or write the file".to_string(),
+            AppError::InvalidUrl(_) => "Invalid address".to_string(),
+            AppError::InvalidRequest(_) => "Invalid request parameters".to_string(),
+            AppError::NotFound(_) => "File or directory not found".to_string(),
+            AppError::Redirect(_) => {
+                "The request was redirected and could not complete".to_string()
+            }
+            AppError::OriginDenied(_) => "The address is not allowed through the proxy".to_string(),
+            AppError::Cancelled => "The request was cancelled".to_string(),
+            AppError::CircuitOpen {
+                retry_after_secs, ..
+            } => format!(
+                "The service is temporarily unavailable, please retry in {} seconds",
+                retry_after_secs
+            ),
+            AppError::Internal(_) => "Internal error".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locale() {
+        assert_eq!(Locale::from_tag("zh-CN"), Locale::Zh);
+        assert_eq!(Locale::from_tag("ZH_tw"), Locale::Zh);
+        assert_eq!(Locale::from_tag("en-US"), Locale::En);
+        assert_eq!(Locale::from_tag("ja"), Locale::En);
+
+        let error = AppError::HttpStatus(502);
+        assert_eq!(error.message_in(Locale::Zh), "服务器返回错误状态 HTTP 502");
+        assert_eq!(error.message_in(Locale::En), "The server returned HTTP 502");
+        assert_eq!(error.code(), "HTTP_STATUS");
+    }
+}
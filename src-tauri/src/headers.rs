@@ -0,0 +1,208 @@
+/// HTTP 头列表
+/// 保留重复头（Set-Cookie、Link、Vary 等）与原始顺序，替代会吞掉重复值的 HashMap。
+/// 与前端之间以 [[name, value], ...] 传输，也兼容旧的 { name: value } 对象写法。
+/// 头的值是字节串：按 Latin-1 逐字节映射为字符（与浏览器 ByteString 一致），
+/// 转回请求头时再按同样规则还原，保证原始字节不丢失
+use serde::de::{MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// 逐跳头：只对单个连接有意义，代理不转发（RFC 9110 §7.6.1）
+const HOP_BY_HOP: [&str; 8] = [
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// 由 reqwest 按实际目标与请求体重新生成的请求头
+const REGENERATED_REQUEST_HEADERS: [&str; 2] = ["host", "content-length"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderList(Vec<(String, String)>);
+
+impl HeaderList {
+    /// 从 reqwest 的 HeaderMap 收集，同名的多个值逐条保留
+    pub fn from_header_map(map: &reqwest::header::HeaderMap) -> Self {
+        Self(
+            map.iter()
+                .map(|(name, value)| (name.as_str().to_string(), bytes_to_string(value.as_bytes())))
+                .collect(),
+        )
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// 第一个同名值（大小写不敏感）
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// 替换所有同名头
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        self.remove(&name);
+        self.0.push((name, value.into()));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.0.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+    }
+
+    /// 用另一组头覆盖同名头（按名称整体替换），skip 返回 true 的名称保持原值
+    pub fn merge_from(&mut self, other: &HeaderList, skip: impl Fn(&str) -> bool) {
+        let mut replaced: Vec<String> = Vec::new();
+        for (name, value) in &other.0 {
+            if skip(name) {
+                continue;
+            }
+            let lower = name.to_ascii_lowercase();
+            if !replaced.contains(&lower) {
+                self.remove(name);
+                replaced.push(lower);
+            }
+            self.0.push((name.clone(), value.clone()));
+        }
+    }
+
+    /// 可以转发给后端的请求头：去掉逐跳头、Connection 中列出的头、
+    /// Host / Content-Length 以及 Tauri 内部头
+    pub fn forwardable_request(&self) -> HeaderList {
+        self.without_hop_by_hop(|name| {
+            name.starts_with("tauri-") || REGENERATED_REQUEST_HEADERS.contains(&name)
+        })
+    }
+
+    /// 可以交给页面的响应头：去掉逐跳头与 Connection 中列出的头
+    pub fn forwardable_response(&self) -> HeaderList {
+        self.without_hop_by_hop(|_| false)
+    }
+
+    fn without_hop_by_hop(&self, also_drop: impl Fn(&str) -> bool) -> HeaderList {
+        let connection_tokens: Vec<String> = self
+            .get_all("connection")
+            .flat_map(|v| v.split(','))
+            .map(|token| token.trim().to_ascii_lowercase())
+            .filter(|token| !token.is_empty())
+            .collect();
+
+        Self(
+            self.0
+                .iter()
+                .filter(|(name, _)| {
+                    let lower = name.to_ascii_lowercase();
+                    !HOP_BY_HOP.contains(&lower.as_str())
+                        && !connection_tokens.contains(&lower)
+                        && !also_drop(&lower)
+                })
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// 按原始字节还原的头（见 string_to_bytes），供 reqwest 以外的客户端使用
+    pub fn iter_bytes(&self) -> impl Iterator<Item = (&str, Vec<u8>)> {
+        self.0.iter().map(|(k, v)| (k.as_str(), string_to_bytes(v)))
+    }
+
+    /// 写入 reqwest 请求；同名头逐条追加，值按原始字节还原
+    pub fn apply_to(&self, mut builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        for (name, value) in self.iter_bytes() {
+            let (Ok(name), Ok(value)) = (
+                reqwest::header::HeaderName::from_bytes(name.as_bytes()),
+                reqwest::header::HeaderValue::from_bytes(&value),
+            ) else {
+                continue;
+            };
+            builder = builder.header(name, value);
+        }
+        builder
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for HeaderList {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self(iter.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+/// 字节 → 字符串：按 Latin-1 逐字节映射（ASCII 不变）
+fn bytes_to_string(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// 字符串 → 字节：全部字符都在 Latin-1 范围内时逐字符还原为单字节，否则按 UTF-8 编码
+fn string_to_bytes(value: &str) -> Vec<u8> {
+    if value.chars().all(|c| (c as u32) <= 0xFF) {
+        value.chars().map(|c| c as u8).collect()
+    } else {
+        value.as_bytes().to_vec()
+    }
+}
+
+impl Serialize for HeaderList {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for pair in &self.0 {
+            seq.serialize_element(pair)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for HeaderList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct HeaderListVisitor;
+
+        impl<'de> Visitor<'de> for HeaderListVisitor {
+            type Value = HeaderList;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a list of [name, value] pairs or a header object")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut headers = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(pair) = seq.next_element::<(String, String)>()? {
+                    headers.push(pair);
+                }
+                Ok(HeaderList(headers))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut headers = Vec::with_capacity(map.size_hint().unwrap_or(0));
+                while let Some(pair) = map.next_entry::<String, String>()? {
+                    headers.push(pair);
+                }
+                Ok(HeaderList(headers))
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                Ok(HeaderList::default())
+            }
+        }
+
+        deserializer.deserialize_any(HeaderListVisitor)
+    }
+}
@@ -0,0 +1,383 @@
+/// 代理层 HTTP 缓存模块
+/// 所有请求都经 Rust 代理发出，WebView 自带的 HTTP 缓存形同虚设，
+/// 因此在这里实现一个私有磁盘缓存：遵循 Cache-Control / Expires，过期后用
+/// If-None-Match / If-Modified-Since 重新验证；缓存键包含环境与签名身份
+/// （设备指纹 + 认证头 + Cookie 罐中该 URL 的 Cookie），按总大小做 LRU 淘汰。
+/// 登出或清空代理 Cookie 时清空当前环境的缓存，也可通过 purge_http_cache 手动清空。
+/// 缓存语义见 cache_policy，LRU 索引见 cache_index
+use reqwest::cookie::CookieStore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use tauri::{Manager, State};
+use tokio::sync::Mutex;
+
+use crate::cache_index::CacheIndex;
+use crate::cache_policy::{fresh_until, has_directive, is_storable, vary_matches, vary_values};
+use crate::cookie_jar::PersistentCookieJar;
+use crate::error::AppError;
+use crate::fingerprint::get_device_fingerprint;
+use crate::headers::HeaderList;
+use crate::proxy::{AppState, ProxyRequest};
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 默认缓存总大小上限（MB）
+const DEFAULT_MAX_MB: u64 = 200;
+
+/// 单个条目最多占总容量的 1/8，避免一个大文件挤掉所有字典/配置接口
+const MAX_ENTRY_FRACTION: u64 = 8;
+
+/// 响应头中标记缓存命中情况，便于在 DevTools 中排查
+pub const CACHE_STATUS_HEADER: &str = "x-proxy-cache";
+
+/// 参与身份划分的请求头：不同登录用户/令牌的响应互不可见
+const IDENTITY_HEADERS: [&str; 2] = ["authorization", "cookie"];
+
+/// 从编译期环境变量读取缓存上限（TAURI_PROXY_CACHE_MAX_MB），0 表示禁用缓存
+pub fn max_bytes_from_env() -> u64 {
+    option_env!("TAURI_PROXY_CACHE_MAX_MB")
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(DEFAULT_MAX_MB)
+        * 1024
+        * 1024
+}
+
+/// 磁盘上的条目元数据（<key>.json），响应体单独存放在 <key>.body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct EntryMeta {
+    url: String,
+    status: u16,
+    headers: HeaderList,
+    /// Vary 指定的请求头及存储时的取值
+    vary: Vec<(String, Option<String>)>,
+    /// 存储（或最近一次重新验证）时间，Unix 秒
+    stored_at: i64,
+    /// 新鲜期截止时间；None 表示每次使用前都必须重新验证
+    fresh_until: Option<i64>,
+    size: u64,
+}
+
+/// 一个命中的缓存条目
+pub(crate) struct CachedResponse {
+    key: String,
+    meta: EntryMeta,
+    pub body: Vec<u8>,
+}
+
+impl CachedResponse {
+    pub fn status(&self) -> u16 {
+        self.meta.status
+    }
+
+    pub fn is_fresh(&self) -> bool {
+        self.meta
+            .fresh_until
+            .is_some_and(|until| chrono::Utc::now().timestamp() < until)
+    }
+
+    /// 返回给前端的响应头：补充 Age 与命中标记
+    pub fn headers(&self, cache_status: &str) -> HeaderList {
+        let mut headers = self.meta.headers.clone();
+        let age = (chrono::Utc::now().timestamp() - self.meta.stored_at).max(0);
+        headers.set("age", age.to_string());
+        headers.set(CACHE_STATUS_HEADER, cache_status);
+        headers
+    }
+
+    /// 重新验证用的条件请求头
+    pub fn conditional_headers(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(etag) = self.meta.headers.get("etag") {
+            headers.push(("If-None-Match".to_string(), etag.to_string()));
+        }
+        if let Some(last_modified) = self.meta.headers.get("last-modified") {
+            headers.push(("If-Modified-Since".to_string(), last_modified.to_string()));
+        }
+        headers
+    }
+}
+
+pub struct HttpCache {
+    max_bytes: u64,
+    dir: OnceLock<PathBuf>,
+    /// 首次使用时扫描磁盘建立
+    index: Mutex<Option<CacheIndex>>,
+}
+
+impl HttpCache {
+    pub fn new(max_bytes: u64) -> Self {
+        Self {
+            max_bytes,
+            dir: OnceLock::new(),
+            index: Mutex::new(None),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.max_bytes > 0
+    }
+
+    /// 缓存目录：<app_cache_dir>/http-cache/<env_key>，不同环境互不干扰
+    fn dir(&self, app: &tauri::AppHandle) -> &Path {
+        self.dir.get_or_init(|| {
+            app.path()
+                .app_cache_dir()
+                .unwrap_or_else(|_| std::env::temp_dir().join("backstage68"))
+                .join("http-cache")
+                .join(crate::env_key())
+        })
+    }
+
+    /// 计算缓存键；请求不适合走缓存时返回 None
+    /// 页面自己带了条件请求头或 Range 时由页面自行处理，不介入。
+    /// Cookie 罐中的 Cookie 由 reqwest 在发送时附加，页面请求头里看不到，需单独计入
+    pub(crate) fn key_for(
+        &self,
+        request: &ProxyRequest,
+        cookies: &PersistentCookieJar,
+    ) -> Option<String> {
+        if !self.enabled()
+            || !request.method.eq_ignore_ascii_case("GET")
+            || request.body.is_some()
+            || request.multipart_parts().is_some()
+        {
+            return None;
+        }
+
+        let header = |name: &str| request.headers.get(name);
+        if header("range").is_some()
+            || header("if-none-match").is_some()
+            || header("if-modified-since").is_some()
+            || header("cache-control").is_some_and(|v| has_directive(v, "no-store"))
+        {
+            return None;
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(crate::env_key().as_bytes());
+        hasher.update(b"|");
+        hasher.update(get_device_fingerprint().as_bytes());
+        for name in IDENTITY_HEADERS {
+            hasher.update(b"|");
+            hasher.update(header(name).unwrap_or_default().as_bytes());
+        }
+        hasher.update(b"|");
+        if let Some(jar_cookies) = reqwest::Url::parse(&request.url)
+            .ok()
+            .and_then(|url| cookies.cookies(&url))
+        {
+            hasher.update(jar_cookies.as_bytes());
+        }
+        hasher.update(b"|GET|");
+        hasher.update(request.url.as_bytes());
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    /// 查找条目；Vary 指定的请求头与存储时不一致视为未命中
+    pub(crate) async fn lookup(
+        &self,
+        app: &tauri::AppHandle,
+        key: &str,
+        request: &ProxyRequest,
+    ) -> Option<CachedResponse> {
+        let dir = self.dir(app).to_path_buf();
+        self.ensure_index(&dir).await;
+
+        let meta = read_meta(&dir, key).await?;
+
+        if !vary_matches(&meta.vary, &request.headers) {
+            return None;
+        }
+
+        let body = tokio::fs::read(dir.join(format!("{}.body", key))).await.ok()?;
+        if body.len() as u64 != meta.size {
+            return None;
+        }
+
+        if let Some(index) = self.index.lock().await.as_mut() {
+            index.touch(key);
+        }
+
+        Some(CachedResponse {
+            key: key.to_string(),
+            meta,
+            body,
+        })
+    }
+
+    /// 写入可缓存的响应；不可缓存、过大或写盘失败时静默跳过
+    pub(crate) async fn store(
+        &self,
+        app: &tauri::AppHandle,
+        key: &str,
+        request: &ProxyRequest,
+        status: u16,
+        headers: &HeaderList,
+        body: &[u8],
+    ) {
+        let size = body.len() as u64;
+        if !is_storable(status, headers) || size > self.max_entry_bytes() {
+            return;
+        }
+
+        let meta = EntryMeta {
+            url: request.url.clone(),
+            status,
+            headers: headers.clone(),
+            vary: vary_values(headers, &request.headers),
+            stored_at: chrono::Utc::now().timestamp(),
+            fresh_until: fresh_until(headers),
+            size,
+        };
+
+        let dir = self.dir(app).to_path_buf();
+        if let Err(e) = write_entry(&dir, key, &meta, Some(body)).await {
+            log!("⚠️  [Cache] 写入失败: {}", e);
+            return;
+        }
+        log!("💾 [Cache] 已缓存: {} ({} bytes)", request.url, size);
+
+        self.ensure_index(&dir).await;
+        let mut guard = self.index.lock().await;
+        let index = guard.get_or_insert_with(CacheIndex::default);
+        index.insert(key.to_string(), size);
+        for (key, size) in index.evict(self.max_bytes) {
+            remove_entry(&dir, &key).await;
+            log!("🧹 [Cache] 淘汰: {} ({} bytes)", key, size);
+        }
+    }
+
+    /// 收到 304 后用新的响应头更新条目（新鲜期重新计算），返回更新后的条目
+    pub(crate) async fn revalidated(
+        &self,
+        app: &tauri::AppHandle,
+        mut cached: CachedResponse,
+        not_modified_headers: &HeaderList,
+    ) -> CachedResponse {
+        // 304 不携带实体相关的头，保留原值
+        cached.meta.headers.merge_from(not_modified_headers, |name| {
+            name.eq_ignore_ascii_case("content-length")
+                || name.eq_ignore_ascii_case("content-type")
+                || name.eq_ignore_ascii_case("content-encoding")
+                || name.eq_ignore_ascii_case(CACHE_STATUS_HEADER)
+        });
+        cached.meta.stored_at = chrono::Utc::now().timestamp();
+        cached.meta.fresh_until = fresh_until(&cached.meta.headers);
+
+        let dir = self.dir(app).to_path_buf();
+        if let Err(e) = write_entry(&dir, &cached.key, &cached.meta, None).await {
+            log!("⚠️  [Cache] 更新失败: {}", e);
+        }
+        cached
+    }
+
+    /// 清空缓存；指定 url_prefix 时只清除匹配的条目。返回释放的字节数
+    pub async fn purge(&self, app: &tauri::AppHandle, url_prefix: Option<&str>) -> u64 {
+        let dir = self.dir(app).to_path_buf();
+        self.ensure_index(&dir).await;
+
+        let mut guard = self.index.lock().await;
+        let Some(index) = guard.as_mut() else {
+            return 0;
+        };
+
+        let mut keys = Vec::new();
+        for key in index.keys() {
+            let matched = match url_prefix {
+                None => true,
+                Some(prefix) => read_meta(&dir, key)
+                    .await
+                    .is_some_and(|meta| meta.url.starts_with(prefix)),
+            };
+            if matched {
+                keys.push(key.clone());
+            }
+        }
+
+        let mut freed = 0;
+        for key in keys {
+            freed += index.remove(&key).unwrap_or(0);
+            remove_entry(&dir, &key).await;
+        }
+        freed
+    }
+
+    pub(crate) fn max_entry_bytes(&self) -> u64 {
+        self.max_bytes / MAX_ENTRY_FRACTION
+    }
+
+    /// 首次使用时扫描磁盘建立索引
+    async fn ensure_index(&self, dir: &Path) {
+        let mut guard = self.index.lock().await;
+        if guard.is_none() {
+            let index = CacheIndex::load(dir).await;
+            log!("💾 [Cache] 索引已加载: {} 条, {} bytes", index.len(), index.total());
+            *guard = Some(index);
+        }
+    }
+}
+
+async fn read_meta(dir: &Path, key: &str) -> Option<EntryMeta> {
+    let raw = tokio::fs::read(dir.join(format!("{}.json", key))).await.ok()?;
+    serde_json::from_slice(&raw).ok()
+}
+
+/// 先写临时文件再重命名，避免进程中断留下半个条目
+async fn write_entry(
+    dir: &Path,
+    key: &str,
+    meta: &EntryMeta,
+    body: Option<&[u8]>,
+) -> Result<(), AppError> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    if let Some(body) = body {
+        let tmp = dir.join(format!("{}.body.tmp", key));
+        tokio::fs::write(&tmp, body).await?;
+        tokio::fs::rename(&tmp, dir.join(format!("{}.body", key))).await?;
+    }
+
+    let raw = serde_json::to_vec(meta).map_err(|e| AppError::Internal(e.to_string()))?;
+    let tmp = dir.join(format!("{}.json.tmp", key));
+    tokio::fs::write(&tmp, raw).await?;
+    tokio::fs::rename(&tmp, dir.join(format!("{}.json", key))).await?;
+    Ok(())
+}
+
+async fn remove_entry(dir: &Path, key: &str) {
+    let _ = tokio::fs::remove_file(dir.join(format!("{}.body", key))).await;
+    let _ = tokio::fs::remove_file(dir.join(format!("{}.json", key))).await;
+}
+
+/// 清空代理 HTTP 缓存；url_prefix 为空时清空全部，返回释放的字节数
+#[tauri::command]
+pub async fn purge_http_cache(
+    app: tauri::AppHandle,
+    url_prefix: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<u64, AppError> {
+    let freed = state.cache.purge(&app, url_prefix.as_deref()).await;
+    log!("🧹 [Cache] 已清除 {} bytes", freed);
+    Ok(freed)
+}
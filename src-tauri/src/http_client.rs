@@ -0,0 +1,379 @@
+/// 出站 HTTP 客户端
+/// 代理请求使用的 Client 与 download_file 等临时 Client 都从这里构建，上游代理与 TLS 设置对所有客户端一致生效。
+/// 设置变化时重新构建代理 Client（reqwest::Client 内部是 Arc，取用时克隆，旧请求不受影响）
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+use tauri::{Manager, State};
+
+use crate::client_identity::ClientIdentity;
+use crate::cookie_jar::PersistentCookieJar;
+use crate::crypto::{needs_reseal, seal, unseal};
+use crate::error::{AppError, DnsError};
+use crate::pac::fetch_pac;
+use crate::proxy::AppState;
+use crate::tls::TlsPolicy;
+use crate::upstream_proxy::{ProxyMode, ProxyResolver, ProxySettings};
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 代理请求使用的 User-Agent
+const PROXY_USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 TauriApp/1.0";
+
+/// 加密用途标识（参与密钥派生）
+const SEAL_PURPOSE: &str = "network-proxy";
+
+/// 落盘格式：密码不随 ProxySettings 序列化，单独保存
+#[derive(Serialize, Deserialize)]
+struct StoredSettings {
+    #[serde(flatten)]
+    settings: ProxySettings,
+    password: Option<String>,
+}
+
+pub struct HttpClients {
+    cookies: Arc<PersistentCookieJar>,
+    connect_timeout: Duration,
+    env_key: String,
+    tls: Arc<TlsPolicy>,
+    /// 当前出示的客户端证书（mTLS）
+    identity: RwLock<Option<Arc<ClientIdentity>>>,
+    /// 配置了自定义 CA、证书固定或客户端证书时使用的 rustls 配置，否则用 reqwest 默认的 native-tls
+    tls_config: RwLock<Option<rustls::ClientConfig>>,
+    settings: RwLock<ProxySettings>,
+    resolver: RwLock<Arc<ProxyResolver>>,
+    proxy_client: RwLock<reqwest::Client>,
+    /// 启动时在 setup 中确定，确定之前不落盘
+    path: OnceLock<PathBuf>,
+    /// 串行化设置变更（PAC 下载期间可能有第二次修改）
+    update_lock: tokio::sync::Mutex<()>,
+}
+
+impl HttpClients {
+    /// 按编译期默认设置创建；PAC 需要下载脚本，在 load 中生效，此前沿用系统代理
+    pub fn new(
+        cookies: Arc<PersistentCookieJar>,
+        connect_timeout: Duration,
+        env_key: &str,
+        tls: Arc<TlsPolicy>,
+    ) -> Self {
+        let tls_config = rustls_config_for(&tls, None).expect("Invalid TLS configuration");
+        let settings = ProxySettings::from_env();
+        let resolver = match settings.mode {
+            ProxyMode::Pac => None,
+            _ => ProxyResolver::new(&settings, None)
+                .inspect_err(|e| log!("⚠️  [Proxy] 默认代理设置无效，使用系统代理: {}", e))
+                .ok(),
+        }
+        .unwrap_or_else(system_resolver);
+        let resolver = Arc::new(resolver);
+        let proxy_client =
+            build_proxy_client(&cookies, connect_timeout, &resolver, tls_config.as_ref())
+                .expect("Failed to create reqwest client");
+
+        Self {
+            cookies,
+            connect_timeout,
+            env_key: env_key.to_string(),
+            tls,
+            identity: RwLock::new(None),
+            tls_config: RwLock::new(tls_config),
+            settings: RwLock::new(settings),
+            resolver: RwLock::new(resolver),
+            proxy_client: RwLock::new(proxy_client),
+            path: OnceLock::new(),
+            update_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// 代理请求使用的 Client（带 Cookie 罐，不自动跟随重定向）
+    pub fn client(&self) -> reqwest::Client {
+        self.proxy_client
+            .read()
+            .map(|client| client.clone())
+            .unwrap_or_default()
+    }
+
+    /// 其他出站请求的 ClientBuilder，已应用上游代理与 TLS 设置
+    pub fn builder(&self) -> reqwest::ClientBuilder {
+        configure(
+            reqwest::Client::builder(),
+            &self.resolver(),
+            self.tls_config().as_ref(),
+        )
+    }
+
+    pub fn tls(&self) -> &Arc<TlsPolicy> {
+        &self.tls
+    }
+
+    fn tls_config(&self) -> Option<rustls::ClientConfig> {
+        self.tls_config
+            .read()
+            .ok()
+            .and_then(|config| config.clone())
+    }
+
+    pub fn client_identity(&self) -> Option<Arc<ClientIdentity>> {
+        self.identity
+            .read()
+            .ok()
+            .and_then(|identity| identity.clone())
+    }
+
+    /// 更换客户端证书并重建代理 Client，之后建立的连接出示新证书
+    pub async fn set_client_identity(
+        &self,
+        identity: Option<Arc<ClientIdentity>>,
+    ) -> Result<(), AppError> {
+        let _guard = self.update_lock.lock().await;
+        let tls_config = rustls_config_for(&self.tls, identity.as_deref())?;
+        let client = build_proxy_client(
+            &self.cookies,
+            self.connect_timeout,
+            &self.resolver(),
+            tls_config.as_ref(),
+        )?;
+
+        if let Ok(mut current) = self.tls_config.write() {
+            *current = tls_config;
+        }
+        if let Ok(mut current) = self.proxy_client.write() {
+            *current = client;
+        }
+        if let Ok(mut current) = self.identity.write() {
+            *current = identity;
+        }
+        Ok(())
+    }
+
+    /// 发送前预先求出目标地址的上游代理（PAC 求值会查询 DNS，不能在连接器里同步执行）
+    pub async fn prepare_proxy(&self, url: &str) {
+        if let Ok(url) = reqwest::Url::parse(url) {
+            self.resolver().resolve(&url).await;
+        }
+    }
+
+    pub fn resolver(&self) -> Arc<ProxyResolver> {
+        self.resolver
+            .read()
+            .map(|resolver| Arc::clone(&resolver))
+            .unwrap_or_else(|_| Arc::new(system_resolver()))
+    }
+
+    pub fn settings(&self) -> ProxySettings {
+        self.settings
+            .read()
+            .map(|settings| settings.clone())
+            .unwrap_or_default()
+    }
+
+    /// 读回上次保存的设置并生效；没有保存过时应用编译期默认设置（PAC 在这里下载）
+    pub async fn load(&self, app: &tauri::AppHandle) {
+        let Ok(dir) = app.path().app_data_dir() else {
+            log!("⚠️  [Proxy] 无法获取应用数据目录，代理设置不会持久化");
+            return;
+        };
+        let path = dir.join("network-proxy.bin");
+        let raw = std::fs::read(&path).unwrap_or_default();
+        let saved = unseal(SEAL_PURPOSE, &self.env_key, &raw)
+            .and_then(|plain| serde_json::from_slice::<StoredSettings>(&plain).ok())
+            .map(|stored| ProxySettings {
+                password: stored.password,
+                ..stored.settings
+            });
+        let _ = self.path.set(path);
+        if let Some(settings) = saved.as_ref().filter(|_| needs_reseal(&raw)) {
+            self.save(settings.clone());
+        }
+
+        let settings = match saved {
+            Some(settings) => settings,
+            None if self.settings().mode == ProxyMode::Pac => self.settings(),
+            None => return,
+        };
+        if let Err(e) = self.apply(settings, false).await {
+            log!("⚠️  [Proxy] 代理设置未能生效，使用系统代理: {}", e);
+        }
+    }
+
+    /// 校验并应用新设置，重建代理 Client；persist 为 true 时加密保存
+    pub async fn apply(&self, settings: ProxySettings, persist: bool) -> Result<(), AppError> {
+        let _guard = self.update_lock.lock().await;
+        let settings = settings.inherit_password(&self.settings());
+
+        let pac_source = match (settings.mode, settings.pac_url.as_deref()) {
+            (ProxyMode::Pac, Some(url)) if !url.trim().is_empty() => Some(fetch_pac(url.trim()).await?),
+            (ProxyMode::Pac, _) => {
+                return Err(AppError::InvalidRequest("PAC 模式需要 pac_url".to_string()))
+            }
+            _ => None,
+        };
+        let resolver = Arc::new(ProxyResolver::new(&settings, pac_source)?);
+        let client = build_proxy_client(
+            &self.cookies,
+            self.connect_timeout,
+            &resolver,
+            self.tls_config().as_ref(),
+        )?;
+
+        if let Ok(mut current) = self.resolver.write() {
+            *current = resolver;
+        }
+        if let Ok(mut current) = self.proxy_client.write() {
+            *current = client;
+        }
+        if let Ok(mut current) = self.settings.write() {
+            *current = settings.clone();
+        }
+        log!("🌐 [Proxy] 上游代理模式: {:?}", settings.mode);
+
+        if persist {
+            self.save(settings);
+        }
+        Ok(())
+    }
+
+    fn save(&self, settings: ProxySettings) {
+        let Some(path) = self.path.get() else {
+            return;
+        };
+        let stored = StoredSettings {
+            password: settings.password.clone(),
+            settings,
+        };
+        let Some(sealed) = serde_json::to_vec(&stored)
+            .ok()
+            .and_then(|plain| seal(SEAL_PURPOSE, &self.env_key, &plain))
+        else {
+            log!("⚠️  [Proxy] 加密失败，未保存");
+            return;
+        };
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, sealed));
+        if let Err(e) = result {
+            log!("⚠️  [Proxy] 保存失败: {}", e);
+        }
+    }
+}
+
+fn rustls_config_for(
+    tls: &Arc<TlsPolicy>,
+    identity: Option<&ClientIdentity>,
+) -> Result<Option<rustls::ClientConfig>, AppError> {
+    if !tls.is_customized() && identity.is_none() {
+        return Ok(None);
+    }
+    tls.rustls_config(identity).map(Some)
+}
+
+fn system_resolver() -> ProxyResolver {
+    ProxyResolver::new(&ProxySettings::default(), None).expect("system proxy settings are always valid")
+}
+
+/// 系统 DNS 解析（getaddrinfo），与 reqwest 默认行为一致；
+/// 失败时返回 DnsError，AppError 据此把域名解析失败与其他连接错误区分开
+struct SystemResolver;
+
+impl reqwest::dns::Resolve for SystemResolver {
+    fn resolve(&self, name: hyper::client::connect::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let resolved = tokio::net::lookup_host((host.as_str(), 0))
+                .await
+                .map(|addrs| addrs.collect::<Vec<_>>());
+            match resolved {
+                Ok(addrs) => Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs),
+                Err(source) => Err(Box::new(DnsError { host, source }) as _),
+            }
+        })
+    }
+}
+
+/// 把 DNS 解析、上游代理与 TLS 设置应用到 ClientBuilder
+fn configure(
+    builder: reqwest::ClientBuilder,
+    resolver: &Arc<ProxyResolver>,
+    tls_config: Option<&rustls::ClientConfig>,
+) -> reqwest::ClientBuilder {
+    let builder = builder.dns_resolver(Arc::new(SystemResolver));
+    let builder = match tls_config {
+        Some(config) => builder.use_preconfigured_tls(config.clone()),
+        None => builder,
+    };
+    match resolver.mode() {
+        // 不设置任何代理时 reqwest 自动使用系统代理
+        ProxyMode::System => builder,
+        ProxyMode::Direct => builder.no_proxy(),
+        ProxyMode::Manual | ProxyMode::Pac => {
+            let resolver = Arc::clone(resolver);
+            builder.proxy(reqwest::Proxy::custom(move |url| resolver.proxy_for(url)))
+        }
+    }
+}
+
+fn build_proxy_client(
+    cookies: &Arc<PersistentCookieJar>,
+    connect_timeout: Duration,
+    resolver: &Arc<ProxyResolver>,
+    tls_config: Option<&rustls::ClientConfig>,
+) -> Result<reqwest::Client, AppError> {
+    let builder = reqwest::Client::builder()
+        .cookie_provider(Arc::clone(cookies))
+        .user_agent(PROXY_USER_AGENT)
+        .connect_timeout(connect_timeout)
+        // 重定向由 redirect 模块逐跳处理（每跳重新签名并记录重定向链）
+        .redirect(reqwest::redirect::Policy::none());
+    Ok(configure(builder, resolver, tls_config).build()?)
+}
+
+/// 当前上游代理设置（不含密码）
+#[tauri::command]
+pub fn get_network_proxy(state: State<'_, Arc<AppState>>) -> Result<ProxySettings, AppError> {
+    Ok(state.http.settings())
+}
+
+/// 修改上游代理设置，立即对新请求生效并保存
+#[tauri::command]
+pub async fn set_network_proxy(
+    settings: ProxySettings,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    state.http.apply(settings, true).await
+}
+
+/// 查询某个地址会使用的上游代理（不含认证信息），直连返回 None
+#[tauri::command]
+pub async fn resolve_network_proxy(
+    url: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<String>, AppError> {
+    let url = reqwest::Url::parse(&url).map_err(|e| AppError::InvalidUrl(format!("{}: {}", url, e)))?;
+    Ok(state.http.resolver().resolve(&url).await.map(|mut proxy| {
+        let _ = proxy.set_username("");
+        let _ = proxy.set_password(None);
+        proxy.to_string()
+    }))
+}
@@ -0,0 +1,316 @@
+/// 客户端证书的存储与命令
+/// 证书按环境（env_key）保存：证书链与来源加密写入应用数据目录；私钥（含证书申请中的待签发私钥）
+/// 不写入文件，直接交给系统钥匙串保管，由系统负责访问控制
+///
+/// 旧版本把私钥一起加密写在文件里，读取时迁移到钥匙串并重写文件
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock, RwLock};
+use tauri::{Manager, State};
+
+use crate::client_identity::{
+    from_pem, from_pkcs12, ClientIdentity, ClientIdentityInfo, IdentityOrigin,
+};
+use crate::cookie_jar::sanitize;
+use crate::crypto::{load_secret, needs_reseal, seal, store_secret, unseal};
+use crate::error::AppError;
+use crate::fingerprint::get_device_fingerprint;
+use crate::multipart::read_source;
+use crate::proxy::AppState;
+use crate::upload::FileSource;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 加密用途标识（参与密钥派生）
+const SEAL_PURPOSE: &str = "client-identity";
+
+/// 剩余有效期少于该天数时在启动日志中提醒
+const EXPIRY_WARNING_DAYS: i64 = 30;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientIdentityStatus {
+    pub identity: Option<ClientIdentityInfo>,
+    /// 已生成 CSR、等待导入签发的证书
+    pub enrollment_pending: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case")]
+pub enum ImportIdentityRequest {
+    Pkcs12 {
+        source: FileSource,
+        #[serde(default)]
+        password: String,
+    },
+    /// private_key 省略时使用 create_client_csr 生成的私钥
+    Pem {
+        certificate: String,
+        #[serde(default)]
+        private_key: Option<String>,
+    },
+}
+
+/// 落盘内容；私钥字段只在读取旧文件时出现，写入时省略
+#[derive(Default, Clone, Serialize, Deserialize)]
+struct StoredIdentity {
+    identity: Option<ClientIdentity>,
+    /// 生成 CSR 时的私钥（PKCS#8 DER），导入签发的证书后清除
+    #[serde(default, skip_serializing)]
+    pending_key: Option<Vec<u8>>,
+}
+
+pub struct ClientIdentityStore {
+    env_key: String,
+    stored: RwLock<StoredIdentity>,
+    /// 启动时在 setup 中确定，确定之前不落盘
+    path: OnceLock<PathBuf>,
+}
+
+impl ClientIdentityStore {
+    pub fn new(env_key: &str) -> Self {
+        Self {
+            env_key: env_key.to_string(),
+            stored: RwLock::new(StoredIdentity::default()),
+            path: OnceLock::new(),
+        }
+    }
+
+    pub fn current(&self) -> Option<Arc<ClientIdentity>> {
+        self.stored
+            .read()
+            .ok()
+            .and_then(|stored| stored.identity.clone())
+            .map(Arc::new)
+    }
+
+    pub fn status(&self) -> ClientIdentityStatus {
+        let stored = self.snapshot();
+        ClientIdentityStatus {
+            identity: stored.identity.as_ref().and_then(ClientIdentity::info),
+            enrollment_pending: stored.pending_key.is_some(),
+        }
+    }
+
+    /// 确定存储路径并读回；文件损坏、无法解密或钥匙串中没有私钥时视为未配置
+    pub fn load(&self, app: &tauri::AppHandle) {
+        let Ok(dir) = app.path().app_data_dir() else {
+            log!("⚠️  [mTLS] 无法获取应用数据目录，客户端证书不会持久化");
+            return;
+        };
+        let path = dir
+            .join("client-identity")
+            .join(format!("{}.bin", sanitize(&self.env_key)));
+        let raw = std::fs::read(&path).unwrap_or_default();
+        let loaded = unseal(SEAL_PURPOSE, &self.env_key, &raw)
+            .and_then(|plain| serde_json::from_slice::<StoredIdentity>(&plain).ok());
+        let _ = self.path.set(path);
+
+        let Some(mut loaded) = loaded else {
+            return;
+        };
+        // 文件里带私钥说明是旧格式：迁移到钥匙串
+        let legacy = loaded.pending_key.is_some()
+            || loaded
+                .identity
+                .as_ref()
+                .is_some_and(|identity| !identity.key().is_empty());
+        if !legacy {
+            loaded.pending_key = load_secret(&self.account("pending-key"));
+            loaded.identity = loaded.identity.and_then(|identity| {
+                let key = load_secret(&self.account("key"))?;
+                identity
+                    .with_key(key)
+                    .inspect_err(|e| log!("⚠️  [mTLS] 钥匙串中的私钥不可用: {}", e))
+                    .ok()
+            });
+        }
+        if legacy || needs_reseal(&raw) {
+            self.save(&loaded);
+        }
+
+        if let Some(info) = loaded.identity.as_ref().and_then(ClientIdentity::info) {
+            log!(
+                "🪪 [mTLS] 客户端证书: {} (剩余 {} 天)",
+                info.subject,
+                info.days_remaining
+            );
+            if info.days_remaining < EXPIRY_WARNING_DAYS {
+                log!("⚠️  [mTLS] 客户端证书即将过期或已过期: {}", info.subject);
+            }
+        }
+        if let Ok(mut stored) = self.stored.write() {
+            *stored = loaded;
+        }
+    }
+
+    /// 钥匙串条目名，按环境区分
+    fn account(&self, kind: &str) -> String {
+        format!("client-identity-{}:{}", kind, self.env_key)
+    }
+
+    fn snapshot(&self) -> StoredIdentity {
+        self.stored
+            .read()
+            .map(|stored| stored.clone())
+            .unwrap_or_default()
+    }
+
+    fn replace(&self, next: StoredIdentity) {
+        self.save(&next);
+        if let Ok(mut stored) = self.stored.write() {
+            *stored = next;
+        }
+    }
+
+    /// 私钥写入钥匙串，其余内容加密写入文件；钥匙串不可用时不落盘
+    fn save(&self, stored: &StoredIdentity) {
+        let Some(path) = self.path.get() else {
+            return;
+        };
+        let key = stored.identity.as_ref().map(ClientIdentity::key);
+        if let Err(e) = store_secret(&self.account("key"), key)
+            .and_then(|_| store_secret(&self.account("pending-key"), stored.pending_key.as_deref()))
+        {
+            log!("⚠️  [mTLS] 私钥无法存入系统钥匙串，未保存: {}", e);
+            return;
+        }
+
+        let result = if stored.identity.is_none() && stored.pending_key.is_none() {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            }
+        } else {
+            let Some(sealed) = serde_json::to_vec(stored)
+                .ok()
+                .and_then(|plain| seal(SEAL_PURPOSE, &self.env_key, &plain))
+            else {
+                log!("⚠️  [mTLS] 加密失败，未保存");
+                return;
+            };
+            path.parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(path, sealed))
+        };
+        if let Err(e) = result {
+            log!("⚠️  [mTLS] 保存失败: {}", e);
+        }
+    }
+}
+
+/// 当前客户端证书与申请状态
+#[tauri::command]
+pub fn get_client_identity(
+    state: State<'_, Arc<AppState>>,
+) -> Result<ClientIdentityStatus, AppError> {
+    Ok(state.identity.status())
+}
+
+/// 导入客户端证书（替换已有证书），立即对新连接生效
+#[tauri::command]
+pub async fn import_client_identity(
+    webview: tauri::Webview,
+    request: ImportIdentityRequest,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ClientIdentityInfo, AppError> {
+    let mut stored = state.identity.snapshot();
+    let identity = match &request {
+        ImportIdentityRequest::Pkcs12 { source, password } => {
+            let data = read_source(&state.uploads, webview.label(), source).await?;
+            from_pkcs12(&data, password)?
+        }
+        ImportIdentityRequest::Pem {
+            certificate,
+            private_key,
+        } => from_pem(
+            certificate,
+            private_key.as_deref(),
+            stored.pending_key.as_deref(),
+        )?,
+    };
+    let info = identity
+        .info()
+        .ok_or_else(|| AppError::InvalidRequest("无法读取证书信息".to_string()))?;
+
+    state
+        .http
+        .set_client_identity(Some(Arc::new(identity.clone())))
+        .await?;
+    if identity.origin() == IdentityOrigin::Enrollment {
+        stored.pending_key = None;
+    }
+    stored.identity = Some(identity);
+    state.identity.replace(stored);
+    log!(
+        "🪪 [mTLS] 已导入客户端证书: {} (剩余 {} 天)",
+        info.subject,
+        info.days_remaining
+    );
+    Ok(info)
+}
+
+/// 删除客户端证书（保留未完成的证书申请）
+#[tauri::command]
+pub async fn remove_client_identity(state: State<'_, Arc<AppState>>) -> Result<(), AppError> {
+    state.http.set_client_identity(None).await?;
+    let mut stored = state.identity.snapshot();
+    stored.identity = None;
+    state.identity.replace(stored);
+    log!("🪪 [mTLS] 已删除客户端证书");
+    Ok(())
+}
+
+/// 在设备上生成 ECDSA P-256 密钥并返回 CSR（PEM）；再次调用会替换未完成的申请
+#[tauri::command]
+pub fn create_client_csr(
+    common_name: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, AppError> {
+    let common_name = common_name
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| {
+            let fingerprint = get_device_fingerprint();
+            format!(
+                "backstage68-{}-{}",
+                state.identity.env_key,
+                &fingerprint[..fingerprint.len().min(16)]
+            )
+        });
+
+    let mut params = rcgen::CertificateParams::new(Vec::new());
+    params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+    params.distinguished_name = rcgen::DistinguishedName::new();
+    params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, common_name.trim());
+    let csr = rcgen::Certificate::from_params(params)
+        .map_err(|e| AppError::Internal(format!("生成密钥失败: {}", e)))?;
+    let pem = csr
+        .serialize_request_pem()
+        .map_err(|e| AppError::Internal(format!("生成 CSR 失败: {}", e)))?;
+
+    let mut stored = state.identity.snapshot();
+    stored.pending_key = Some(csr.serialize_private_key_der());
+    state.identity.replace(stored);
+    log!("🪪 [mTLS] 已生成证书申请: CN={}", common_name.trim());
+    Ok(pem)
+}
@@ -1,16 +1,74 @@
-use std::collections::HashMap;
 use std::sync::Arc;
-use tauri::{Builder, Emitter, WebviewUrl, WebviewWindowBuilder};
-use tokio::sync::Mutex;
-
+use tauri::{Builder, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
+
+mod cache_index;
+mod cache_policy;
+mod cancel;
+mod canonical;
+mod challenge;
+mod clock;
+mod client_identity;
+mod coalesce;
+mod cookie_jar;
+mod cookie_sync;
 mod crypto;
+mod device_signature;
+mod dispatch;
+mod envelope;
+mod error;
+mod error_message;
 mod fingerprint;
+mod headers;
+mod http_cache;
+mod http_client;
+mod identity_store;
+mod limiter;
+mod multipart;
+mod origin_policy;
+mod pac;
 mod proxy;
+mod proxy_tunnel;
+mod redirect;
+mod resilience;
 mod security;
-
+mod session_key;
+mod signature_scheme;
+mod signing;
+mod sniff;
+mod sse;
+mod sse_parser;
+mod stream;
+mod stream_body;
+mod tls;
+mod upload;
+mod upstream_proxy;
+mod websocket;
+mod ws_transport;
+
+use cancel::InflightRequests;
+use challenge::ChallengeStore;
+use clock::ServerClock;
+use coalesce::InflightGets;
+use cookie_jar::PersistentCookieJar;
+use cookie_sync::CookieSync;
+use error::{AppError, Locale};
+use headers::HeaderList;
+use http_cache::HttpCache;
+use http_client::HttpClients;
+use identity_store::ClientIdentityStore;
+use limiter::HostLimiter;
+use origin_policy::{OriginAccess, OriginPolicy};
 use proxy::AppState;
+use resilience::{CircuitBreakers, ProxyPolicy};
+use session_key::SessionKeys;
+use signature_scheme::{rejected_by_version, SignatureSchemes};
+use signing::{SigningPipeline, SigningRequest};
+use tls::TlsPolicy;
+use upload::UploadStore;
+use websocket::WebSocketConnections;
 
 // 常量定义
+#[cfg(feature = "devtools")]
 const DEVTOOLS_OPEN_DELAY_SECS: u64 = 3;
 
 /// 转义 JavaScript 字符串中的特殊字符
@@ -87,13 +145,13 @@ const DEVTOOLS_AUTO_OPEN: bool = {
 
 /// 获取当前环境信息
 #[tauri::command]
-fn get_env_info() -> Result<String, String> {
+fn get_env_info() -> Result<String, AppError> {
     Ok(format!("当前环境: {} ({})", env_name(), env_key()))
 }
 
 /// 获取系统下载目录（修复 Linux 下载目录问题）
 #[tauri::command]
-fn get_download_dir() -> Result<String, String> {
+fn get_download_dir() -> Result<String, AppError> {
     // 使用 dirs crate 获取下载目录
     if let Some(download_dir) = dirs::download_dir() {
         let path = download_dir.to_string_lossy().to_string();
@@ -127,7 +185,7 @@ fn get_download_dir() -> Result<String, String> {
         }
     }
 
-    Err("无法获取下载目录".to_string())
+    Err(AppError::NotFound("无法获取下载目录".to_string()))
 }
 
 /// 获取操作系统类型
@@ -148,7 +206,7 @@ fn get_os_type() -> String {
 
 /// 保存文件到下载目录（用于 Linux blob URL 下载问题）
 #[tauri::command]
-async fn save_file_to_downloads(filename: String, data: Vec<u8>) -> Result<String, String> {
+async fn save_file_to_downloads(filename: String, data: Vec<u8>) -> Result<String, AppError> {
     use std::fs;
 
     // 获取下载目录
@@ -168,11 +226,12 @@ async fn save_file_to_downloads(filename: String, data: Vec<u8>) -> Result<Strin
                 }
             })
         })
-        .ok_or("无法获取下载目录")?;
+        .ok_or_else(|| AppError::NotFound("无法获取下载目录".to_string()))?;
 
     // 确保下载目录存在
     if !download_dir.exists() {
-        fs::create_dir_all(&download_dir).map_err(|e| format!("创建下载目录失败: {}", e))?;
+        fs::create_dir_all(&download_dir)
+            .map_err(|e| AppError::Io(format!("创建下载目录失败: {}", e)))?;
     }
 
     // 处理文件名冲突
@@ -200,7 +259,7 @@ async fn save_file_to_downloads(filename: String, data: Vec<u8>) -> Result<Strin
     }
 
     // 写入文件
-    fs::write(&file_path, &data).map_err(|e| format!("保存文件失败: {}", e))?;
+    fs::write(&file_path, &data).map_err(|e| AppError::Io(format!("保存文件失败: {}", e)))?;
 
     let saved_path = file_path.to_string_lossy().to_string();
     log!("📥 文件已保存: {}", saved_path);
@@ -232,7 +291,22 @@ struct DownloadComplete {
 struct DownloadError {
     id: String,
     filename: String,
+    /// 面向用户的错误描述（兼容旧前端直接展示）
     error: String,
+    code: &'static str,
+    retryable: bool,
+}
+
+impl DownloadError {
+    fn new(id: &str, filename: Option<&str>, error: &AppError) -> Self {
+        Self {
+            id: id.to_string(),
+            filename: filename.unwrap_or_default().to_string(),
+            error: error.message(),
+            code: error.code(),
+            retryable: error.retryable(),
+        }
+    }
 }
 
 #[derive(Clone, serde::Serialize)]
@@ -242,15 +316,83 @@ struct DownloadResult {
     id: String,
 }
 
+/// 发送下载请求：与代理请求走同一条签名链，签名来源附加验证头，其他来源只附加追踪头；
+/// 服务端挑战失效时重新领取并重发一次
+async fn send_download(
+    state: &AppState,
+    client: &reqwest::Client,
+    url: &str,
+    headers: &HeaderList,
+    download_id: &str,
+) -> Result<reqwest::Response, AppError> {
+    let signed = state.origins.classify(url) == OriginAccess::Signed;
+    let mut rechallenged = false;
+    let mut resynced = false;
+    let mut rekeyed = false;
+    let mut renegotiated = false;
+    loop {
+        if signed {
+            state.challenge.ensure(state).await?;
+            state.sessions.ensure(state).await;
+        }
+        let signed_offset = state.clock.offset_ms();
+        let signed_headers = state.signers.sign(&SigningRequest {
+            id: Some(download_id),
+            method: "GET",
+            url,
+            headers,
+            body_hash: canonical::EMPTY_BODY_HASH,
+            signed,
+        })?;
+        state.http.prepare_proxy(url).await;
+        let req = headers.forwardable_request().apply_to(client.get(url));
+        let sent_at = chrono::Utc::now();
+        let resp = signed_headers.apply_to(req).send().await?;
+        if signed {
+            state.clock.observe(resp.headers(), sent_at, chrono::Utc::now());
+        }
+
+        let status = resp.status().as_u16();
+        if signed && !rechallenged && state.challenge.requires_rechallenge(status, resp.headers()) {
+            state.challenge.on_rechallenge(resp.headers());
+            rechallenged = true;
+            continue;
+        }
+        if signed && !rekeyed && state.sessions.requires_rekey(resp.headers()) {
+            state.sessions.on_rekey();
+            rekeyed = true;
+            continue;
+        }
+        if signed
+            && !renegotiated
+            && state.schemes.negotiate(resp.headers())
+            && rejected_by_version(status)
+        {
+            renegotiated = true;
+            continue;
+        }
+        if signed
+            && !resynced
+            && state.clock.signature_expired(status, resp.headers(), signed_offset)
+        {
+            log!("🕒 [Download] 签名时间戳过期，按服务器时间重新签名");
+            resynced = true;
+            continue;
+        }
+        return Ok(resp);
+    }
+}
+
 /// 流式下载文件到下载目录（替代 JS 端全量缓冲方案）
 #[tauri::command]
 async fn download_file(
     app: tauri::AppHandle,
     url: String,
     filename: Option<String>,
-    headers: Option<HashMap<String, String>>,
+    headers: Option<HeaderList>,
     id: Option<String>,
-) -> Result<DownloadResult, String> {
+    state: tauri::State<'_, Arc<AppState>>,
+) -> Result<DownloadResult, AppError> {
     use futures_util::StreamExt;
     use std::time::Instant;
     use tokio::io::AsyncWriteExt;
@@ -258,36 +400,29 @@ async fn download_file(
     let download_id = id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
     log!("📥 [Download] 开始: {} (id={})", url, download_id);
 
-    let client = reqwest::Client::builder()
+    let client = state
+        .http
+        .builder()
         .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 TauriApp/1.0")
-        .build()
-        .map_err(|e| e.to_string())?;
+        .build()?;
 
-    let mut req = client.get(&url);
-    if let Some(ref h) = headers {
-        for (k, v) in h {
-            req = req.header(k.as_str(), v.as_str());
-        }
-    }
-
-    let resp = req.send().await.map_err(|e| {
-        let msg = format!("请求失败: {}", e);
-        let _ = app.emit("download-error", DownloadError {
-            id: download_id.clone(),
-            filename: filename.clone().unwrap_or_default(),
-            error: msg.clone(),
-        });
-        msg
-    })?;
+    let headers = headers.unwrap_or_default();
+    let resp = send_download(&state, &client, &url, &headers, &download_id)
+        .await
+        .inspect_err(|error| {
+            let _ = app.emit(
+                "download-error",
+                DownloadError::new(&download_id, filename.as_deref(), error),
+            );
+        })?;
 
     if !resp.status().is_success() {
-        let msg = format!("HTTP {}", resp.status());
-        let _ = app.emit("download-error", DownloadError {
-            id: download_id.clone(),
-            filename: filename.clone().unwrap_or_default(),
-            error: msg.clone(),
-        });
-        return Err(msg);
+        let error = AppError::HttpStatus(resp.status().as_u16());
+        let _ = app.emit(
+            "download-error",
+            DownloadError::new(&download_id, filename.as_deref(), &error),
+        );
+        return Err(error);
     }
 
     let total_size = resp.content_length().unwrap_or(0);
@@ -308,13 +443,13 @@ async fn download_file(
     let download_dir = get_download_dir_path()?;
     if !download_dir.exists() {
         std::fs::create_dir_all(&download_dir)
-            .map_err(|e| format!("创建下载目录失败: {}", e))?;
+            .map_err(|e| AppError::Io(format!("创建下载目录失败: {}", e)))?;
     }
     let save_path = resolve_unique_path(&download_dir, &final_filename);
 
     let mut file = tokio::fs::File::create(&save_path)
         .await
-        .map_err(|e| format!("创建文件失败: {}", e))?;
+        .map_err(|e| AppError::Io(format!("创建文件失败: {}", e)))?;
 
     let mut stream = resp.bytes_stream();
     let mut downloaded: u64 = 0;
@@ -322,10 +457,10 @@ async fn download_file(
     let mut last_emit = Instant::now();
 
     while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("读取数据失败: {}", e))?;
+        let chunk = chunk_result?;
         file.write_all(&chunk)
             .await
-            .map_err(|e| format!("写入文件失败: {}", e))?;
+            .map_err(|e| AppError::Io(format!("写入文件失败: {}", e)))?;
         downloaded += chunk.len() as u64;
 
         // 每 200ms 推送一次进度，避免事件风暴
@@ -350,7 +485,9 @@ async fn download_file(
         }
     }
 
-    file.flush().await.map_err(|e| format!("flush 失败: {}", e))?;
+    file.flush()
+        .await
+        .map_err(|e| AppError::Io(format!("flush 失败: {}", e)))?;
 
     let saved_path = save_path.to_string_lossy().to_string();
     log!("📥 [Download] ✅ 完成: {} ({} bytes)", saved_path, downloaded);
@@ -371,33 +508,33 @@ async fn download_file(
 
 /// 用系统默认程序打开文件
 #[tauri::command]
-async fn open_file(path: String) -> Result<(), String> {
+async fn open_file(path: String) -> Result<(), AppError> {
     let p = std::path::Path::new(&path);
     if !p.exists() {
-        return Err(format!("文件不存在: {}", path));
+        return Err(AppError::NotFound(format!("文件不存在: {}", path)));
     }
-    open::that(&path).map_err(|e| format!("打开文件失败: {}", e))
+    open::that(&path).map_err(|e| AppError::Io(format!("打开文件失败: {}", e)))
 }
 
 /// 用系统文件管理器打开文件所在目录（并选中该文件）
 #[tauri::command]
-async fn open_file_folder(path: String) -> Result<(), String> {
+async fn open_file_folder(path: String) -> Result<(), AppError> {
     let p = std::path::Path::new(&path);
     let dir = if p.is_dir() {
         p.to_path_buf()
     } else {
         p.parent()
             .map(|d| d.to_path_buf())
-            .ok_or_else(|| format!("无法获取父目录: {}", path))?
+            .ok_or_else(|| AppError::NotFound(format!("无法获取父目录: {}", path)))?
     };
     if !dir.exists() {
-        return Err(format!("目录不存在: {}", dir.display()));
+        return Err(AppError::NotFound(format!("目录不存在: {}", dir.display())));
     }
     open::that(dir.to_string_lossy().as_ref())
-        .map_err(|e| format!("打开目录失败: {}", e))
+        .map_err(|e| AppError::Io(format!("打开目录失败: {}", e)))
 }
 
-fn get_download_dir_path() -> Result<std::path::PathBuf, String> {
+fn get_download_dir_path() -> Result<std::path::PathBuf, AppError> {
     dirs::download_dir()
         .or_else(|| {
             dirs::home_dir().map(|h| {
@@ -408,7 +545,7 @@ fn get_download_dir_path() -> Result<std::path::PathBuf, String> {
                 en
             })
         })
-        .ok_or_else(|| "无法获取下载目录".to_string())
+        .ok_or_else(|| AppError::NotFound("无法获取下载目录".to_string()))
 }
 
 fn resolve_unique_path(dir: &std::path::Path, filename: &str) -> std::path::PathBuf {
@@ -450,7 +587,7 @@ fn parse_download_filename(resp: &reqwest::Response, hint: Option<&str>, url: &s
         }
     }
     // 3. 从 URL 路径提取
-    if let Some(segment) = url.split('?').next().and_then(|u| u.split('/').last()) {
+    if let Some(segment) = url.split('?').next().and_then(|u| u.split('/').next_back()) {
         if let Ok(decoded) = urlencoding::decode(segment) {
             let name = decoded.to_string();
             if !name.is_empty() && name != "/" {
@@ -525,8 +662,6 @@ fn guess_extension_from_response(resp: &reqwest::Response) -> &'static str {
 fn guess_extension(ct: &str) -> &'static str {
     if ct.contains("spreadsheetml") || ct.contains("excel") || ct.contains("spreadsheet") {
         ".xlsx"
-    } else if ct.contains("vnd.ms-excel") {
-        ".xlsx"
     } else if ct.contains("csv") {
         ".csv"
     } else if ct.contains("pdf") {
@@ -550,26 +685,32 @@ fn guess_extension(ct: &str) -> &'static str {
     }
 }
 
+/// 设置错误提示的语言（页面启动时传入 navigator.language）
+#[tauri::command]
+fn set_locale(locale: String) {
+    Locale::from_tag(&locale).set_current();
+}
+
 /// 设置页面缩放（使用 Tauri 2.0 WebView 原生缩放）
 #[tauri::command]
-async fn set_zoom(window: tauri::WebviewWindow, zoom_level: f64) -> Result<(), String> {
+async fn set_zoom(window: tauri::WebviewWindow, zoom_level: f64) -> Result<(), AppError> {
     // 使用 Tauri 2.0 的 WebView 原生缩放 API
     // 这会像浏览器原生缩放一样工作，不会有 fixed 元素定位问题
     window.set_zoom(zoom_level)
-        .map_err(|e| format!("Failed to set zoom: {}", e))
+        .map_err(|e| AppError::Internal(format!("Failed to set zoom: {}", e)))
 }
 
 /// 获取当前缩放级别（从前端存储）
 #[tauri::command]
-async fn get_zoom() -> Result<f64, String> {
+async fn get_zoom() -> Result<f64, AppError> {
     // 缩放级别由前端 JavaScript 管理
     Ok(1.0)
 }
 
 /// 设置窗口标题
 #[tauri::command]
-async fn set_window_title(window: tauri::Window, title: String) -> Result<(), String> {
-    window.set_title(&title).map_err(|e| e.to_string())
+async fn set_window_title(window: tauri::Window, title: String) -> Result<(), AppError> {
+    window.set_title(&title).map_err(AppError::from)
 }
 
 /// 创建新窗口（用于支持多窗口）
@@ -584,7 +725,7 @@ async fn create_new_window(
     storage_data: Option<String>,
     width: Option<f64>,
     height: Option<f64>,
-) -> Result<String, String> {
+) -> Result<String, AppError> {
     use std::sync::atomic::{AtomicUsize, Ordering};
 
     // 生成唯一的窗口 ID
@@ -595,7 +736,7 @@ async fn create_new_window(
     log!("🪟 Creating new window: {}", window_label);
 
     // 使用传入的 URL（当前页面）或默认 URL
-    let target_url = current_url.unwrap_or_else(|| env_url());
+    let target_url = current_url.unwrap_or_else(env_url);
     log!("   Target URL: {}", target_url);
 
     // 获取注入脚本
@@ -674,14 +815,15 @@ async fn create_new_window(
         WebviewUrl::External(
             initial_url
                 .parse()
-                .map_err(|e| format!("Invalid URL: {}", e))?,
+                .map_err(|e| AppError::InvalidUrl(format!("Invalid URL: {}", e)))?,
         ),
     )
     .title(format!("{} - 窗口 {}", env_name(), window_id))
     .inner_size(target_width, target_height)
     .initialization_script(&final_script)
+    .on_page_load(cookie_sync::on_page_load)
     .build()
-    .map_err(|e| format!("Failed to create window: {}", e))?;
+    .map_err(|e| AppError::Internal(format!("Failed to create window: {}", e)))?;
 
     Ok(window_label)
 }
@@ -698,18 +840,63 @@ pub fn run() {
     log!("   - VM detected: {}", security_score.is_vm);
     log!("   - Security level: {:?}", security_score.level());
 
-    let client = reqwest::Client::builder()
-        .cookie_store(true)
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36 TauriApp/1.0")
-        .build()
-        .expect("Failed to create reqwest client");
-
-    let app_state = Arc::new(Mutex::new(AppState { client }));
+    let policy = ProxyPolicy::from_env();
+    // 持久化 Cookie：按环境加密保存到应用数据目录，在 setup 中读回
+    let cookies = Arc::new(PersistentCookieJar::new(&env_key()));
+    // 出站客户端统一应用上游代理与 TLS 设置，已保存的代理设置在 setup 中读回
+    let tls = Arc::new(TlsPolicy::from_env(&env_url()));
+    let http = HttpClients::new(Arc::clone(&cookies), policy.connect_timeout, &env_key(), tls);
+    // 服务端签名挑战（未配置 TAURI_SIGN_CHALLENGE_PATH 时关闭）
+    let challenge = ChallengeStore::from_env(&env_url());
+    // 按后端 Date 头校正签名时间戳
+    let clock = ServerClock::from_env();
+    // 会话密钥签名（未配置 TAURI_SIGN_SESSION_PATH 或后端不支持时逐请求 RSA 签名）
+    let sessions = SessionKeys::from_env(&env_url());
+    // 签名方案版本（TAURI_SIGNATURE_VERSION，可由后端 X-Signature-Accept 协商升级）
+    let schemes = SignatureSchemes::from_env();
+    let app_state = Arc::new(AppState {
+        http,
+        identity: ClientIdentityStore::new(&env_key()),
+        limiter: HostLimiter::new(limiter::max_per_host_from_env()),
+        uploads: UploadStore::default(),
+        inflight: InflightRequests::default(),
+        inflight_gets: InflightGets::default(),
+        policy: policy.clone(),
+        breakers: CircuitBreakers::default(),
+        cache: HttpCache::new(http_cache::max_bytes_from_env()),
+        origins: OriginPolicy::from_env(&env_url()),
+        cookies: Arc::clone(&cookies),
+        cookie_sync: CookieSync::new(&env_url()),
+        signers: SigningPipeline::from_env(
+            challenge.clone(),
+            clock.clone(),
+            sessions.clone(),
+            schemes.clone(),
+        ),
+        challenge,
+        clock,
+        sessions,
+        schemes,
+        websockets: WebSocketConnections::default(),
+    });
+    let sync_state = Arc::clone(&app_state);
     let inject_script = include_str!("../../src/inject.js").to_string();
 
     // 使用编译时注入的环境变量
     log!("🌍 Environment: {} ({})", env_name(), env_key());
     log!("📍 URL: {}", env_url());
+    log!("🚦 Proxy max concurrency per host: {}", limiter::max_per_host_from_env());
+    log!(
+        "⏱️  Proxy timeouts: connect {:?}, read {:?}, total {:?}, max retries {}",
+        policy.connect_timeout,
+        policy.read_timeout,
+        policy.total_timeout,
+        policy.max_retries
+    );
+    log!(
+        "💾 Proxy HTTP cache: {} MB",
+        http_cache::max_bytes_from_env() / 1024 / 1024
+    );
     log!(
         "🔧 DevTools: {}",
         if DEVTOOLS_ENABLED {
@@ -722,6 +909,28 @@ pub fn run() {
     Builder::default()
         .manage(app_state)
         .setup(move |app| {
+            cookies.load(app.handle());
+            // 客户端证书在第一个请求之前生效
+            sync_state.identity.load(app.handle());
+            if let Err(e) = tauri::async_runtime::block_on(
+                sync_state.http.set_client_identity(sync_state.identity.current()),
+            ) {
+                log!("⚠️  [mTLS] 客户端证书未能生效: {}", e);
+            }
+            let handle = app.handle().clone();
+            sync_state.http.tls().set_listener(move |mismatch| {
+                let _ = handle.emit(tls::PIN_MISMATCH_EVENT, mismatch);
+            });
+            let handle = app.handle().clone();
+            sync_state.clock.set_listener(move |skew| {
+                let _ = handle.emit(clock::CLOCK_SKEW_EVENT, skew);
+            });
+            let proxy_state = Arc::clone(&sync_state);
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                proxy_state.http.load(&handle).await;
+            });
+
             log!("🚀 Creating main window...");
 
             // 准备注入脚本：将 inject.js 内容和目标 URL 变量合并
@@ -741,11 +950,15 @@ pub fn run() {
             .inner_size(1200.0, 800.0)
             .resizable(true)
             .initialization_script(&final_script)
+            .on_page_load(cookie_sync::on_page_load)
             .build()
             .expect("Failed to create window");
 
             log!("✓ Window created");
 
+            // 窗口创建后再同步：WebView 的 Cookie 接口需要已有 WebView
+            cookie_sync::start(app.handle(), &sync_state);
+
             // 在 devtools 启用且设置为自动打开时才打开
             if DEVTOOLS_ENABLED && DEVTOOLS_AUTO_OPEN {
                 #[cfg(feature = "devtools")]
@@ -768,7 +981,32 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             proxy::proxy_request,
+            stream::proxy_request_stream,
+            cancel::cancel_proxy_request,
+            upload::upload_begin,
+            upload::upload_chunk,
+            upload::upload_discard,
+            http_cache::purge_http_cache,
+            cookie_jar::list_proxy_cookies,
+            cookie_jar::delete_proxy_cookie,
+            cookie_jar::clear_proxy_cookies,
+            cookie_sync::sync_proxy_cookies,
+            cookie_sync::logout_proxy_cookies,
+            sse::sse_connect,
+            sse::sse_close,
+            websocket::ws_connect,
+            websocket::ws_send,
+            websocket::ws_close,
+            http_client::get_network_proxy,
+            http_client::set_network_proxy,
+            http_client::resolve_network_proxy,
+            identity_store::get_client_identity,
+            identity_store::import_client_identity,
+            identity_store::remove_client_identity,
+            identity_store::create_client_csr,
+            signing::set_proxy_auth_token,
             get_env_info,
+            set_locale,
             set_zoom,
             get_zoom,
             set_window_title,
@@ -783,6 +1021,11 @@ pub fn run() {
         .build(tauri::generate_context!())
         .expect("error while building tauri application")
         .run(|app, event| {
+            // 退出前把尚未写盘的 Cookie 变化落盘
+            if let tauri::RunEvent::Exit = event {
+                app.state::<Arc<AppState>>().cookies.flush();
+            }
+
             // macOS: 处理 Reopen 事件
             #[cfg(target_os = "macos")]
             if let tauri::RunEvent::Reopen { has_visible_windows, .. } = event {
@@ -797,7 +1040,8 @@ pub fn run() {
 }
 
 /// 创建 Reopen 窗口（用于 macOS 双击图标时）
-fn create_reopen_window(app: &tauri::AppHandle) -> Result<(), String> {
+#[cfg(target_os = "macos")]
+fn create_reopen_window(app: &tauri::AppHandle) -> Result<(), AppError> {
     use std::sync::atomic::{AtomicUsize, Ordering};
     
     static REOPEN_COUNTER: AtomicUsize = AtomicUsize::new(1);
@@ -814,14 +1058,15 @@ fn create_reopen_window(app: &tauri::AppHandle) -> Result<(), String> {
     WebviewWindowBuilder::new(
         app,
         &window_label,
-        WebviewUrl::External(target_url.parse().map_err(|e| format!("Invalid URL: {}", e))?),
+        WebviewUrl::External(target_url.parse().map_err(|e| AppError::InvalidUrl(format!("Invalid URL: {}", e)))?),
     )
     .title(format!("Backstage68 - {}", env_name()))
     .inner_size(1200.0, 800.0)
     .resizable(true)
     .initialization_script(&final_script)
+    .on_page_load(cookie_sync::on_page_load)
     .build()
-    .map_err(|e| format!("Failed to create window: {}", e))?;
+    .map_err(|e| AppError::Internal(format!("Failed to create window: {}", e)))?;
     
     log!("✓ New window created: {}", window_label);
     Ok(())
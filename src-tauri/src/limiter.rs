@@ -0,0 +1,172 @@
+/// 按主机并发限流模块
+/// 每个 scheme://host:port 对应一个信号量，代替之前贯穿整个请求的全局锁，
+/// 不同主机之间互不影响，同一主机最多同时发出 N 个请求（与浏览器的每主机连接上限类似）；
+/// 没有请求在用的信号量会在下次获取许可时清理，访问过的主机再多也不会一直占用内存
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::error::AppError;
+
+/// 默认每个主机的最大并发请求数（Chrome 对 HTTP/1.1 同样限制为 6）
+const DEFAULT_MAX_PER_HOST: usize = 6;
+
+/// 从编译期环境变量读取每主机并发上限（TAURI_PROXY_MAX_PER_HOST）
+pub fn max_per_host_from_env() -> usize {
+    option_env!("TAURI_PROXY_MAX_PER_HOST")
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_MAX_PER_HOST)
+}
+
+pub struct HostLimiter {
+    max_per_host: usize,
+    // 只在查找/插入信号量时短暂持有，不跨 await
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+    pub fn new(max_per_host: usize) -> Self {
+        Self {
+            max_per_host: max_per_host.max(1),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 获取目标主机的并发许可，许可在返回值被 drop 时自动归还
+    pub async fn acquire(&self, url: &str) -> Result<OwnedSemaphorePermit, AppError> {
+        let semaphore = {
+            let mut hosts = self
+                .hosts
+                .lock()
+                .map_err(|_| AppError::Internal("Host limiter poisoned".to_string()))?;
+            // 信号量只被表本身引用时，说明没有请求持有或等待它的许可，可以清理
+            hosts.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            hosts
+                .entry(host_key(url))
+                .or_insert_with(|| Arc::new(Semaphore::new(self.max_per_host)))
+                .clone()
+        };
+
+        semaphore
+            .acquire_owned()
+            .await
+            .map_err(|e| AppError::Internal(format!("Failed to acquire host permit: {}", e)))
+    }
+
+    #[cfg(test)]
+    fn tracked_hosts(&self) -> usize {
+        self.hosts.lock().map(|hosts| hosts.len()).unwrap_or(0)
+    }
+}
+
+/// 提取限流维度：scheme://host:port，无法解析时退化为原始 URL
+pub(crate) fn host_key(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => format!(
+            "{}://{}:{}",
+            parsed.scheme(),
+            parsed.host_str().unwrap_or(""),
+            parsed.port_or_known_default().unwrap_or(0)
+        ),
+        Err(_) => url.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const DELAY: Duration = Duration::from_millis(300);
+
+    /// 每个请求都先等待 DELAY 再响应的本地服务，返回地址与观察到的最大并发数
+    async fn slow_server() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let observed = Arc::clone(&peak);
+        tokio::spawn(async move {
+            loop {
+                let Ok((mut socket, _)) = listener.accept().await else {
+                    return;
+                };
+                let active = Arc::clone(&active);
+                let peak = Arc::clone(&peak);
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 1024];
+                    let _ = socket.read(&mut buf).await;
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(DELAY).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    let _ = socket
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        )
+                        .await;
+                });
+            }
+        });
+        (url, observed)
+    }
+
+    /// 以 limiter 限流并行发出 n 个请求，返回总耗时
+    async fn run_parallel(limiter: Arc<HostLimiter>, url: &str, n: usize) -> Duration {
+        let client = reqwest::Client::new();
+        let started = Instant::now();
+        let tasks: Vec<_> = (0..n)
+            .map(|_| {
+                let limiter = Arc::clone(&limiter);
+                let client = client.clone();
+                let url = url.to_string();
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire(&url).await.unwrap();
+                    client.get(&url).send().await.unwrap().status().as_u16()
+                })
+            })
+            .collect();
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 200);
+        }
+        started.elapsed()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_parallel_requests_take_one_round_trip() {
+        let (url, peak) = slow_server().await;
+        let limiter = Arc::new(HostLimiter::new(6));
+        let elapsed = run_parallel(limiter, &url, 6).await;
+        // 串行需要 6 个往返，并行应接近 1 个
+        assert!(elapsed < DELAY * 2, "elapsed {:?}", elapsed);
+        assert_eq!(peak.load(Ordering::SeqCst), 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_per_host_cap() {
+        let (url, peak) = slow_server().await;
+        let limiter = Arc::new(HostLimiter::new(2));
+        let elapsed = run_parallel(Arc::clone(&limiter), &url, 6).await;
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert!(elapsed >= DELAY * 3, "elapsed {:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_idle_hosts_are_evicted() {
+        let limiter = HostLimiter::new(2);
+        let held = limiter.acquire("https://a.example.com/").await.unwrap();
+        drop(limiter.acquire("https://b.example.com/").await.unwrap());
+        assert_eq!(limiter.tracked_hosts(), 2);
+
+        // b 已空闲，获取 c 时被清理；a 仍有请求持有许可
+        let _c = limiter.acquire("https://c.example.com/").await.unwrap();
+        assert_eq!(limiter.tracked_hosts(), 2);
+        drop(held);
+        drop(limiter.acquire("https://c.example.com/").await.unwrap());
+        assert_eq!(limiter.tracked_hosts(), 1);
+    }
+}
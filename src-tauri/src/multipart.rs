@@ -0,0 +1,293 @@
+/// multipart 请求体构建
+/// 按表单项顺序构建 multipart 请求体，文件内容以流的形式写入，不整体读入内存；
+/// 带请求 ID 时向发起请求的 webview 推送 upload-progress 事件（其他窗口收不到）。
+/// 同时提供签名用的文件哈希与小文件的整体读取
+use base64::{engine::general_purpose, Engine as _};
+use futures_util::StreamExt;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use tauri::{Emitter, EventTarget};
+
+use crate::error::AppError;
+use crate::upload::{FileSource, FormPart, UploadStore};
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 上传进度事件的最小推送间隔
+const PROGRESS_INTERVAL_MS: u128 = 200;
+
+#[derive(Clone, Serialize)]
+struct UploadProgress {
+    id: String,
+    uploaded: u64,
+    total_size: u64,
+    percent: u8,
+}
+
+/// 统计上传字节数并节流推送 upload-progress 事件
+pub(crate) struct UploadTracker {
+    app: tauri::AppHandle,
+    /// 接收进度事件的 webview
+    webview: String,
+    id: String,
+    total_size: u64,
+    uploaded: AtomicU64,
+    last_emit: Mutex<Instant>,
+}
+
+impl UploadTracker {
+    fn new(app: tauri::AppHandle, webview: String, id: String, total_size: u64) -> Self {
+        Self {
+            app,
+            webview,
+            id,
+            total_size,
+            uploaded: AtomicU64::new(0),
+            last_emit: Mutex::new(Instant::now()),
+        }
+    }
+
+    fn add(&self, bytes: u64) {
+        let uploaded = self.uploaded.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let finished = uploaded >= self.total_size;
+
+        if let Ok(mut last_emit) = self.last_emit.lock() {
+            if !finished && last_emit.elapsed().as_millis() < PROGRESS_INTERVAL_MS {
+                return;
+            }
+            *last_emit = Instant::now();
+        }
+
+        let percent = if self.total_size > 0 {
+            ((uploaded as f64 / self.total_size as f64) * 100.0).min(100.0) as u8
+        } else {
+            100
+        };
+        let _ = self.app.emit_to(
+            EventTarget::webview(self.webview.as_str()),
+            "upload-progress",
+            UploadProgress {
+                id: self.id.clone(),
+                uploaded,
+                total_size: self.total_size,
+                percent,
+            },
+        );
+    }
+}
+
+/// 按顺序构建 multipart 表单，文件内容以流的形式读取；webview 为发起请求的窗口
+pub(crate) async fn build_multipart(
+    app: &tauri::AppHandle,
+    uploads: &UploadStore,
+    webview: Option<&str>,
+    request_id: Option<&str>,
+    parts: &[FormPart],
+) -> Result<reqwest::multipart::Form, AppError> {
+    // 先解析所有文件来源，得到总大小用于进度计算
+    let mut resolved = Vec::with_capacity(parts.len());
+    let mut total_size = 0u64;
+    for part in parts {
+        let source = match part {
+            FormPart::Text { .. } => None,
+            FormPart::File { source, .. } => {
+                let resolved_source =
+                    resolve_source(uploads, webview.unwrap_or_default(), source).await?;
+                total_size += resolved_source.len();
+                Some(resolved_source)
+            }
+        };
+        resolved.push(source);
+    }
+
+    let tracker = request_id.zip(webview).map(|(id, webview)| {
+        Arc::new(UploadTracker::new(
+            app.clone(),
+            webview.to_string(),
+            id.to_string(),
+            total_size,
+        ))
+    });
+
+    let mut form = reqwest::multipart::Form::new();
+    for (part, source) in parts.iter().zip(resolved) {
+        match part {
+            FormPart::Text { name, value } => {
+                log!("   表单字段: {} = {}", name, value);
+                form = form.text(name.clone(), value.clone());
+            }
+            FormPart::File {
+                name,
+                file_name,
+                content_type,
+                ..
+            } => {
+                let source =
+                    source.ok_or_else(|| AppError::Internal("文件来源解析失败".to_string()))?;
+                log!(
+                    "   文件: {} ({}, {} bytes)",
+                    file_name,
+                    content_type,
+                    source.len()
+                );
+                let file_part = source
+                    .into_part(tracker.clone())
+                    .await?
+                    .file_name(file_name.clone())
+                    .mime_str(content_type)
+                    .map_err(|e| {
+                        AppError::InvalidRequest(format!("Invalid content type: {}", e))
+                    })?;
+                form = form.part(name.clone(), file_part);
+            }
+        }
+    }
+
+    Ok(form)
+}
+
+enum ResolvedSource {
+    Bytes(Vec<u8>),
+    File { path: PathBuf, len: u64 },
+}
+
+impl ResolvedSource {
+    fn len(&self) -> u64 {
+        match self {
+            ResolvedSource::Bytes(bytes) => bytes.len() as u64,
+            ResolvedSource::File { len, .. } => *len,
+        }
+    }
+
+    async fn into_part(
+        self,
+        tracker: Option<Arc<UploadTracker>>,
+    ) -> Result<reqwest::multipart::Part, AppError> {
+        match self {
+            ResolvedSource::Bytes(bytes) => {
+                if let Some(tracker) = &tracker {
+                    tracker.add(bytes.len() as u64);
+                }
+                Ok(reqwest::multipart::Part::bytes(bytes))
+            }
+            ResolvedSource::File { path, len } => {
+                let file = tokio::fs::File::open(&path).await.map_err(|e| {
+                    AppError::Io(format!("打开上传文件失败 {}: {}", path.display(), e))
+                })?;
+                let stream = tokio_util::io::ReaderStream::new(file).inspect(move |chunk| {
+                    if let (Some(tracker), Ok(bytes)) = (&tracker, chunk) {
+                        tracker.add(bytes.len() as u64);
+                    }
+                });
+                Ok(reqwest::multipart::Part::stream_with_length(
+                    reqwest::Body::wrap_stream(stream),
+                    len,
+                ))
+            }
+        }
+    }
+}
+
+/// 解析文件来源；上传句柄只能由创建它的 webview（owner）引用
+async fn resolve_source(
+    uploads: &UploadStore,
+    owner: &str,
+    source: &FileSource,
+) -> Result<ResolvedSource, AppError> {
+    let path = match source {
+        FileSource::Data(data) => {
+            let bytes = general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| AppError::InvalidRequest(format!("Failed to decode file: {}", e)))?;
+            return Ok(ResolvedSource::Bytes(bytes));
+        }
+        FileSource::Upload(upload_id) => uploads.lease(upload_id, owner)?,
+    };
+
+    let metadata = tokio::fs::metadata(&path)
+        .await
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => AppError::NotFound(path.display().to_string()),
+            _ => AppError::Io(format!("读取上传文件失败 {}: {}", path.display(), e)),
+        })?;
+    if !metadata.is_file() {
+        return Err(AppError::InvalidRequest(format!(
+            "不是普通文件: {}",
+            path.display()
+        )));
+    }
+
+    Ok(ResolvedSource::File {
+        path,
+        len: metadata.len(),
+    })
+}
+
+/// 整体读入文件来源（证书等小文件）；上传句柄读完即释放
+pub(crate) async fn read_source(
+    uploads: &UploadStore,
+    owner: &str,
+    source: &FileSource,
+) -> Result<Vec<u8>, AppError> {
+    match resolve_source(uploads, owner, source).await? {
+        ResolvedSource::Bytes(bytes) => Ok(bytes),
+        ResolvedSource::File { path, .. } => {
+            let bytes = tokio::fs::read(&path).await?;
+            if let FileSource::Upload(upload_id) = source {
+                uploads.release(upload_id).await;
+            }
+            Ok(bytes)
+        }
+    }
+}
+
+/// 文件来源内容的 SHA-256（十六进制，用于请求签名）；文件按块读取，不整体载入内存
+pub(crate) async fn hash_source(
+    uploads: &UploadStore,
+    owner: &str,
+    source: &FileSource,
+) -> Result<String, AppError> {
+    use sha2::{Digest, Sha256};
+    use tokio::io::AsyncReadExt;
+
+    let mut hasher = Sha256::new();
+    match resolve_source(uploads, owner, source).await? {
+        ResolvedSource::Bytes(bytes) => hasher.update(&bytes),
+        ResolvedSource::File { path, .. } => {
+            let mut file = tokio::fs::File::open(&path)
+                .await
+                .map_err(|e| AppError::Io(format!("打开上传文件失败 {}: {}", path.display(), e)))?;
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let read = file.read(&mut buffer).await?;
+                if read == 0 {
+                    break;
+                }
+                hasher.update(&buffer[..read]);
+            }
+        }
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
@@ -0,0 +1,209 @@
+/// 代理目标来源策略
+/// proxy_request 会附加设备指纹、设备信息和 RSA 签名头，不能变成任意 URL 的开放中转。
+/// 按来源（scheme://host:port）决定：签名后代理、不签名代理或拒绝。
+/// 签名来源默认只有 TAURI_ENV_URL 的来源，可通过 TAURI_PROXY_SIGNED_ORIGINS 追加；
+/// TAURI_PROXY_UNSIGNED_ORIGINS 中的来源只转发、不附加任何设备与签名头；其余一律拒绝。
+/// 列表以逗号分隔，支持 https://*.example.com 形式匹配子域名
+use crate::error::AppError;
+
+/// 目标来源的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OriginAccess {
+    /// 代理并附加验证头
+    Signed,
+    /// 代理但不附加设备指纹、设备信息与签名
+    Unsigned,
+    /// 拒绝代理
+    Denied,
+}
+
+/// 来源匹配规则
+#[derive(Debug, Clone)]
+struct OriginPattern {
+    scheme: String,
+    host: String,
+    port: u16,
+    /// *.host：只匹配子域名，不含 host 本身
+    wildcard: bool,
+}
+
+impl OriginPattern {
+    fn parse(entry: &str) -> Option<Self> {
+        let (scheme, rest) = entry.trim().split_once("://")?;
+        let (wildcard, rest) = match rest.strip_prefix("*.") {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        let url = reqwest::Url::parse(&format!("{}://{}", scheme, rest)).ok()?;
+        Some(Self {
+            scheme: url.scheme().to_string(),
+            host: url.host_str()?.to_ascii_lowercase(),
+            port: url.port_or_known_default()?,
+            wildcard,
+        })
+    }
+
+    fn matches(&self, url: &reqwest::Url) -> bool {
+        let Some(host) = url.host_str().map(str::to_ascii_lowercase) else {
+            return false;
+        };
+        let host_matches = if self.wildcard {
+            host.strip_suffix(self.host.as_str())
+                .is_some_and(|prefix| prefix.ends_with('.'))
+        } else {
+            host == self.host
+        };
+        url.scheme() == self.scheme && url.port_or_known_default() == Some(self.port) && host_matches
+    }
+}
+
+pub struct OriginPolicy {
+    signed: Vec<OriginPattern>,
+    unsigned: Vec<OriginPattern>,
+}
+
+impl OriginPolicy {
+    /// 由后端地址和编译期环境变量构建
+    pub fn from_env(env_url: &str) -> Self {
+        Self::new(
+            env_url,
+            option_env!("TAURI_PROXY_SIGNED_ORIGINS"),
+            option_env!("TAURI_PROXY_UNSIGNED_ORIGINS"),
+        )
+    }
+
+    fn new(env_url: &str, signed: Option<&str>, unsigned: Option<&str>) -> Self {
+        let mut patterns: Vec<OriginPattern> = OriginPattern::parse(env_url).into_iter().collect();
+        patterns.extend(parse_list(signed));
+        Self {
+            signed: patterns,
+            unsigned: parse_list(unsigned),
+        }
+    }
+
+    /// 判断目标 URL 的处理方式；签名来源优先，无法解析的 URL 视为拒绝
+    pub fn classify(&self, url: &str) -> OriginAccess {
+        let Ok(url) = reqwest::Url::parse(url) else {
+            return OriginAccess::Denied;
+        };
+        if self.signed.iter().any(|pattern| pattern.matches(&url)) {
+            OriginAccess::Signed
+        } else if self.unsigned.iter().any(|pattern| pattern.matches(&url)) {
+            OriginAccess::Unsigned
+        } else {
+            OriginAccess::Denied
+        }
+    }
+
+    /// 拒绝的来源直接返回错误，否则返回是否需要签名
+    pub fn check(&self, url: &str) -> Result<bool, AppError> {
+        match self.classify(url) {
+            OriginAccess::Signed => Ok(true),
+            OriginAccess::Unsigned => Ok(false),
+            OriginAccess::Denied => Err(AppError::OriginDenied(url.to_string())),
+        }
+    }
+}
+
+fn parse_list(value: Option<&str>) -> Vec<OriginPattern> {
+    value
+        .unwrap_or_default()
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(OriginPattern::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> OriginPolicy {
+        OriginPolicy::new(
+            "https://api.example.com/base_api",
+            Some("https://*.example.com, http://localhost:8080"),
+            Some("https://cdn.example.net, https://*.example.com"),
+        )
+    }
+
+    #[test]
+    fn test_exact_origin_and_default_port() {
+        let policy = policy();
+        assert_eq!(
+            policy.classify("https://api.example.com/base_api/dict/list"),
+            OriginAccess::Signed
+        );
+        // 默认端口写不写都一样
+        assert_eq!(
+            policy.classify("https://api.example.com:443/x"),
+            OriginAccess::Signed
+        );
+        assert_eq!(
+            policy.classify("https://cdn.example.net:8443/x"),
+            OriginAccess::Denied
+        );
+        assert_eq!(
+            policy.classify("http://localhost:8080/x"),
+            OriginAccess::Signed
+        );
+        assert_eq!(policy.classify("http://localhost/x"), OriginAccess::Denied);
+    }
+
+    #[test]
+    fn test_wildcard_matches_subdomains_only() {
+        let policy = OriginPolicy::new("https://backend.test", Some("https://*.example.com"), None);
+        assert_eq!(
+            policy.classify("https://a.example.com/"),
+            OriginAccess::Signed
+        );
+        assert_eq!(
+            policy.classify("https://a.b.EXAMPLE.com/"),
+            OriginAccess::Signed
+        );
+        for url in [
+            "https://example.com/",
+            "https://evilexample.com/",
+            "https://example.com.evil.test/",
+        ] {
+            assert_eq!(policy.classify(url), OriginAccess::Denied, "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_scheme_mismatch_is_denied() {
+        let policy = policy();
+        assert_eq!(
+            policy.classify("http://api.example.com/x"),
+            OriginAccess::Denied
+        );
+        assert_eq!(
+            policy.classify("wss://api.example.com/x"),
+            OriginAccess::Denied
+        );
+    }
+
+    #[test]
+    fn test_unparsable_urls_are_denied() {
+        let policy = policy();
+        for url in ["", "not a url", "/base_api/relative", "https://"] {
+            assert_eq!(policy.classify(url), OriginAccess::Denied, "{:?}", url);
+            assert!(matches!(policy.check(url), Err(AppError::OriginDenied(_))));
+        }
+    }
+
+    #[test]
+    fn test_signed_takes_precedence_over_unsigned() {
+        let policy = policy();
+        // *.example.com 同时出现在两个列表里，按签名来源处理
+        assert_eq!(
+            policy.classify("https://static.example.com/a.png"),
+            OriginAccess::Signed
+        );
+        assert_eq!(
+            policy.classify("https://cdn.example.net/a.png"),
+            OriginAccess::Unsigned
+        );
+        assert_eq!(policy.check("https://static.example.com/").ok(), Some(true));
+        assert_eq!(policy.check("https://cdn.example.net/").ok(), Some(false));
+    }
+}
@@ -0,0 +1,300 @@
+/// PAC 脚本求值
+/// 使用内置的 JavaScript 引擎（boa）执行 FindProxyForURL。PAC 辅助函数按 Netscape 规范用 JS 实现，
+/// 只有 dnsResolve / myIpAddress 由 Rust 提供。每次求值新建上下文，结果由调用方按主机缓存。
+/// 未启用 pac 特性时求值直接失败（调用方按直连处理）
+///
+/// 求值会做 DNS 查询，不能在 reqwest 的连接器里同步执行：发送前由 PacScript::resolve
+/// 在阻塞线程池求值并按主机缓存，连接器只读缓存
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::error::AppError;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 下载 PAC 脚本的超时
+const PAC_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// PAC 结果缓存的主机数上限，超过后整体清空
+const PAC_CACHE_LIMIT: usize = 512;
+
+#[cfg(feature = "pac")]
+mod engine {
+    use boa_engine::{Context, JsResult, JsString, JsValue, NativeFunction, Source};
+    use std::net::{ToSocketAddrs, UdpSocket};
+
+    /// 标准 PAC 辅助函数
+    const PAC_PRELUDE: &str = r#"
+function isPlainHostName(host) { return host.indexOf('.') < 0; }
+function dnsDomainIs(host, domain) {
+  return host.length >= domain.length && host.substring(host.length - domain.length) === domain;
+}
+function localHostOrDomainIs(host, hostdom) {
+  return host === hostdom || hostdom.lastIndexOf(host + '.', 0) === 0;
+}
+function isResolvable(host) { return dnsResolve(host) !== null; }
+function dnsDomainLevels(host) { return host.split('.').length - 1; }
+function convert_addr(ipchars) {
+  var bytes = ipchars.split('.');
+  return ((bytes[0] & 0xff) << 24) | ((bytes[1] & 0xff) << 16) | ((bytes[2] & 0xff) << 8) | (bytes[3] & 0xff);
+}
+function isInNet(ipaddr, pattern, maskstr) {
+  if (!/^\d+\.\d+\.\d+\.\d+$/.test(ipaddr)) {
+    ipaddr = dnsResolve(ipaddr);
+    if (ipaddr === null) return false;
+  }
+  var mask = convert_addr(maskstr);
+  return (convert_addr(ipaddr) & mask) === (convert_addr(pattern) & mask);
+}
+function shExpMatch(str, shexp) {
+  var re = shexp.replace(/[.+^${}()|[\]\\]/g, '\\$&').replace(/\*/g, '.*').replace(/\?/g, '.');
+  return new RegExp('^' + re + '$').test(str);
+}
+var __days = ['SUN', 'MON', 'TUE', 'WED', 'THU', 'FRI', 'SAT'];
+function weekdayRange(wd1, wd2, gmt) {
+  if (wd2 === 'GMT') { gmt = wd2; wd2 = undefined; }
+  var now = new Date();
+  var day = gmt === 'GMT' ? now.getUTCDay() : now.getDay();
+  var d1 = __days.indexOf(wd1);
+  var d2 = wd2 === undefined ? d1 : __days.indexOf(wd2);
+  return d1 <= d2 ? (day >= d1 && day <= d2) : (day >= d1 || day <= d2);
+}
+function timeRange() {
+  var args = Array.prototype.slice.call(arguments);
+  var gmt = args[args.length - 1] === 'GMT';
+  if (gmt) args.pop();
+  var now = new Date();
+  var hour = gmt ? now.getUTCHours() : now.getHours();
+  if (args.length === 1) return hour === args[0];
+  if (args.length === 2) return args[0] <= args[1] ? (hour >= args[0] && hour < args[1]) : (hour >= args[0] || hour < args[1]);
+  return true;
+}
+function dateRange() { return true; }
+"#;
+
+    fn dns_resolve(_this: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+        let host = match args.first() {
+            Some(value) => value.to_string(context)?.to_std_string_escaped(),
+            None => return Ok(JsValue::null()),
+        };
+        let resolved = (host.as_str(), 0)
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut addrs| addrs.find(|addr| addr.is_ipv4()))
+            .map(|addr| addr.ip().to_string());
+        Ok(match resolved {
+            Some(ip) => JsValue::from(JsString::from(ip.as_str())),
+            None => JsValue::null(),
+        })
+    }
+
+    fn my_ip_address(_this: &JsValue, _args: &[JsValue], _context: &mut Context) -> JsResult<JsValue> {
+        // 不实际发包，只借路由表确定出口地址
+        let ip = UdpSocket::bind("0.0.0.0:0")
+            .and_then(|socket| socket.connect("8.8.8.8:80").map(|_| socket))
+            .and_then(|socket| socket.local_addr())
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|_| "127.0.0.1".to_string());
+        Ok(JsValue::from(JsString::from(ip.as_str())))
+    }
+
+    pub fn find_proxy_for_url(source: &str, url: &str, host: &str) -> Result<String, String> {
+        let mut context = Context::default();
+        context
+            .register_global_callable(JsString::from("dnsResolve"), 1, NativeFunction::from_fn_ptr(dns_resolve))
+            .map_err(|e| e.to_string())?;
+        context
+            .register_global_callable(JsString::from("myIpAddress"), 0, NativeFunction::from_fn_ptr(my_ip_address))
+            .map_err(|e| e.to_string())?;
+        context
+            .eval(Source::from_bytes(PAC_PRELUDE))
+            .map_err(|e| e.to_string())?;
+        context
+            .eval(Source::from_bytes(source))
+            .map_err(|e| e.to_string())?;
+
+        // 参数以 JSON 字符串字面量传入，避免引号转义问题
+        let call = format!(
+            "FindProxyForURL({}, {})",
+            serde_json::to_string(url).map_err(|e| e.to_string())?,
+            serde_json::to_string(host).map_err(|e| e.to_string())?
+        );
+        let result = context
+            .eval(Source::from_bytes(&call))
+            .map_err(|e| e.to_string())?;
+        Ok(result
+            .to_string(&mut context)
+            .map_err(|e| e.to_string())?
+            .to_std_string_escaped())
+    }
+}
+
+#[cfg(feature = "pac")]
+pub use engine::find_proxy_for_url;
+
+#[cfg(not(feature = "pac"))]
+pub fn find_proxy_for_url(_source: &str, _url: &str, _host: &str) -> Result<String, String> {
+    Err("当前构建未启用 PAC 支持".to_string())
+}
+
+/// 已下载的 PAC 脚本，按 scheme://host:port 缓存求值结果
+pub struct PacScript {
+    source: Arc<str>,
+    cache: Mutex<HashMap<String, Option<reqwest::Url>>>,
+}
+
+impl PacScript {
+    pub fn new(source: String) -> Self {
+        Self {
+            source: source.into(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 发送前求值：未缓存时在阻塞线程池执行脚本
+    pub async fn resolve(&self, url: &reqwest::Url) -> Option<reqwest::Url> {
+        let key = cache_key(url);
+        if let Some(cached) = self.cached(&key) {
+            return cached;
+        }
+        let source = Arc::clone(&self.source);
+        let target = url.clone();
+        let result = tokio::task::spawn_blocking(move || evaluate(&source, &target))
+            .await
+            .unwrap_or(None);
+        self.store(key, result.clone());
+        result
+    }
+
+    /// 连接器中调用，正常情况下 resolve 已写入缓存；
+    /// 只有设置恰在两者之间切换、或缓存刚被清空时才在这里同步求值
+    pub fn proxy_for(&self, url: &reqwest::Url) -> Option<reqwest::Url> {
+        let key = cache_key(url);
+        if let Some(cached) = self.cached(&key) {
+            return cached;
+        }
+        log!("⚠️  [Proxy] PAC 未预先求值，同步求值 {}", key);
+        let result = evaluate(&self.source, url);
+        self.store(key, result.clone());
+        result
+    }
+
+    fn cached(&self, key: &str) -> Option<Option<reqwest::Url>> {
+        self.cache.lock().ok().and_then(|cache| cache.get(key).cloned())
+    }
+
+    fn store(&self, key: String, result: Option<reqwest::Url>) {
+        if let Ok(mut cache) = self.cache.lock() {
+            if cache.len() >= PAC_CACHE_LIMIT {
+                cache.clear();
+            }
+            cache.insert(key, result);
+        }
+    }
+}
+
+fn cache_key(url: &reqwest::Url) -> String {
+    format!(
+        "{}://{}:{}",
+        url.scheme(),
+        url.host_str().unwrap_or_default(),
+        url.port_or_known_default().unwrap_or_default()
+    )
+}
+
+/// 执行 FindProxyForURL（可能阻塞在 DNS 查询上）
+fn evaluate(source: &str, url: &reqwest::Url) -> Option<reqwest::Url> {
+    let result = match find_proxy_for_url(source, url.as_str(), url.host_str().unwrap_or_default()) {
+        Ok(result) => parse_pac_result(&result),
+        Err(e) => {
+            log!("⚠️  [Proxy] PAC 求值失败，直连 {}: {}", cache_key(url), e);
+            None
+        }
+    };
+    log!("🧭 [Proxy] PAC {} -> {:?}", cache_key(url), result.as_ref().map(|u| u.as_str()));
+    result
+}
+
+/// 解析 FindProxyForURL 返回值，取第一个可用项（如 "PROXY a:8080; SOCKS5 b:1080; DIRECT"）
+fn parse_pac_result(result: &str) -> Option<reqwest::Url> {
+    for directive in result.split(';') {
+        let mut parts = directive.split_whitespace();
+        let kind = parts.next().unwrap_or_default().to_ascii_uppercase();
+        let target = parts.next().unwrap_or_default();
+        let scheme = match kind.as_str() {
+            "DIRECT" => return None,
+            "PROXY" | "HTTP" => "http",
+            "HTTPS" => "https",
+            "SOCKS" | "SOCKS5" => "socks5",
+            _ => continue,
+        };
+        if let Ok(url) = reqwest::Url::parse(&format!("{}://{}", scheme, target)) {
+            return Some(url);
+        }
+    }
+    None
+}
+
+/// 下载 PAC 脚本（直连，不经过任何代理）；只接受 http(s)://，
+/// 地址来自前端设置，不能借此读取本地文件
+pub async fn fetch_pac(pac_url: &str) -> Result<String, AppError> {
+    let url = reqwest::Url::parse(pac_url)
+        .map_err(|e| AppError::InvalidUrl(format!("PAC 地址 {}: {}", pac_url, e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(AppError::InvalidUrl(format!("PAC 地址须为 http:// 或 https://: {}", pac_url)));
+    }
+    if !cfg!(feature = "pac") {
+        return Err(AppError::InvalidRequest("当前构建未启用 PAC 支持".to_string()));
+    }
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(PAC_FETCH_TIMEOUT)
+        .build()?;
+    let response = client.get(url).send().await?;
+    let status = response.status().as_u16();
+    if !(200..300).contains(&status) {
+        return Err(AppError::HttpStatus(status));
+    }
+    Ok(response.text().await?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pac_result() {
+        let proxy = parse_pac_result("PROXY 10.0.0.1:8080; DIRECT").unwrap();
+        assert_eq!(proxy.as_str(), "http://10.0.0.1:8080/");
+        let proxy = parse_pac_result("BOGUS x; SOCKS5 10.0.0.2:1080").unwrap();
+        assert_eq!(proxy.scheme(), "socks5");
+        assert!(parse_pac_result("DIRECT; PROXY 10.0.0.1:8080").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_pac_rejects_file_urls() {
+        for url in ["file:///etc/passwd", "ftp://example.com/proxy.pac"] {
+            assert!(matches!(fetch_pac(url).await, Err(AppError::InvalidUrl(_))));
+        }
+    }
+}
@@ -1,11 +1,37 @@
-use crate::crypto::{encrypt_signature, generate_signature_data};
-use crate::fingerprint::{get_device_fingerprint, get_device_info_json};
-use base64::{engine::general_purpose, Engine as _};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+/// HTTP 代理模块
+/// 页面的 fetch / XHR 经 proxy_request 由 Rust 发出：共享状态 AppState、请求与响应的传输格式，
+/// 以及一次代理请求的完整流程（合并相同 GET、查缓存、发送并跟随重定向、解码响应体）。
+/// 请求的构建与按策略发送见 dispatch 模块
+use std::borrow::Cow;
 use std::sync::Arc;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
 use tauri::State;
-use tokio::sync::Mutex;
+
+use crate::cache_policy::forces_revalidation;
+use crate::cancel::{run_cancellable, InflightRequests};
+use crate::challenge::ChallengeStore;
+use crate::clock::ServerClock;
+use crate::coalesce::{InflightGets, Joined, COALESCED_HEADER};
+use crate::cookie_jar::PersistentCookieJar;
+use crate::cookie_sync::CookieSync;
+use crate::dispatch::{build_debug_info, read_body, ProxyDebugInfo, SentRequest};
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::http_cache::{CachedResponse, HttpCache, CACHE_STATUS_HEADER};
+use crate::http_client::HttpClients;
+use crate::identity_store::ClientIdentityStore;
+use crate::limiter::HostLimiter;
+use crate::origin_policy::OriginPolicy;
+use crate::redirect::{send_following_redirects, Followed, RedirectHop, RedirectMode};
+use crate::resilience::{CircuitBreakers, ProxyPolicy, ProxyTimeouts};
+use crate::session_key::SessionKeys;
+use crate::signature_scheme::SignatureSchemes;
+use crate::signing::SigningPipeline;
+use crate::sniff::{decode_body, DecodedBody};
+use crate::upload::{FileSource, FormPart, UploadStore};
+use crate::websocket::WebSocketConnections;
 
 // 编译时判断是否启用日志（使用字节比较避免 const 限制）
 #[cfg(debug_assertions)]
@@ -28,50 +54,31 @@ macro_rules! log {
     };
 }
 
+/// 代理共享状态
+/// 不再整体加锁：reqwest::Client 内部自带连接池且可并发使用，
+/// 并发控制交给按主机划分的 HostLimiter
 pub struct AppState {
-    pub client: reqwest::Client,
-}
-
-// 判断 Content-Type 是否为二进制类型
-fn is_binary_content_type(content_type: &str) -> bool {
-    // 文本类型（返回 false）
-    if content_type.starts_with("text/")
-        || content_type.contains("application/json")
-        || content_type.contains("application/xml")
-        || content_type.contains("application/javascript")
-        || content_type.contains("application/x-www-form-urlencoded")
-        || content_type.contains("application/ld+json")
-        || content_type.contains("application/vnd.api+json")
-        || content_type.is_empty()
-    {
-        return false;
-    }
-
-    // 明确的二进制类型（返回 true）
-    if content_type.starts_with("image/")
-        || content_type.starts_with("audio/")
-        || content_type.starts_with("video/")
-        || content_type.contains("application/octet-stream")
-        || content_type.contains("application/pdf")
-        || content_type.contains("application/zip")
-        || content_type.contains("application/x-rar")
-        || content_type.contains("application/x-tar")
-        || content_type.contains("application/x-gzip")
-        || content_type.contains("application/vnd.ms-excel")
-        || content_type.contains("application/vnd.openxmlformats-officedocument")
-        || content_type.contains("application/msword")
-        || content_type.contains("application/vnd.ms-powerpoint")
-        || content_type.contains("font/")
-        || content_type.contains("application/x-font")
-    {
-        return true;
-    }
-
-    // 默认：其他 application/* 视为二进制
-    content_type.starts_with("application/")
+    pub http: HttpClients,
+    pub identity: ClientIdentityStore,
+    pub limiter: HostLimiter,
+    pub uploads: UploadStore,
+    pub inflight: InflightRequests,
+    pub inflight_gets: InflightGets,
+    pub policy: ProxyPolicy,
+    pub breakers: CircuitBreakers,
+    pub cache: HttpCache,
+    pub origins: OriginPolicy,
+    pub cookies: Arc<PersistentCookieJar>,
+    pub cookie_sync: CookieSync,
+    pub signers: SigningPipeline,
+    pub challenge: ChallengeStore,
+    pub clock: ServerClock,
+    pub sessions: SessionKeys,
+    pub schemes: SignatureSchemes,
+    pub websockets: WebSocketConnections,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FormDataFile {
     pub field_name: String,
     pub file_name: String,
@@ -79,206 +86,238 @@ pub struct FormDataFile {
     pub data: String, // base64 encoded
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyRequest {
+    /// 前端生成的请求 ID，用于取消请求和关联 upload-progress 等事件
+    #[serde(default)]
+    pub id: Option<String>,
     pub method: String,
     pub url: String,
-    pub headers: HashMap<String, String>,
+    /// 请求头，保留顺序与重复项；兼容旧的对象写法
+    pub headers: HeaderList,
     pub body: Option<String>, // Text body for JSON/text requests
     pub form_data: Option<Vec<(String, String)>>, // 表单字段：[(key, value), ...]
-    pub files: Option<Vec<FormDataFile>>, // 文件数据
+    pub files: Option<Vec<FormDataFile>>, // 文件数据（base64，旧格式）
+    /// 有序的 multipart 表单项，优先于 form_data/files
+    #[serde(default)]
+    pub parts: Option<Vec<FormPart>>,
+    /// 覆盖全局超时设置
+    #[serde(default)]
+    pub timeouts: Option<ProxyTimeouts>,
+    /// 覆盖全局最大重试次数（仅幂等方法会重试）
+    #[serde(default)]
+    pub max_retries: Option<u32>,
+    /// 重定向模式：follow / error / manual
+    #[serde(default)]
+    pub redirect: RedirectMode,
+    /// 是否允许与同时进行中的相同 GET 合并为一次上游请求（默认允许）
+    #[serde(default = "default_coalesce")]
+    pub coalesce: bool,
+    /// 发起请求的 webview，由命令填入、不接受前端传值；
+    /// 上传进度只发给它，也只能引用它创建的上传句柄
+    #[serde(skip)]
+    pub(crate) webview: Option<String>,
+}
+
+fn default_coalesce() -> bool {
+    true
+}
+
+impl ProxyRequest {
+    /// 统一得到有序的 multipart 表单项；旧格式按“字段在前、文件在后”转换
+    pub(crate) fn multipart_parts(&self) -> Option<Cow<'_, [FormPart]>> {
+        if let Some(parts) = &self.parts {
+            return Some(Cow::Borrowed(parts.as_slice()));
+        }
+
+        let files = self.files.as_ref()?;
+        let fields = self.form_data.iter().flatten().map(|(key, value)| FormPart::Text {
+            name: key.clone(),
+            value: value.clone(),
+        });
+        let files = files.iter().map(|file| FormPart::File {
+            name: file.field_name.clone(),
+            file_name: file.file_name.clone(),
+            content_type: file.content_type.clone(),
+            source: FileSource::Data(file.data.clone()),
+        });
+        Some(Cow::Owned(fields.chain(files).collect()))
+    }
+
+    /// 复制一份请求并追加请求头（用于缓存重新验证的条件请求）
+    pub(crate) fn with_headers(&self, extra: Vec<(String, String)>) -> Self {
+        let mut request = self.clone();
+        for (name, value) in extra {
+            request.headers.set(name, value);
+        }
+        request
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProxyResponse {
     pub status: u16,
-    pub headers: HashMap<String, String>,
+    /// 最终响应的地址（跟随重定向后可能与请求地址不同）
+    pub url: String,
+    pub redirected: bool,
+    /// 经过的重定向，按顺序
+    pub redirect_chain: Vec<RedirectHop>,
+    pub headers: HeaderList,
     pub body: String, // 文本响应直接存储，二进制响应存储 base64 编码
     pub is_binary: bool, // 标识是否为二进制响应
+    /// 文本响应解码时使用的编码（声明的 charset、BOM 或嗅探结果），二进制响应为 None
+    #[serde(default)]
+    pub encoding: Option<String>,
     // 用于开发调试：记录完整的请求信息
     #[serde(skip_serializing_if = "Option::is_none")]
     pub debug_info: Option<ProxyDebugInfo>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProxyDebugInfo {
-    pub request_method: String,
-    pub request_url: String,
-    pub request_headers: HashMap<String, String>,
-    pub request_body: Option<String>,
-    pub response_status: u16,
-    pub response_headers: HashMap<String, String>,
-}
 
 #[tauri::command]
 pub async fn proxy_request(
-    request: ProxyRequest,
-    state: State<'_, Arc<Mutex<AppState>>>,
-) -> Result<ProxyResponse, String> {
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    mut request: ProxyRequest,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ProxyResponse, AppError> {
+    request.webview = Some(webview.label().to_string());
     // 过滤掉 Tauri 内部请求
-    if request.url.contains("ipc://") || request.url.contains("tauri://") {
-        return Err("Internal IPC request, skipping".to_string());
-    }
-
-    log!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    log!("🔄 [PROXY REQUEST]");
-    log!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-    log!("📍 URL: {} {}", request.method, request.url);
-
-    let app_state = state.lock().await;
-    let client = &app_state.client;
-
-    let method = request
-        .method
-        .parse::<reqwest::Method>()
-        .map_err(|e| e.to_string())?;
-
-    // 1. Build the request
-    let mut req_builder = client.request(method, &request.url);
-
-    // 2. Copy headers (skip restricted ones if necessary, but usually fine)
-    log!("📤 原始请求头:");
-    for (k, v) in &request.headers {
-        // 跳过 Tauri 内部头
-        if k.starts_with("tauri-") {
-            continue;
-        }
-        log!("   {} : {}", k, v);
-        req_builder = req_builder.header(k, v);
+    if is_internal_url(&request.url) {
+        return Err(AppError::InvalidRequest("Internal IPC request, skipping".to_string()));
     }
 
-    // 3. Add CUSTOM VERIFICATION HEADERS here
-    // 生成时间戳
-    let timestamp = chrono::Utc::now().to_rfc3339();
-
-    // 获取设备指纹
-    let device_fingerprint = get_device_fingerprint();
-
-    // 获取设备详细信息（原始 JSON 用于签名计算）
-    let device_info = get_device_info_json();
-    
-    // URL 编码后用于 HTTP 头传输（确保特殊字符不被改变）
-    let device_info_encoded = urlencoding::encode(&device_info).to_string();
-
-    // 生成签名数据：timestamp|fingerprint|device_info_hash|url_hash
-    // 注意：签名使用原始 JSON，HTTP 头使用编码后的 JSON
-    let signature_data = generate_signature_data(&timestamp, &device_fingerprint, &device_info, &request.url);
-
-    log!("\n🔐 安全验证信息:");
-    log!("   ⏰ Timestamp: {}", timestamp);
-    log!("   🖥️  Device Fingerprint: {}", device_fingerprint);
-    log!("   📝 Signature Data: {}", signature_data);
-
-    // 使用 RSA 公钥加密签名（服务端用私钥解密验证）
-    let encrypted_signature = encrypt_signature(&signature_data)
-        .map_err(|e| format!("Failed to encrypt signature: {}", e))?;
-
-    log!("   🔒 Encrypted Signature: {}", encrypted_signature);
-
-    // 添加加密后的验证头
-    req_builder = req_builder.header("X-Client-Signature", &encrypted_signature);
-    req_builder = req_builder.header("X-Timestamp", &timestamp);
-    req_builder = req_builder.header("X-Device-Fingerprint", &device_fingerprint);
-    req_builder = req_builder.header("X-Device-Info", &device_info_encoded);
-
-    log!("\n✅ 已添加验证头:");
-    log!("   X-Client-Signature: {}", encrypted_signature);
-    log!("   X-Timestamp: {}", timestamp);
-    log!("   X-Device-Fingerprint: {}", device_fingerprint);
-    log!("   X-Device-Info (encoded): {}", device_info_encoded);
-
-    // 4. Set body (优先处理 multipart，其次是普通 body)
-    if let Some(files) = &request.files {
-        // 文件上传请求，使用 multipart/form-data
-        log!("\n📦 文件上传请求，构建 multipart/form-data");
-        log!("   文件数量: {}", files.len());
-
-        let mut form = reqwest::multipart::Form::new();
-
-        // 添加普通表单字段
-        if let Some(form_data) = &request.form_data {
-            for (key, value) in form_data {
-                log!("   表单字段: {} = {}", key, value);
-                form = form.text(key.clone(), value.clone());
+    // 带 id 的请求可被 cancel_proxy_request 中断
+    let guard = request.id.as_deref().map(|id| state.inflight.register(id));
+    let token = guard.as_ref().map(|g| g.token());
+    let result = match state.inflight_gets.key_for(&request) {
+        // 相同的 GET 正在进行中时共享它的响应；取消只影响当前等待方
+        Some(key) => {
+            let joined = state.inflight_gets.join(&key, || {
+                let app = app.clone();
+                let state = Arc::clone(state.inner());
+                let request = request.clone();
+                async move { execute_proxy_request(&app, &state, &request).await }
+            });
+            let (shared, coalesced) = match joined {
+                Joined::Leader(shared) => (shared, false),
+                Joined::Follower(shared) => {
+                    log!("🔗 合并到进行中的相同请求: {}", request.url);
+                    (shared, true)
+                }
+            };
+            let mut result = run_cancellable(token, shared).await;
+            if let (true, Ok(response)) = (coalesced, result.as_mut()) {
+                response.headers.set(COALESCED_HEADER, "true");
             }
+            result
         }
+        None => {
+            run_cancellable(token, execute_proxy_request(&app, &state, &request)).await
+        }
+    };
 
-        // 添加文件
-        for file in files {
-            log!("   文件: {} ({})", file.file_name, file.content_type);
+    // 请求已结束（成功、失败或取消），释放分块上传的临时文件
+    if let Some(parts) = &request.parts {
+        state.uploads.release_parts(parts).await;
+    }
 
-            // 解码 base64 文件数据
-            let file_bytes = general_purpose::STANDARD
-                .decode(&file.data)
-                .map_err(|e| format!("Failed to decode file: {}", e))?;
+    if let Err(e) = &result {
+        if e.is_cancelled() {
+            log!("🛑 请求已取消: {} {}", request.method, request.url);
+        }
+    }
 
-            log!("   文件大小: {} bytes", file_bytes.len());
+    result
+}
 
-            // 创建文件部分
-            let part = reqwest::multipart::Part::bytes(file_bytes)
-                .file_name(file.file_name.clone())
-                .mime_str(&file.content_type)
-                .map_err(|e| format!("Invalid content type: {}", e))?;
+async fn execute_proxy_request(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    request: &ProxyRequest,
+) -> Result<ProxyResponse, AppError> {
+    log!("\n━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    log!("🔄 [PROXY REQUEST]");
+    log!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
+    log!("📍 URL: {} {}", request.method, request.url);
 
-            form = form.part(file.field_name.clone(), part);
+    // 可缓存的 GET 先查本地缓存：新鲜副本直接返回，过期副本带条件头重新验证
+    let cache_key = state.cache.key_for(request, &state.cookies);
+    let cached = match &cache_key {
+        Some(key) => state.cache.lookup(app, key, request).await,
+        None => None,
+    };
+    if let Some(entry) = &cached {
+        if entry.is_fresh() && !forces_revalidation(request) {
+            log!("💾 缓存命中");
+            return Ok(cached_proxy_response(request, entry, "HIT"));
         }
-
-        req_builder = req_builder.multipart(form);
-    } else if let Some(body) = &request.body {
-        // 普通请求体（JSON、文本等）
-        log!("\n📦 请求体: {} bytes", body.len());
-        req_builder = req_builder.body(body.clone());
     }
+    let conditional = cached
+        .as_ref()
+        .map(|entry| request.with_headers(entry.conditional_headers()));
+    let outgoing = conditional.as_ref().unwrap_or(request);
+
+    let timeouts = state.policy.effective(request.timeouts.as_ref());
+    let deadline = Instant::now() + timeouts.total;
 
-    // 5. Send request
+    // 5. Send request（按主机限流，许可持有到响应体读取完毕）
     log!("\n🚀 发送请求到后端...");
-    let resp = req_builder.send().await.map_err(|e| {
-        log!("❌ 请求失败: {}", e);
-        e.to_string()
-    })?;
+    let Followed {
+        sent:
+            SentRequest {
+                response: resp,
+                permit: _permit,
+                signed_headers,
+            },
+        request: final_request,
+        chain,
+    } = send_following_redirects(app, state, outgoing, timeouts, deadline).await?;
+    let redirected = !chain.is_empty();
 
     // 6. Process response
     let status = resp.status().as_u16();
     log!("📥 响应状态: {}", status);
 
-    let headers: HashMap<String, String> = resp
-        .headers()
-        .iter()
-        .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
-        .collect();
+    let mut headers = collect_response_headers(&resp);
 
-    // 检测 Content-Type 来判断是文本还是二进制
-    let content_type = headers
-        .get("content-type")
-        .or_else(|| headers.get("Content-Type"))
-        .map(|s| s.to_lowercase())
-        .unwrap_or_default();
+    if status == 304 && !redirected {
+        if let Some(entry) = cached {
+            log!("💾 缓存重新验证通过 (304)");
+            let entry = state.cache.revalidated(app, entry, &headers).await;
+            return Ok(cached_proxy_response(request, &entry, "REVALIDATED"));
+        }
+    }
 
-    // 判断是否为二进制内容
-    let is_binary = is_binary_content_type(&content_type);
+    let content_type = content_type_of(&headers);
+    let bytes = read_body(resp, timeouts.read, deadline).await?;
+    // 重定向后的响应不缓存：缓存命中时无法还原重定向链
+    if let Some(key) = cache_key.as_ref().filter(|_| !redirected) {
+        state
+            .cache
+            .store(app, key, request, status, &headers, &bytes)
+            .await;
+        headers.set(CACHE_STATUS_HEADER, "MISS");
+    }
 
-    let body = if is_binary {
-        // 二进制响应：获取字节并转换为 base64
-        log!("📦 检测到二进制响应 ({})", content_type);
-        let bytes = resp.bytes().await.map_err(|e| e.to_string())?;
-        log!("📦 二进制大小: {} bytes", bytes.len());
-        general_purpose::STANDARD.encode(&bytes)
+    // 结合 Content-Type 与响应体内容判断文本还是二进制，并按检测到的编码解码
+    let DecodedBody {
+        body,
+        is_binary,
+        encoding,
+    } = decode_body(&bytes, &content_type);
+    if is_binary {
+        log!("📦 检测到二进制响应 ({}), {} bytes", content_type, bytes.len());
     } else {
-        // 文本响应：直接获取文本
-        log!("📄 检测到文本响应 ({})", content_type);
-        resp.text().await.map_err(|e| e.to_string())?
-    };
+        log!("📄 检测到文本响应 ({}, {})", content_type, encoding.unwrap_or_default());
+    }
 
     if status == 403 {
         log!("⚠️  收到 403 Forbidden 响应！");
         if !is_binary {
             log!(
                 "📄 响应内容: {}",
-                if body.len() > 200 {
-                    &body[..200]
-                } else {
-                    &body
-                }
+                body.chars().take(200).collect::<String>()
             );
         }
     } else {
@@ -288,30 +327,57 @@ pub async fn proxy_request(
     log!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
 
     // 在开发模式下，返回调试信息
-    let debug_info = if ENABLE_LOGS {
-        // 收集所有请求头（包括安全头）
-        let mut all_request_headers = request.headers.clone();
-        all_request_headers.insert("X-Client-Signature".to_string(), encrypted_signature.clone());
-        all_request_headers.insert("X-Timestamp".to_string(), timestamp.clone());
-        all_request_headers.insert("X-Device-Fingerprint".to_string(), device_fingerprint.clone());
-        
-        Some(ProxyDebugInfo {
-            request_method: request.method.clone(),
-            request_url: request.url.clone(),
-            request_headers: all_request_headers,
-            request_body: request.body.clone(),
-            response_status: status,
-            response_headers: headers.clone(),
-        })
-    } else {
-        None
-    };
+    let debug_info = build_debug_info(&final_request, &signed_headers, status, &headers);
 
     Ok(ProxyResponse {
         status,
+        url: final_request.url,
+        redirected,
+        redirect_chain: chain,
         headers,
         body,
         is_binary,
+        encoding: encoding.map(str::to_string),
         debug_info,
     })
 }
+
+/// 由缓存条目构造响应（未发出请求，不带调试信息）
+fn cached_proxy_response(
+    request: &ProxyRequest,
+    entry: &CachedResponse,
+    cache_status: &str,
+) -> ProxyResponse {
+    let headers = entry.headers(cache_status);
+    let decoded = decode_body(&entry.body, &content_type_of(&headers));
+
+    ProxyResponse {
+        status: entry.status(),
+        url: request.url.clone(),
+        redirected: false,
+        redirect_chain: Vec::new(),
+        body: decoded.body,
+        headers,
+        is_binary: decoded.is_binary,
+        encoding: decoded.encoding.map(str::to_string),
+        debug_info: None,
+    }
+}
+
+pub(crate) fn content_type_of(headers: &HeaderList) -> String {
+    headers
+        .get("content-type")
+        .map(|s| s.to_lowercase())
+        .unwrap_or_default()
+}
+
+
+/// Tauri 内部请求不走代理
+pub(crate) fn is_internal_url(url: &str) -> bool {
+    url.contains("ipc://") || url.contains("tauri://")
+}
+
+/// 收集响应头：保留重复项与原始字节，去掉逐跳头
+pub(crate) fn collect_response_headers(resp: &reqwest::Response) -> HeaderList {
+    HeaderList::from_header_map(resp.headers()).forwardable_response()
+}
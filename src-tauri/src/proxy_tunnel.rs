@@ -0,0 +1,111 @@
+/// 经上游代理建立 TCP 隧道
+/// WebSocket 连接不经过 reqwest，需要自行穿过上游代理（reqwest 自带代理支持）：
+/// - http://：发送 CONNECT，收到 200 后即为透明隧道
+/// - socks5://：本机解析目标域名，只把 IP 交给代理（与 curl、reqwest 的语义一致）
+/// - socks5h://：把域名原样交给代理，由代理解析
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_socks::IntoTargetAddr;
+
+use crate::error::AppError;
+
+/// 建立到 host:port 的隧道
+pub async fn tunnel(proxy: &reqwest::Url, host: &str, port: u16) -> Result<TcpStream, AppError> {
+    let proxy_host = proxy
+        .host_str()
+        .ok_or_else(|| AppError::InvalidUrl(format!("代理地址缺少主机: {}", proxy)))?;
+    let proxy_port = proxy
+        .port_or_known_default()
+        .unwrap_or(match proxy.scheme() {
+            "socks5" | "socks5h" => 1080,
+            _ => 8080,
+        });
+    let username = urlencoding::decode(proxy.username())
+        .map(|u| u.into_owned())
+        .unwrap_or_default();
+    let password = proxy
+        .password()
+        .and_then(|p| urlencoding::decode(p).ok())
+        .map(|p| p.into_owned())
+        .unwrap_or_default();
+    let credentials = (!username.is_empty()).then_some((username.as_str(), password.as_str()));
+
+    match proxy.scheme() {
+        "socks5" => {
+            let target = tokio::net::lookup_host((host, port))
+                .await
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .ok_or_else(|| AppError::Dns(format!("无法解析 {}", host)))?;
+            socks5_connect((proxy_host, proxy_port), target, credentials).await
+        }
+        "socks5h" => socks5_connect((proxy_host, proxy_port), (host, port), credentials).await,
+        "http" => http_connect((proxy_host, proxy_port), host, port, credentials).await,
+        other => Err(AppError::InvalidRequest(format!(
+            "WebSocket 不支持 {} 代理",
+            other
+        ))),
+    }
+}
+
+async fn socks5_connect<'t>(
+    (proxy_host, proxy_port): (&str, u16),
+    target: impl IntoTargetAddr<'t>,
+    credentials: Option<(&str, &str)>,
+) -> Result<TcpStream, AppError> {
+    let stream = match credentials {
+        None => tokio_socks::tcp::Socks5Stream::connect((proxy_host, proxy_port), target).await,
+        Some((username, password)) => {
+            tokio_socks::tcp::Socks5Stream::connect_with_password(
+                (proxy_host, proxy_port),
+                target,
+                username,
+                password,
+            )
+            .await
+        }
+    }
+    .map_err(|e| AppError::Network(format!("SOCKS5 代理 {}: {}", proxy_host, e)))?;
+    Ok(stream.into_inner())
+}
+
+async fn http_connect(
+    (proxy_host, proxy_port): (&str, u16),
+    host: &str,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> Result<TcpStream, AppError> {
+    let mut stream = TcpStream::connect((proxy_host, proxy_port)).await?;
+    let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+    if let Some((username, password)) = credentials {
+        use base64::{engine::general_purpose, Engine as _};
+        let token = general_purpose::STANDARD.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // 读取 CONNECT 响应头；隧道建立前代理不会发送其他数据
+    let mut reader = BufReader::new(&mut stream);
+    let mut status_line = String::new();
+    reader.read_line(&mut status_line).await?;
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .unwrap_or(0);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line == "\r\n" || line == "\n" {
+            break;
+        }
+    }
+    if status != 200 {
+        return Err(AppError::Network(format!(
+            "HTTP 代理 {} 拒绝建立隧道: {}",
+            proxy_host,
+            status_line.trim()
+        )));
+    }
+    Ok(stream)
+}
@@ -0,0 +1,176 @@
+/// 重定向处理
+/// Client 关闭了自动跟随，由这里逐跳发送：签名包含 URL 哈希，每一跳都要重新签名；
+/// 同时记录经过的地址，供前端设置 Response.url / redirected。
+/// 模式与 fetch 的 redirect 选项一致：follow（默认）/ error / manual
+use crate::dispatch::{send_with_policy, SentRequest};
+use crate::error::AppError;
+use crate::proxy::{AppState, ProxyRequest};
+use crate::resilience::EffectiveTimeouts;
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 方法改为 GET 时一并去掉的请求体相关头
+const REQUEST_BODY_HEADERS: [&str; 4] = [
+    "content-type",
+    "content-encoding",
+    "content-language",
+    "content-location",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedirectMode {
+    /// 自动跟随，最多 max_redirects 跳
+    #[default]
+    Follow,
+    /// 遇到重定向直接报错
+    Error,
+    /// 不跟随，把 3xx 响应原样交给页面
+    Manual,
+}
+
+/// 重定向链中的一跳：返回重定向的地址及其状态码
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedirectHop {
+    pub url: String,
+    pub status: u16,
+}
+
+/// 跟随重定向后的发送结果
+pub(crate) struct Followed {
+    pub sent: SentRequest,
+    /// 最终发出的请求（URL、方法与请求体可能已随重定向改变）
+    pub request: ProxyRequest,
+    pub chain: Vec<RedirectHop>,
+}
+
+/// 按请求的重定向模式发送；每一跳都经过 send_with_policy（限流、熔断、重试、重新签名）
+pub(crate) async fn send_following_redirects(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    request: &ProxyRequest,
+    timeouts: EffectiveTimeouts,
+    deadline: Instant,
+) -> Result<Followed, AppError> {
+    let mode = request.redirect;
+    let mut current = request.clone();
+    let mut chain = Vec::new();
+
+    loop {
+        let sent = send_with_policy(app, state, &current, timeouts, deadline).await?;
+        let status = sent.response.status().as_u16();
+        let location = sent
+            .response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+
+        let Some(location) = location.filter(|_| is_redirect_status(status)) else {
+            return Ok(Followed {
+                sent,
+                request: current,
+                chain,
+            });
+        };
+
+        match mode {
+            RedirectMode::Manual => {
+                log!("↪️  重定向 ({}) 不跟随: {}", status, location);
+                return Ok(Followed {
+                    sent,
+                    request: current,
+                    chain,
+                });
+            }
+            RedirectMode::Error => {
+                return Err(AppError::Redirect(format!(
+                    "{} 返回 {}，重定向到 {}",
+                    current.url, status, location
+                )));
+            }
+            RedirectMode::Follow => {}
+        }
+
+        if chain.len() as u32 >= state.policy.max_redirects {
+            return Err(AppError::Redirect(format!(
+                "重定向超过 {} 次: {}",
+                state.policy.max_redirects, current.url
+            )));
+        }
+
+        let next = redirect_target(&current, status, &location)?;
+        log!("↪️  重定向 ({}): {} -> {} {}", status, current.url, next.method, next.url);
+
+        // 释放本跳的连接与并发许可后再发下一跳
+        drop(sent);
+        chain.push(RedirectHop {
+            url: std::mem::replace(&mut current, next).url,
+            status,
+        });
+    }
+}
+
+fn is_redirect_status(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// 按 fetch 规范生成下一跳请求：
+/// 301/302 的 POST 与 303 的非 GET/HEAD 改为不带请求体的 GET；跨源时去掉 Authorization
+fn redirect_target(
+    request: &ProxyRequest,
+    status: u16,
+    location: &str,
+) -> Result<ProxyRequest, AppError> {
+    let base = reqwest::Url::parse(&request.url)
+        .map_err(|e| AppError::InvalidUrl(format!("{}: {}", request.url, e)))?;
+    let target = base
+        .join(location)
+        .map_err(|e| AppError::Redirect(format!("无效的 Location {}: {}", location, e)))?;
+    if !matches!(target.scheme(), "http" | "https") {
+        return Err(AppError::Redirect(format!("不支持重定向到 {}", target)));
+    }
+
+    let mut next = request.clone();
+    let method = request.method.to_ascii_uppercase();
+    let becomes_get = (matches!(status, 301 | 302) && method == "POST")
+        || (status == 303 && method != "GET" && method != "HEAD");
+    if becomes_get {
+        next.method = "GET".to_string();
+        next.body = None;
+        next.parts = None;
+        next.form_data = None;
+        next.files = None;
+        for name in REQUEST_BODY_HEADERS {
+            next.headers.remove(name);
+        }
+    }
+
+    if base.origin() != target.origin() {
+        next.headers.remove("authorization");
+    }
+
+    next.url = target.to_string();
+    Ok(next)
+}
@@ -0,0 +1,397 @@
+/// 代理容错模块：超时、重试退避、Retry-After 解析与按主机熔断
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::tls::find_pin_mismatch;
+
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_READ_TIMEOUT_MS: u64 = 30_000;
+const DEFAULT_TOTAL_TIMEOUT_MS: u64 = 120_000;
+const DEFAULT_MAX_RETRIES: u32 = 2;
+/// 与浏览器 fetch 的上限一致
+const DEFAULT_MAX_REDIRECTS: u32 = 20;
+
+/// 退避基数与上限
+const BACKOFF_BASE_MS: u64 = 200;
+const BACKOFF_MAX_MS: u64 = 5_000;
+
+/// Retry-After 超过该值时不再自动重试，直接把响应交给页面
+const MAX_RETRY_AFTER_SECS: u64 = 30;
+
+/// 连续失败多少次后熔断，以及熔断持续时间
+const BREAKER_FAILURE_THRESHOLD: u32 = 5;
+const BREAKER_OPEN_SECS: u64 = 30;
+
+fn env_u64(value: Option<&'static str>, default: u64) -> u64 {
+    value
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .unwrap_or(default)
+}
+
+/// 单个请求可覆盖的超时设置（毫秒）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProxyTimeouts {
+    /// 建立连接并收到响应头的超时（全局 connect 超时另在 Client 上设置）
+    pub connect_ms: Option<u64>,
+    /// 读取响应体时两个数据块之间的最大间隔
+    pub read_ms: Option<u64>,
+    /// 整个请求（含重试与读取响应体）的总超时；流式请求只约束到收到响应头
+    pub total_ms: Option<u64>,
+}
+
+/// 全局代理策略，默认值可通过编译期环境变量覆盖
+#[derive(Debug, Clone)]
+pub struct ProxyPolicy {
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub total_timeout: Duration,
+    pub max_retries: u32,
+    pub max_redirects: u32,
+}
+
+impl ProxyPolicy {
+    pub fn from_env() -> Self {
+        Self {
+            connect_timeout: Duration::from_millis(env_u64(
+                option_env!("TAURI_PROXY_CONNECT_TIMEOUT_MS"),
+                DEFAULT_CONNECT_TIMEOUT_MS,
+            )),
+            read_timeout: Duration::from_millis(env_u64(
+                option_env!("TAURI_PROXY_READ_TIMEOUT_MS"),
+                DEFAULT_READ_TIMEOUT_MS,
+            )),
+            total_timeout: Duration::from_millis(env_u64(
+                option_env!("TAURI_PROXY_TOTAL_TIMEOUT_MS"),
+                DEFAULT_TOTAL_TIMEOUT_MS,
+            )),
+            max_retries: env_u64(
+                option_env!("TAURI_PROXY_MAX_RETRIES"),
+                DEFAULT_MAX_RETRIES as u64,
+            ) as u32,
+            max_redirects: env_u64(
+                option_env!("TAURI_PROXY_MAX_REDIRECTS"),
+                DEFAULT_MAX_REDIRECTS as u64,
+            ) as u32,
+        }
+    }
+
+    /// 合并单个请求的覆盖值
+    pub fn effective(&self, overrides: Option<&ProxyTimeouts>) -> EffectiveTimeouts {
+        let overrides = overrides.cloned().unwrap_or_default();
+        EffectiveTimeouts {
+            connect: overrides
+                .connect_ms
+                .map(Duration::from_millis)
+                .unwrap_or(self.connect_timeout + self.read_timeout),
+            read: overrides
+                .read_ms
+                .map(Duration::from_millis)
+                .unwrap_or(self.read_timeout),
+            total: overrides
+                .total_ms
+                .map(Duration::from_millis)
+                .unwrap_or(self.total_timeout),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EffectiveTimeouts {
+    /// 等待响应头（含建立连接）
+    pub connect: Duration,
+    /// 响应体数据块间隔
+    pub read: Duration,
+    /// 整体截止时间
+    pub total: Duration,
+}
+
+/// 幂等方法才允许自动重试
+pub fn is_idempotent(method: &reqwest::Method) -> bool {
+    matches!(
+        *method,
+        reqwest::Method::GET
+            | reqwest::Method::HEAD
+            | reqwest::Method::OPTIONS
+            | reqwest::Method::PUT
+            | reqwest::Method::DELETE
+            | reqwest::Method::TRACE
+    )
+}
+
+/// 可重试的响应状态：限流与网关错误
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 429 | 502 | 503 | 504)
+}
+
+/// 计入熔断统计的状态（429 说明后端存活，不计入）
+pub fn is_backend_failure_status(status: u16) -> bool {
+    matches!(status, 502..=504)
+}
+
+/// 连接失败/超时类错误可重试
+pub fn is_retryable_error(error: &reqwest::Error) -> bool {
+    // 证书固定不匹配发生在建立连接阶段，重试也不会改变结果
+    (error.is_connect() || error.is_timeout()) && find_pin_mismatch(error).is_none()
+}
+
+/// 全抖动指数退避：random(0, min(max, base * 2^attempt))
+pub fn backoff_delay(attempt: u32) -> Duration {
+    let ceiling = BACKOFF_BASE_MS
+        .saturating_mul(1u64 << attempt.min(16))
+        .min(BACKOFF_MAX_MS);
+    let jittered = rand::thread_rng().gen_range(0..=ceiling);
+    Duration::from_millis(jittered.max(BACKOFF_BASE_MS / 2))
+}
+
+/// 解析 Retry-After（秒数或 HTTP-date），超出上限返回 None
+pub fn parse_retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    let value = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim();
+
+    let delay = if let Ok(secs) = value.parse::<u64>() {
+        Duration::from_secs(secs)
+    } else {
+        let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+        let millis = (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+            .num_milliseconds()
+            .max(0) as u64;
+        Duration::from_millis(millis)
+    };
+
+    (delay <= Duration::from_secs(MAX_RETRY_AFTER_SECS)).then_some(delay)
+}
+
+/// Retry-After 是否存在但超出自动重试上限
+pub fn retry_after_too_long(headers: &reqwest::header::HeaderMap) -> bool {
+    headers.contains_key(reqwest::header::RETRY_AFTER) && parse_retry_after(headers).is_none()
+}
+
+enum BreakerState {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    /// 探测请求发出的时间；探测请求被取消而未回报结果时，超时后允许再次探测
+    HalfOpen { probe_started: Instant },
+}
+
+/// 按主机熔断：连续失败达到阈值后在一段时间内直接失败，
+/// 到期后放行一个探测请求，成功则恢复，失败则重新熔断
+#[derive(Default)]
+pub struct CircuitBreakers {
+    hosts: Mutex<HashMap<String, BreakerState>>,
+}
+
+impl CircuitBreakers {
+    /// 请求前检查；熔断中返回剩余秒数
+    pub fn check(&self, host: &str) -> Result<(), u64> {
+        let Ok(mut hosts) = self.hosts.lock() else {
+            return Ok(());
+        };
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert(BreakerState::Closed { failures: 0 });
+
+        match state {
+            BreakerState::Closed { .. } => Ok(()),
+            BreakerState::Open { until } => {
+                let now = Instant::now();
+                if now >= *until {
+                    *state = BreakerState::HalfOpen { probe_started: now };
+                    Ok(())
+                } else {
+                    Err((*until - now).as_secs().max(1))
+                }
+            }
+            BreakerState::HalfOpen { probe_started } => {
+                if probe_started.elapsed() < Duration::from_secs(BREAKER_OPEN_SECS) {
+                    Err(1)
+                } else {
+                    *probe_started = Instant::now();
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    pub fn record_success(&self, host: &str) {
+        if let Ok(mut hosts) = self.hosts.lock() {
+            hosts.insert(host.to_string(), BreakerState::Closed { failures: 0 });
+        }
+    }
+
+    pub fn record_failure(&self, host: &str) {
+        let Ok(mut hosts) = self.hosts.lock() else {
+            return;
+        };
+        let open = BreakerState::Open {
+            until: Instant::now() + Duration::from_secs(BREAKER_OPEN_SECS),
+        };
+        let state = hosts
+            .entry(host.to_string())
+            .or_insert(BreakerState::Closed { failures: 0 });
+
+        match state {
+            BreakerState::Closed { failures } => {
+                *failures += 1;
+                if *failures >= BREAKER_FAILURE_THRESHOLD {
+                    *state = open;
+                }
+            }
+            BreakerState::HalfOpen { .. } => *state = open,
+            BreakerState::Open { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+
+    fn retry_after(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    fn http_date(offset_secs: i64) -> String {
+        (chrono::Utc::now() + chrono::Duration::seconds(offset_secs))
+            .format("%a, %d %b %Y %H:%M:%S GMT")
+            .to_string()
+    }
+
+    /// 直接改写熔断状态，模拟时间流逝
+    fn set_state(breakers: &CircuitBreakers, host: &str, state: BreakerState) {
+        breakers
+            .hosts
+            .lock()
+            .unwrap()
+            .insert(host.to_string(), state);
+    }
+
+    #[test]
+    fn test_backoff_bounds() {
+        for attempt in 0..8 {
+            let ceiling = (BACKOFF_BASE_MS << attempt).min(BACKOFF_MAX_MS);
+            for _ in 0..200 {
+                let delay = backoff_delay(attempt).as_millis() as u64;
+                assert!(
+                    delay >= BACKOFF_BASE_MS / 2,
+                    "attempt {}: {}",
+                    attempt,
+                    delay
+                );
+                assert!(delay <= ceiling, "attempt {}: {}", attempt, delay);
+            }
+        }
+        // 次数很大时不溢出，仍受上限约束
+        assert!(backoff_delay(u32::MAX) <= Duration::from_millis(BACKOFF_MAX_MS));
+    }
+
+    #[test]
+    fn test_backoff_is_jittered() {
+        let delays: std::collections::HashSet<_> = (0..50).map(|_| backoff_delay(5)).collect();
+        assert!(delays.len() > 1);
+    }
+
+    #[test]
+    fn test_retry_after_seconds() {
+        assert_eq!(parse_retry_after(&retry_after("0")), Some(Duration::ZERO));
+        assert_eq!(
+            parse_retry_after(&retry_after(" 5 ")),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            parse_retry_after(&retry_after("30")),
+            Some(Duration::from_secs(MAX_RETRY_AFTER_SECS))
+        );
+        assert_eq!(parse_retry_after(&retry_after("31")), None);
+        assert!(retry_after_too_long(&retry_after("31")));
+        assert!(!retry_after_too_long(&retry_after("5")));
+        assert!(!retry_after_too_long(&HeaderMap::new()));
+        assert_eq!(parse_retry_after(&retry_after("soon")), None);
+    }
+
+    #[test]
+    fn test_retry_after_http_date() {
+        let delay = parse_retry_after(&retry_after(&http_date(10))).unwrap();
+        assert!(delay <= Duration::from_secs(10), "{:?}", delay);
+        assert!(delay >= Duration::from_secs(8), "{:?}", delay);
+
+        // 已经过去的时间立即重试
+        assert_eq!(
+            parse_retry_after(&retry_after(&http_date(-60))),
+            Some(Duration::ZERO)
+        );
+
+        // 超过上限
+        assert_eq!(parse_retry_after(&retry_after(&http_date(3600))), None);
+        assert!(retry_after_too_long(&retry_after(&http_date(3600))));
+    }
+
+    #[test]
+    fn test_breaker_opens_after_threshold() {
+        let breakers = CircuitBreakers::default();
+        for _ in 0..BREAKER_FAILURE_THRESHOLD - 1 {
+            breakers.record_failure("h");
+            assert!(breakers.check("h").is_ok());
+        }
+        breakers.record_failure("h");
+        let remaining = breakers.check("h").unwrap_err();
+        assert!((1..=BREAKER_OPEN_SECS).contains(&remaining));
+
+        // 成功会清零失败计数
+        breakers.record_success("h");
+        breakers.record_failure("h");
+        assert!(breakers.check("h").is_ok());
+        assert!(breakers.check("other").is_ok());
+    }
+
+    #[test]
+    fn test_breaker_half_open_probe() {
+        let breakers = CircuitBreakers::default();
+        set_state(
+            &breakers,
+            "h",
+            BreakerState::Open {
+                until: Instant::now(),
+            },
+        );
+
+        // 到期后只放行一个探测请求
+        assert!(breakers.check("h").is_ok());
+        assert_eq!(breakers.check("h"), Err(1));
+
+        // 探测失败重新熔断
+        breakers.record_failure("h");
+        assert!(breakers.check("h").unwrap_err() > 1);
+
+        // 探测成功恢复
+        set_state(
+            &breakers,
+            "h",
+            BreakerState::Open {
+                until: Instant::now(),
+            },
+        );
+        assert!(breakers.check("h").is_ok());
+        breakers.record_success("h");
+        assert!(breakers.check("h").is_ok());
+        assert!(breakers.check("h").is_ok());
+    }
+
+    #[test]
+    fn test_breaker_stale_probe_is_replaced() {
+        let breakers = CircuitBreakers::default();
+        // 探测请求被取消、一直没有回报结果
+        let probe_started = Instant::now() - Duration::from_secs(BREAKER_OPEN_SECS + 1);
+        set_state(&breakers, "h", BreakerState::HalfOpen { probe_started });
+        assert!(breakers.check("h").is_ok());
+        assert_eq!(breakers.check("h"), Err(1));
+    }
+}
@@ -1,5 +1,6 @@
 /// 安全检测模块
 /// 包含反调试、虚拟机检测、环境安全评分等功能
+#[cfg(target_os = "macos")]
 use std::process::Command;
 
 /// 安全评分（0-100）
@@ -7,6 +8,8 @@ pub struct SecurityScore {
     pub score: u8,
     pub is_debugger: bool,
     pub is_vm: bool,
+    /// 预留：完整性检测尚未实现，目前恒为 false
+    #[allow(dead_code)]
     pub is_modified: bool,
 }
 
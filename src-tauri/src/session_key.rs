@@ -0,0 +1,341 @@
+/// 会话密钥签名
+/// 逐请求签名每次都要做一次 RSA-2048 加密（并重新解析公钥、重新计算环境安全评分），
+/// 请求密集时开销明显。配置 TAURI_SIGN_SESSION_PATH 后改为会话模式：
+/// - 密钥交换：客户端生成 256 位随机密钥，按当前签名方案用公钥加密后放在 X-Session-Key 头中 POST 到交换接口；
+///   交换请求本身按逐请求 RSA 方式签名，x-session-key 参与规范请求签名。
+///   后端返回 {"session_id": "...", "expires_in": 秒}
+/// - 之后签名来源的请求带 X-Session-Id（参与签名），X-Client-Signature 改为对同一份签名数据
+///   计算的 HMAC-SHA256（Base64），不再做 RSA 加密
+/// - 会话过期前自动重新交换；后端返回 X-Session-Expired 头时丢弃会话，重新交换后自动重发一次
+/// - 交换接口返回 404 / 405 / 501 时视为后端不支持，本次运行一直使用逐请求 RSA 签名；
+///   其他失败时暂时回退，一段时间后再尝试
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::canonical::EMPTY_BODY_HASH;
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::proxy::AppState;
+use crate::signing::SigningRequest;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 密钥交换请求中携带 RSA 加密会话密钥的请求头
+pub const SESSION_KEY_HEADER: &str = "X-Session-Key";
+
+/// 会话模式下标识会话的请求头
+pub const SESSION_ID_HEADER: &str = "X-Session-Id";
+
+/// 后端拒绝会话（过期或未知）时返回的响应头
+const SESSION_EXPIRED_HEADER: &str = "x-session-expired";
+
+/// 后端未给出有效期时的默认值
+const DEFAULT_TTL_SECS: u64 = 1800;
+
+/// 提前重新交换的余量，避免请求在途中会话过期
+const EXPIRY_MARGIN: Duration = Duration::from_secs(30);
+
+/// 交换失败（非不支持）后暂停尝试的时间
+const RETRY_AFTER_FAILURE: Duration = Duration::from_secs(60);
+
+type HmacSha256 = Hmac<Sha256>;
+
+#[derive(Deserialize)]
+struct ExchangeResponse {
+    session_id: String,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+/// 当前会话（签名链使用）
+#[derive(Clone)]
+pub struct Session {
+    pub id: String,
+    key: [u8; 32],
+    expires_at: Instant,
+}
+
+impl Session {
+    /// 签名数据的 HMAC-SHA256（Base64）
+    pub fn sign(&self, data: &str) -> String {
+        session_signature(&self.key, data)
+    }
+}
+
+struct Inner {
+    /// 交换接口地址；未配置时整个机制关闭
+    endpoint: Option<String>,
+    current: RwLock<Option<Session>>,
+    /// 后端不支持会话模式（本次运行不再尝试）
+    unsupported: AtomicBool,
+    /// 交换失败后，在此之前不再尝试
+    retry_at: RwLock<Option<Instant>>,
+    /// 同一时刻只交换一次，其余请求等待结果
+    refresh: tokio::sync::Mutex<()>,
+}
+
+/// 会话密钥；签名链与发送逻辑共用
+#[derive(Clone)]
+pub struct SessionKeys(Arc<Inner>);
+
+impl SessionKeys {
+    pub fn from_env(env_url: &str) -> Self {
+        let endpoint = option_env!("TAURI_SIGN_SESSION_PATH")
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .and_then(|path| reqwest::Url::parse(env_url).ok()?.join(path).ok())
+            .map(|url| url.to_string());
+        if let Some(endpoint) = &endpoint {
+            log!("🔑 [SESSION] 密钥交换接口: {}", endpoint);
+        }
+        Self::new(endpoint)
+    }
+
+    fn new(endpoint: Option<String>) -> Self {
+        Self(Arc::new(Inner {
+            endpoint,
+            current: RwLock::new(None),
+            unsupported: AtomicBool::new(false),
+            retry_at: RwLock::new(None),
+            refresh: tokio::sync::Mutex::new(()),
+        }))
+    }
+
+    fn enabled(&self) -> bool {
+        self.0.endpoint.is_some() && !self.0.unsupported.load(Ordering::Relaxed)
+    }
+
+    /// 未过期的会话；没有时签名链回退到逐请求 RSA 签名
+    pub fn current(&self) -> Option<Session> {
+        let current = self.0.current.read().ok()?;
+        current
+            .as_ref()
+            .filter(|session| Instant::now() + EXPIRY_MARGIN < session.expires_at)
+            .cloned()
+    }
+
+    fn accept(&self, id: String, key: [u8; 32], ttl_secs: Option<u64>) {
+        let ttl = Duration::from_secs(ttl_secs.unwrap_or(DEFAULT_TTL_SECS));
+        log!("🔑 [SESSION] 会话已建立，有效期 {} 秒", ttl.as_secs());
+        if let Ok(mut current) = self.0.current.write() {
+            *current = Some(Session {
+                id,
+                key,
+                expires_at: Instant::now() + ttl,
+            });
+        }
+    }
+
+    fn invalidate(&self) {
+        if let Ok(mut current) = self.0.current.write() {
+            *current = None;
+        }
+    }
+
+    /// 是否是后端拒绝会话的响应
+    pub fn requires_rekey(&self, headers: &reqwest::header::HeaderMap) -> bool {
+        self.enabled() && headers.contains_key(SESSION_EXPIRED_HEADER)
+    }
+
+    /// 丢弃当前会话，下次签名前重新交换
+    pub fn on_rekey(&self) {
+        log!("🔑 [SESSION] 后端拒绝会话，重新交换密钥");
+        self.invalidate();
+    }
+
+    /// 确保有可用的会话；未启用、后端不支持或交换失败时不报错，签名链回退到逐请求 RSA 签名
+    pub async fn ensure(&self, state: &AppState) {
+        let Some(endpoint) = self.0.endpoint.as_deref() else {
+            return;
+        };
+        // 签名数据不覆盖会话 ID 时不使用会话（见 device_signature），也就不必交换
+        if !self.enabled()
+            || self.current().is_some()
+            || self.backing_off()
+            || !state.schemes.active().covers_request()
+        {
+            return;
+        }
+        let _refreshing = self.0.refresh.lock().await;
+        // 等锁期间其他请求可能已经交换完成或失败
+        if !self.enabled() || self.current().is_some() || self.backing_off() {
+            return;
+        }
+
+        log!("🔑 [SESSION] 交换会话密钥: {}", endpoint);
+        match self.exchange(state, endpoint).await {
+            Ok(()) => {}
+            Err(Exchange::Unsupported(status)) => {
+                log!(
+                    "🔑 [SESSION] 后端不支持会话模式（{}），使用逐请求签名",
+                    status
+                );
+                self.0.unsupported.store(true, Ordering::Relaxed);
+            }
+            Err(Exchange::Failed(e)) => {
+                log!("⚠️  [SESSION] 密钥交换失败，暂时使用逐请求签名: {}", e);
+                if let Ok(mut retry_at) = self.0.retry_at.write() {
+                    *retry_at = Some(Instant::now() + RETRY_AFTER_FAILURE);
+                }
+            }
+        }
+    }
+
+    fn backing_off(&self) -> bool {
+        self.0
+            .retry_at
+            .read()
+            .ok()
+            .and_then(|retry_at| *retry_at)
+            .is_some_and(|at| Instant::now() < at)
+    }
+
+    async fn exchange(&self, state: &AppState, endpoint: &str) -> Result<(), Exchange> {
+        let key: [u8; 32] = rand::random();
+        // 按当前签名方案加密（与交换请求的 X-Signature-Version 一致）
+        let encrypted_key = state.schemes.active().seal(&hex::encode(key))?;
+
+        // 交换请求本身按逐请求 RSA 方式签名（此时没有可用会话），加密后的密钥参与签名
+        let headers: HeaderList = [(SESSION_KEY_HEADER, encrypted_key.as_str())]
+            .into_iter()
+            .collect();
+        let signed_headers = state.signers.sign(&SigningRequest {
+            id: None,
+            method: "POST",
+            url: endpoint,
+            headers: &headers,
+            body_hash: EMPTY_BODY_HASH,
+            signed: true,
+        })?;
+        state.http.prepare_proxy(endpoint).await;
+        let builder = headers.apply_to(state.http.client().post(endpoint));
+        // 整个请求（含读取响应体）共用总超时
+        let deadline = tokio::time::Instant::now() + state.policy.effective(None).total;
+        let sent_at = chrono::Utc::now();
+        let response = tokio::time::timeout_at(deadline, signed_headers.apply_to(builder).send())
+            .await
+            .map_err(|_| AppError::Timeout(format!("密钥交换超时: {}", endpoint)))??;
+        state
+            .clock
+            .observe(response.headers(), sent_at, chrono::Utc::now());
+
+        let status = response.status().as_u16();
+        if matches!(status, 404 | 405 | 501) {
+            return Err(Exchange::Unsupported(status));
+        }
+        if !response.status().is_success() {
+            return Err(AppError::Signature(format!("密钥交换接口返回 {}", status)).into());
+        }
+        let body: ExchangeResponse = tokio::time::timeout_at(deadline, response.json())
+            .await
+            .map_err(|_| AppError::Timeout(format!("密钥交换超时: {}", endpoint)))?
+            .map_err(|e| AppError::Signature(format!("密钥交换响应无法解析: {}", e)))?;
+        if body.session_id.trim().is_empty() {
+            return Err(AppError::Signature("密钥交换接口返回空会话 ID".to_string()).into());
+        }
+        self.accept(body.session_id, key, body.expires_in);
+        Ok(())
+    }
+}
+
+enum Exchange {
+    /// 后端不支持会话模式（状态码）
+    Unsupported(u16),
+    Failed(AppError),
+}
+
+impl<E: Into<AppError>> From<E> for Exchange {
+    fn from(error: E) -> Self {
+        Exchange::Failed(error.into())
+    }
+}
+
+/// HMAC-SHA256(key, data)，Base64 编码
+fn session_signature(key: &[u8], data: &str) -> String {
+    // HMAC 接受任意长度的密钥，new_from_slice 不会失败
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC 接受任意长度的密钥");
+    mac.update(data.as_bytes());
+    general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+impl SessionKeys {
+    /// 测试用：直接装入一个会话
+    pub(crate) fn with_session(id: &str, key: [u8; 32]) -> Self {
+        let keys = Self::new(Some("https://example.com/session".to_string()));
+        keys.accept(id.to_string(), key, None);
+        keys
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_signature() {
+        // RFC 4231 测试用例 2
+        let mut mac = HmacSha256::new_from_slice(b"Jefe").unwrap();
+        mac.update(b"what do ya want for nothing?");
+        assert_eq!(
+            hex::encode(mac.finalize().into_bytes()),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            session_signature(b"Jefe", "what do ya want for nothing?"),
+            "W9zBRr9gdU5qBCQmCJV1x1oAPwidJzmDnexYuWTsOEM="
+        );
+    }
+
+    #[test]
+    fn test_rekey() {
+        let keys = SessionKeys::with_session("s1", [7; 32]);
+        assert_eq!(keys.current().map(|s| s.id), Some("s1".to_string()));
+
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert!(!keys.requires_rekey(&headers));
+        headers.insert(
+            SESSION_EXPIRED_HEADER,
+            reqwest::header::HeaderValue::from_static("1"),
+        );
+        assert!(keys.requires_rekey(&headers));
+        keys.on_rekey();
+        assert!(keys.current().is_none());
+    }
+
+    #[test]
+    fn test_disabled_without_endpoint() {
+        let keys = SessionKeys::new(None);
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(
+            SESSION_EXPIRED_HEADER,
+            reqwest::header::HeaderValue::from_static("1"),
+        );
+        assert!(!keys.requires_rekey(&headers));
+        assert!(keys.current().is_none());
+    }
+}
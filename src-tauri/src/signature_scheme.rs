@@ -0,0 +1,326 @@
+/// 签名方案版本
+/// X-Client-Signature 的信封格式带版本号（X-Signature-Version），以后调整格式时新旧客户端可以并存：
+/// - v1：最初的格式 timestamp|fingerprint|device_info_hash(16 位)|path_hash(16 位)，RSA PKCS#1 v1.5 加密；
+///   只覆盖去掉 /base_api 前缀后的路径与查询串，保持不变以兼容尚未升级的验签端
+/// - v2：固定字段顺序的 JSON（哈希均为完整 64 位），AES-256-GCM 加密后用 RSA-OAEP-SHA256 包裹密钥
+///
+/// 会话模式下两个版本都改为对签名数据计算 HMAC（见 session_key）。
+/// 版本按环境由 TAURI_SIGNATURE_VERSION 配置（默认 2；v1 只在验签端尚未升级时显式选用），同时是允许使用的最低版本；
+/// 后端在签名来源的响应中用 X-Signature-Accept 列出支持的版本时，改用双方都支持的最高版本（不低于配置），
+/// 切换发生在 400/401/403 响应上时，用新版本重新签名并自动重发一次
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+
+use crate::crypto::{encrypt_signature, generate_legacy_signature_data};
+use crate::envelope::encrypt_envelope;
+use crate::error::AppError;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 标明签名方案版本的请求头
+pub const SIGNATURE_VERSION_HEADER: &str = "X-Signature-Version";
+
+/// 后端列出所支持版本的响应头（逗号分隔，如 "1, 2"）
+const SIGNATURE_ACCEPT_HEADER: &str = "x-signature-accept";
+
+/// 未配置 TAURI_SIGNATURE_VERSION 时使用的版本；必须覆盖规范请求
+const DEFAULT_VERSION: u8 = 2;
+
+/// 参与签名的各项数据
+pub struct SignatureFields<'a> {
+    pub timestamp: &'a str,
+    pub fingerprint: &'a str,
+    /// 设备信息原始 JSON（X-Device-Info 解码后的内容）
+    pub device_info_json: &'a str,
+    /// 请求地址；只有 v1 使用（对路径取哈希）
+    pub url: &'a str,
+    pub canonical_hash: &'a str,
+    pub nonce: &'a str,
+}
+
+/// 一种签名方案
+pub trait SignatureScheme: Send + Sync {
+    /// 版本号，写入 X-Signature-Version
+    fn version(&self) -> u8;
+
+    /// 签名数据是否包含规范请求哈希与 nonce；不包含时方法、查询串、请求体与签名头都不受保护
+    fn covers_request(&self) -> bool {
+        true
+    }
+
+    /// 待签名的数据；会话模式下直接对它计算 HMAC
+    fn payload(&self, fields: &SignatureFields<'_>) -> String;
+
+    /// 用公钥加密签名数据，结果作为 X-Client-Signature
+    fn seal(&self, payload: &str) -> Result<String, AppError>;
+}
+
+/// v1：最初的签名数据（只含路径哈希），RSA PKCS#1 v1.5 加密
+pub struct SchemeV1;
+
+impl SignatureScheme for SchemeV1 {
+    fn version(&self) -> u8 {
+        1
+    }
+
+    fn covers_request(&self) -> bool {
+        false
+    }
+
+    fn payload(&self, fields: &SignatureFields<'_>) -> String {
+        generate_legacy_signature_data(
+            fields.timestamp,
+            fields.fingerprint,
+            fields.device_info_json,
+            fields.url,
+        )
+    }
+
+    fn seal(&self, payload: &str) -> Result<String, AppError> {
+        encrypt_signature(payload)
+            .map_err(|e| AppError::Signature(format!("Failed to encrypt signature: {}", e)))
+    }
+}
+
+/// v2 的签名数据；字段顺序固定，验签端在会话模式下按同样顺序重建
+#[derive(Serialize)]
+struct PayloadV2<'a> {
+    version: u8,
+    timestamp: &'a str,
+    device_fingerprint: &'a str,
+    device_info_hash: String,
+    canonical_request_hash: &'a str,
+    nonce: &'a str,
+}
+
+/// v2：JSON 签名数据，完整长度的哈希，混合加密（见 envelope::encrypt_envelope）
+pub struct SchemeV2;
+
+impl SignatureScheme for SchemeV2 {
+    fn version(&self) -> u8 {
+        2
+    }
+
+    fn payload(&self, fields: &SignatureFields<'_>) -> String {
+        let payload = PayloadV2 {
+            version: self.version(),
+            timestamp: fields.timestamp,
+            device_fingerprint: fields.fingerprint,
+            device_info_hash: hex::encode(Sha256::digest(fields.device_info_json.as_bytes())),
+            canonical_request_hash: fields.canonical_hash,
+            nonce: fields.nonce,
+        };
+        // 只含字符串与整数，序列化不会失败
+        serde_json::to_string(&payload).unwrap_or_default()
+    }
+
+    fn seal(&self, payload: &str) -> Result<String, AppError> {
+        encrypt_envelope(payload)
+            .map_err(|e| AppError::Signature(format!("Failed to encrypt signature: {}", e)))
+    }
+}
+
+struct Inner {
+    schemes: Vec<Box<dyn SignatureScheme>>,
+    /// 配置的版本，也是协商允许的最低版本
+    minimum: u8,
+    active: AtomicU8,
+}
+
+/// 已注册的签名方案与当前使用的版本；签名链与发送逻辑共用
+#[derive(Clone)]
+pub struct SignatureSchemes(Arc<Inner>);
+
+impl SignatureSchemes {
+    pub fn from_env() -> Self {
+        let configured = option_env!("TAURI_SIGNATURE_VERSION")
+            .map(|v| v.trim().trim_start_matches(['v', 'V']))
+            .and_then(|v| v.parse::<u8>().ok());
+        Self::new(
+            vec![Box::new(SchemeV1), Box::new(SchemeV2)],
+            configured.unwrap_or(DEFAULT_VERSION),
+        )
+    }
+
+    /// 配置的版本未注册时使用默认版本
+    pub fn new(schemes: Vec<Box<dyn SignatureScheme>>, configured: u8) -> Self {
+        let minimum = if schemes.iter().any(|s| s.version() == configured) {
+            configured
+        } else {
+            log!(
+                "⚠️  [SIGN] 未知的签名版本 {}，使用 v{}",
+                configured,
+                DEFAULT_VERSION
+            );
+            DEFAULT_VERSION
+        };
+        log!("🔏 [SIGN] 签名方案 v{}", minimum);
+        if schemes
+            .iter()
+            .any(|s| s.version() == minimum && !s.covers_request())
+        {
+            log!(
+                "⚠️  [SIGN] v{} 只签路径哈希，方法、查询串、请求体与 nonce 不受签名保护",
+                minimum
+            );
+        }
+        Self(Arc::new(Inner {
+            schemes,
+            minimum,
+            active: AtomicU8::new(minimum),
+        }))
+    }
+
+    /// 当前使用的方案
+    pub fn active(&self) -> &dyn SignatureScheme {
+        let version = self.0.active.load(Ordering::Relaxed);
+        self.0
+            .schemes
+            .iter()
+            .find(|scheme| scheme.version() == version)
+            .or_else(|| self.0.schemes.first())
+            .map(Box::as_ref)
+            .expect("至少注册一种签名方案")
+    }
+
+    /// 根据后端的 X-Signature-Accept 协商版本；版本发生变化时返回 true
+    pub fn negotiate(&self, headers: &reqwest::header::HeaderMap) -> bool {
+        let Some(accepted) = headers
+            .get(SIGNATURE_ACCEPT_HEADER)
+            .and_then(|v| v.to_str().ok())
+        else {
+            return false;
+        };
+        let accepted: Vec<u8> = accepted
+            .split(',')
+            .filter_map(|v| v.trim().trim_start_matches(['v', 'V']).parse().ok())
+            .collect();
+        let Some(best) = self
+            .0
+            .schemes
+            .iter()
+            .map(|scheme| scheme.version())
+            .filter(|version| *version >= self.0.minimum && accepted.contains(version))
+            .max()
+        else {
+            log!(
+                "⚠️  [SIGN] 后端支持的签名版本 {:?} 均低于 v{}，不降级",
+                accepted,
+                self.0.minimum
+            );
+            return false;
+        };
+        let previous = self.0.active.swap(best, Ordering::Relaxed);
+        if previous != best {
+            log!("🔏 [SIGN] 签名方案 v{} -> v{}", previous, best);
+        }
+        previous != best
+    }
+}
+
+/// 协商切换版本后是否值得重发：后端因版本不符拒绝时通常返回 400/401/403
+pub fn rejected_by_version(status: u16) -> bool {
+    matches!(status, 400 | 401 | 403)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    const FIELDS: SignatureFields<'static> = SignatureFields {
+        timestamp: "2024-01-01T00:00:00+00:00",
+        fingerprint: "fp",
+        device_info_json: "{}",
+        url: "https://uat.example.com/base_api/dict/%E5%AD%97%E5%85%B8?type=1",
+        canonical_hash: "c0ffee",
+        nonce: "n1",
+    };
+
+    fn accept(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(SIGNATURE_ACCEPT_HEADER, HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn test_payloads() {
+        // v1：去掉 /base_api 前缀、URL 解码后的路径与查询串
+        let path_hash = hex::encode(Sha256::digest("/dict/字典?type=1".as_bytes()));
+        assert_eq!(
+            SchemeV1.payload(&FIELDS),
+            format!(
+                "2024-01-01T00:00:00+00:00|fp|44136fa355b3678a|{}",
+                &path_hash[..16]
+            )
+        );
+        assert_eq!(
+            SchemeV2.payload(&FIELDS),
+            concat!(
+                r#"{"version":2,"timestamp":"2024-01-01T00:00:00+00:00","device_fingerprint":"fp","#,
+                r#""device_info_hash":"44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a","#,
+                r#""canonical_request_hash":"c0ffee","nonce":"n1"}"#
+            )
+        );
+    }
+
+    fn all_schemes() -> Vec<Box<dyn SignatureScheme>> {
+        vec![Box::new(SchemeV1), Box::new(SchemeV2)]
+    }
+
+    #[test]
+    fn test_negotiation() {
+        let schemes = SignatureSchemes::new(all_schemes(), 1);
+        assert_eq!(schemes.active().version(), 1);
+        assert!(!schemes.negotiate(&HeaderMap::new()));
+
+        // 升级到双方都支持的最高版本
+        assert!(schemes.negotiate(&accept("1, 2, 3")));
+        assert_eq!(schemes.active().version(), 2);
+        assert!(!schemes.negotiate(&accept("v2")));
+
+        // 允许回到配置的版本，但不低于它
+        assert!(schemes.negotiate(&accept("1")));
+        assert_eq!(schemes.active().version(), 1);
+
+        let pinned = SignatureSchemes::new(all_schemes(), 2);
+        assert!(!pinned.negotiate(&accept("1")));
+        assert_eq!(pinned.active().version(), 2);
+        assert!(!pinned.negotiate(&accept("1, 2")));
+        assert_eq!(pinned.active().version(), 2);
+    }
+
+    #[test]
+    fn test_default_version_covers_request() {
+        let schemes = SignatureSchemes::new(all_schemes(), DEFAULT_VERSION);
+        assert!(schemes.active().covers_request());
+        assert!(!SchemeV1.covers_request());
+    }
+
+    #[test]
+    fn test_unknown_configured_version() {
+        let schemes = SignatureSchemes::new(all_schemes(), 9);
+        assert_eq!(schemes.active().version(), DEFAULT_VERSION);
+    }
+}
@@ -0,0 +1,393 @@
+/// 请求签名流水线
+/// 代理、下载与 WebSocket 握手发出的请求都经过同一条有序的签名链，
+/// 每个阶段实现 RequestSigner，按顺序向请求追加头；后端规则变化时只需替换或新增阶段。
+/// 默认链：
+/// 1. AuthTokenInjector：登录后由前端设置的访问令牌（页面已带 Authorization 时不覆盖）
+/// 2. TraceHeaders：W3C traceparent 与 X-Request-Id，便于后端串联日志
+/// 3. EnvironmentHeaders：TAURI_PROXY_EXTRA_HEADERS 配置的按环境固定请求头
+/// 4. ServerChallenge：启用服务端挑战时附加当前挑战值（见 challenge）
+/// 5. DeviceSignature：时间戳（按服务器时差校正，见 clock）、随机 nonce、设备指纹、设备信息与
+///    RSA 加密签名（有会话时为会话密钥 HMAC，见 session_key）；放在最后，
+///    签名覆盖的规范请求（见 canonical）包含前面阶段追加的头
+///
+/// 设备、凭据与环境相关的阶段只对签名来源生效，非签名来源只附加追踪头
+/// ServerChallenge 与 DeviceSignature 见 device_signature
+use std::sync::{Arc, RwLock};
+use tauri::State;
+
+use crate::canonical::signed_header_names;
+use crate::challenge::ChallengeStore;
+use crate::clock::ServerClock;
+use crate::device_signature::{DeviceSignature, ServerChallenge};
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::proxy::AppState;
+use crate::session_key::SessionKeys;
+use crate::signature_scheme::SignatureSchemes;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 交给签名链的请求（各阶段只读）
+pub struct SigningRequest<'a> {
+    /// 前端请求 ID（下载任务 ID）；没有时为 None
+    pub id: Option<&'a str>,
+    pub method: &'a str,
+    pub url: &'a str,
+    /// 页面传入的请求头
+    pub headers: &'a HeaderList,
+    /// 请求体 SHA-256（十六进制），见 canonical::body_hash
+    pub body_hash: &'a str,
+    /// 目标是否为签名来源
+    pub signed: bool,
+}
+
+/// 签名链中的一个阶段
+pub trait RequestSigner: Send + Sync {
+    /// 阶段名称（用于日志）
+    fn name(&self) -> &'static str;
+
+    /// 向 out 追加本阶段的请求头；out 中已有前面阶段的结果。返回错误时请求不会发出
+    fn sign(&self, request: &SigningRequest<'_>, out: &mut HeaderList) -> Result<(), AppError>;
+}
+
+/// 有序的签名链
+pub struct SigningPipeline {
+    stages: Vec<Box<dyn RequestSigner>>,
+    auth: AuthToken,
+}
+
+impl SigningPipeline {
+    pub fn new(auth: AuthToken, stages: Vec<Box<dyn RequestSigner>>) -> Self {
+        Self { stages, auth }
+    }
+
+    /// 默认签名链（见模块说明）
+    pub fn from_env(
+        challenge: ChallengeStore,
+        clock: ServerClock,
+        sessions: SessionKeys,
+        schemes: SignatureSchemes,
+    ) -> Self {
+        let auth = AuthToken::default();
+        Self::new(
+            auth.clone(),
+            vec![
+                Box::new(AuthTokenInjector::new(auth)),
+                Box::new(TraceHeaders),
+                Box::new(EnvironmentHeaders::from_env()),
+                Box::new(ServerChallenge::new(challenge, schemes.clone())),
+                Box::new(DeviceSignature::new(
+                    signed_header_names(),
+                    clock,
+                    sessions,
+                    schemes,
+                )),
+            ],
+        )
+    }
+
+    /// 前端设置的访问令牌
+    pub fn auth_token(&self) -> &AuthToken {
+        &self.auth
+    }
+
+    /// 依次运行各阶段，返回需要追加到请求上的头
+    pub fn sign(&self, request: &SigningRequest<'_>) -> Result<HeaderList, AppError> {
+        log!("🔏 [SIGN] {} {}", request.method, request.url);
+        let mut out = HeaderList::default();
+        for stage in &self.stages {
+            stage.sign(request, &mut out).inspect_err(|e| {
+                log!("❌ [SIGN] {} 失败: {}", stage.name(), e);
+            })?;
+        }
+        Ok(out)
+    }
+}
+
+/// 前端登录后设置的访问令牌，签名链与命令共用
+#[derive(Clone, Default)]
+pub struct AuthToken(Arc<RwLock<Option<String>>>);
+
+impl AuthToken {
+    pub fn get(&self) -> Option<String> {
+        self.0.read().ok().and_then(|token| token.clone())
+    }
+
+    pub fn set(&self, token: Option<String>) {
+        if let Ok(mut current) = self.0.write() {
+            *current = token.filter(|t| !t.trim().is_empty());
+        }
+    }
+}
+
+/// 注入 Authorization: Bearer <token>；页面自己带了 Authorization 时保持不变
+pub struct AuthTokenInjector {
+    token: AuthToken,
+}
+
+impl AuthTokenInjector {
+    pub fn new(token: AuthToken) -> Self {
+        Self { token }
+    }
+}
+
+impl RequestSigner for AuthTokenInjector {
+    fn name(&self) -> &'static str {
+        "auth-token"
+    }
+
+    fn sign(&self, request: &SigningRequest<'_>, out: &mut HeaderList) -> Result<(), AppError> {
+        if !request.signed || request.headers.contains("authorization") {
+            return Ok(());
+        }
+        if let Some(token) = self.token.get() {
+            out.set("Authorization", format!("Bearer {}", token));
+        }
+        Ok(())
+    }
+}
+
+/// W3C Trace Context 与请求 ID；页面已带 traceparent 时沿用页面的链路
+pub struct TraceHeaders;
+
+impl RequestSigner for TraceHeaders {
+    fn name(&self) -> &'static str {
+        "trace"
+    }
+
+    fn sign(&self, request: &SigningRequest<'_>, out: &mut HeaderList) -> Result<(), AppError> {
+        if !request.headers.contains("traceparent") {
+            let trace_id = uuid::Uuid::new_v4().simple().to_string();
+            let span_id = &uuid::Uuid::new_v4().simple().to_string()[..16];
+            out.set("traceparent", format!("00-{}-{}-01", trace_id, span_id));
+        }
+        if !request.headers.contains("x-request-id") {
+            let id = request
+                .id
+                .map(str::to_string)
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            out.set("X-Request-Id", id);
+        }
+        Ok(())
+    }
+}
+
+/// 按环境固定附加的请求头
+/// TAURI_PROXY_EXTRA_HEADERS 为 JSON 对象，例如 {"X-App-Channel":"desktop"}；页面传入的同名头优先
+pub struct EnvironmentHeaders {
+    headers: HeaderList,
+}
+
+impl EnvironmentHeaders {
+    pub fn new(headers: HeaderList) -> Self {
+        Self { headers }
+    }
+
+    pub fn from_env() -> Self {
+        let headers = match option_env!("TAURI_PROXY_EXTRA_HEADERS") {
+            Some(raw) if !raw.trim().is_empty() => serde_json::from_str::<HeaderList>(raw)
+                .unwrap_or_else(|e| {
+                    log!("⚠️  [SIGN] TAURI_PROXY_EXTRA_HEADERS 解析失败，忽略: {}", e);
+                    HeaderList::default()
+                }),
+            _ => HeaderList::default(),
+        };
+        Self::new(headers)
+    }
+}
+
+impl RequestSigner for EnvironmentHeaders {
+    fn name(&self) -> &'static str {
+        "environment-headers"
+    }
+
+    fn sign(&self, request: &SigningRequest<'_>, out: &mut HeaderList) -> Result<(), AppError> {
+        if !request.signed {
+            return Ok(());
+        }
+        for (name, value) in self.headers.iter() {
+            if !request.headers.contains(name) && !out.contains(name) {
+                out.set(name, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 设置（或清除）签名来源请求附加的访问令牌
+#[tauri::command]
+pub fn set_proxy_auth_token(
+    token: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    log!(
+        "🔑 [SIGN] {}访问令牌",
+        if token.is_some() { "设置" } else { "清除" }
+    );
+    state.signers.auth_token().set(token);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canonical::EMPTY_BODY_HASH;
+
+    fn request<'a>(headers: &'a HeaderList, signed: bool) -> SigningRequest<'a> {
+        SigningRequest {
+            id: Some("req-1"),
+            method: "GET",
+            url: "https://example.com/base_api/dict/list?type=1",
+            headers,
+            body_hash: EMPTY_BODY_HASH,
+            signed,
+        }
+    }
+
+    #[test]
+    fn test_auth_token_injection() {
+        let token = AuthToken::default();
+        let stage = AuthTokenInjector::new(token.clone());
+        let empty = HeaderList::default();
+
+        let mut out = HeaderList::default();
+        stage.sign(&request(&empty, true), &mut out).unwrap();
+        assert_eq!(out.get("authorization"), None, "未设置令牌时不应附加");
+
+        token.set(Some("abc".to_string()));
+        let mut out = HeaderList::default();
+        stage.sign(&request(&empty, true), &mut out).unwrap();
+        assert_eq!(out.get("authorization"), Some("Bearer abc"));
+
+        let mut out = HeaderList::default();
+        stage.sign(&request(&empty, false), &mut out).unwrap();
+        assert_eq!(out.get("authorization"), None, "非签名来源不应收到令牌");
+
+        let page: HeaderList = [("Authorization", "Basic xyz")].into_iter().collect();
+        let mut out = HeaderList::default();
+        stage.sign(&request(&page, true), &mut out).unwrap();
+        assert_eq!(
+            out.get("authorization"),
+            None,
+            "页面自带的 Authorization 优先"
+        );
+
+        token.set(Some("  ".to_string()));
+        assert_eq!(token.get(), None);
+    }
+
+    #[test]
+    fn test_trace_headers() {
+        let empty = HeaderList::default();
+        let mut out = HeaderList::default();
+        TraceHeaders
+            .sign(&request(&empty, false), &mut out)
+            .unwrap();
+
+        let traceparent = out.get("traceparent").unwrap();
+        let parts: Vec<&str> = traceparent.split('-').collect();
+        assert_eq!(parts.len(), 4);
+        assert_eq!(
+            (parts[0], parts[1].len(), parts[2].len(), parts[3]),
+            ("00", 32, 16, "01")
+        );
+        assert_eq!(out.get("x-request-id"), Some("req-1"));
+
+        let page: HeaderList = [
+            (
+                "traceparent",
+                "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            ),
+            ("X-Request-Id", "page"),
+        ]
+        .into_iter()
+        .collect();
+        let mut out = HeaderList::default();
+        TraceHeaders.sign(&request(&page, true), &mut out).unwrap();
+        assert_eq!(out, HeaderList::default(), "页面自带的追踪头优先");
+    }
+
+    #[test]
+    fn test_environment_headers() {
+        let stage = EnvironmentHeaders::new(
+            [("X-App-Channel", "desktop"), ("X-Tenant", "default")]
+                .into_iter()
+                .collect(),
+        );
+        let page: HeaderList = [("x-tenant", "page")].into_iter().collect();
+
+        let mut out = HeaderList::default();
+        stage.sign(&request(&page, true), &mut out).unwrap();
+        assert_eq!(out.get("X-App-Channel"), Some("desktop"));
+        assert_eq!(out.get("X-Tenant"), None, "页面传入的同名头优先");
+
+        let mut out = HeaderList::default();
+        stage.sign(&request(&page, false), &mut out).unwrap();
+        assert_eq!(out, HeaderList::default());
+    }
+
+    #[test]
+    fn test_pipeline_order_and_errors() {
+        struct Fixed(&'static str, &'static str);
+        impl RequestSigner for Fixed {
+            fn name(&self) -> &'static str {
+                "fixed"
+            }
+            fn sign(&self, _: &SigningRequest<'_>, out: &mut HeaderList) -> Result<(), AppError> {
+                out.set(self.0, self.1);
+                Ok(())
+            }
+        }
+        struct Failing;
+        impl RequestSigner for Failing {
+            fn name(&self) -> &'static str {
+                "failing"
+            }
+            fn sign(&self, _: &SigningRequest<'_>, _: &mut HeaderList) -> Result<(), AppError> {
+                Err(AppError::Signature("boom".to_string()))
+            }
+        }
+
+        let empty = HeaderList::default();
+        let pipeline = SigningPipeline::new(
+            AuthToken::default(),
+            vec![
+                Box::new(Fixed("X-A", "1")),
+                Box::new(Fixed("X-B", "2")),
+                Box::new(Fixed("X-A", "3")),
+            ],
+        );
+        let out = pipeline.sign(&request(&empty, true)).unwrap();
+        let names: Vec<(&str, &str)> = out.iter().collect();
+        assert_eq!(
+            names,
+            vec![("X-B", "2"), ("X-A", "3")],
+            "后面的阶段覆盖前面的同名头"
+        );
+
+        let pipeline = SigningPipeline::new(
+            AuthToken::default(),
+            vec![Box::new(Fixed("X-A", "1")), Box::new(Failing)],
+        );
+        assert!(pipeline.sign(&request(&empty, true)).is_err());
+    }
+}
@@ -0,0 +1,257 @@
+/// 响应体识别与解码
+/// 只看 Content-Type 不够：老接口会把 GBK 编码的 CSV 标成 text/csv 不带 charset，
+/// 把 JSON 标成 application/octet-stream。这里结合声明类型与响应体内容判断：
+/// 1. 魔数（PNG、PDF、ZIP/xlsx、OLE/xls 等）命中即为二进制
+/// 2. BOM 决定编码（优先于 charset 参数）
+/// 3. charset 参数（GBK/GB18030 等按 WHATWG 标签解析）
+/// 4. 未声明编码的文本：合法 UTF-8 按 UTF-8，否则按 TAURI_PROXY_FALLBACK_CHARSET（默认 GB18030）
+///
+/// 流式响应原样传字节，不经过这里
+use base64::{engine::general_purpose, Engine as _};
+use encoding_rs::Encoding;
+
+/// 未声明 charset 且不是合法 UTF-8 时使用的编码（GB18030 兼容 GBK）
+fn fallback_encoding() -> &'static Encoding {
+    option_env!("TAURI_PROXY_FALLBACK_CHARSET")
+        .and_then(|label| Encoding::for_label(label.trim().as_bytes()))
+        .unwrap_or(encoding_rs::GB18030)
+}
+
+/// 嗅探时最多检查的字节数
+const SNIFF_LEN: usize = 1024;
+
+/// 常见二进制格式的文件头
+const MAGIC_NUMBERS: &[&[u8]] = &[
+    b"\x89PNG\r\n\x1a\n",
+    b"\xFF\xD8\xFF",
+    b"GIF87a",
+    b"GIF89a",
+    b"%PDF-",
+    b"PK\x03\x04",
+    b"PK\x05\x06",
+    b"\x1F\x8B\x08",
+    b"Rar!\x1A\x07",
+    b"7z\xBC\xAF\x27\x1C",
+    b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1",
+    b"\x00asm",
+    b"wOFF",
+    b"wOF2",
+];
+
+/// 声明类型的分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Declared {
+    Text,
+    Binary,
+    /// 未声明或无法据此判断（application/octet-stream、未知的 application/*）
+    Unknown,
+}
+
+/// 解码后的响应体
+pub struct DecodedBody {
+    /// 文本响应为解码后的字符串，二进制响应为 base64
+    pub body: String,
+    pub is_binary: bool,
+    /// 文本响应实际使用的编码（如 UTF-8、GBK、gb18030）
+    pub encoding: Option<&'static str>,
+}
+
+/// 结合 Content-Type 与响应体内容判断文本/二进制，并解码文本
+pub fn decode_body(bytes: &[u8], content_type: &str) -> DecodedBody {
+    let content_type = content_type.to_ascii_lowercase();
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+
+    match detect_text_encoding(bytes, mime, &content_type) {
+        Some(encoding) => {
+            // decode 会去掉与编码一致的 BOM
+            let (text, _, _) = encoding.decode(bytes);
+            DecodedBody {
+                body: text.into_owned(),
+                is_binary: false,
+                encoding: Some(encoding.name()),
+            }
+        }
+        None => DecodedBody {
+            body: general_purpose::STANDARD.encode(bytes),
+            is_binary: true,
+            encoding: None,
+        },
+    }
+}
+
+/// 文本返回其编码，二进制返回 None
+fn detect_text_encoding(
+    bytes: &[u8],
+    mime: &str,
+    content_type: &str,
+) -> Option<&'static Encoding> {
+    if has_magic_number(bytes) {
+        return None;
+    }
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return Some(encoding);
+    }
+
+    let declared = classify_mime(mime);
+    let charset = charset_of(content_type);
+
+    let is_text = match declared {
+        Declared::Text => true,
+        Declared::Binary => false,
+        // 带 charset 的按文本；未声明类型时不含二进制控制字符即按文本；
+        // octet-stream 等只有看起来像 JSON/XML 时才按文本
+        Declared::Unknown => {
+            charset.is_some()
+                || if mime.is_empty() {
+                    !has_binary_data(bytes)
+                } else {
+                    looks_like_structured_text(bytes)
+                }
+        }
+    };
+    if !is_text {
+        return None;
+    }
+
+    charset.or_else(|| {
+        if std::str::from_utf8(bytes).is_ok() {
+            Some(encoding_rs::UTF_8)
+        } else {
+            Some(fallback_encoding())
+        }
+    })
+}
+
+/// 按 MIME 类型分类（不含参数，已转小写）
+fn classify_mime(mime: &str) -> Declared {
+    if mime.is_empty() {
+        return Declared::Unknown;
+    }
+    if mime.starts_with("text/")
+        || mime.ends_with("+json")
+        || mime.ends_with("+xml")
+        || matches!(
+            mime,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/x-javascript"
+                | "application/ecmascript"
+                | "application/x-www-form-urlencoded"
+                | "application/x-ndjson"
+                | "application/csv"
+        )
+    {
+        return Declared::Text;
+    }
+    if mime.starts_with("image/")
+        || mime.starts_with("audio/")
+        || mime.starts_with("video/")
+        || mime.starts_with("font/")
+        || mime.starts_with("application/x-font")
+        || mime.starts_with("application/vnd.openxmlformats-officedocument")
+        || matches!(
+            mime,
+            "application/pdf"
+                | "application/zip"
+                | "application/x-rar-compressed"
+                | "application/vnd.rar"
+                | "application/x-tar"
+                | "application/gzip"
+                | "application/x-gzip"
+                | "application/x-7z-compressed"
+                | "application/vnd.ms-excel"
+                | "application/msword"
+                | "application/vnd.ms-powerpoint"
+                | "application/wasm"
+        )
+    {
+        return Declared::Binary;
+    }
+    Declared::Unknown
+}
+
+/// Content-Type 的 charset 参数；无法识别的标签视为未声明
+fn charset_of(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.trim().strip_prefix("charset="))
+        .find_map(|label| Encoding::for_label(label.trim().trim_matches('"').as_bytes()))
+}
+
+fn has_magic_number(bytes: &[u8]) -> bool {
+    MAGIC_NUMBERS.iter().any(|magic| bytes.starts_with(magic))
+        // RIFF 容器（WEBP/WAV/AVI）与 ISO BMFF（MP4/HEIC）
+        || (bytes.starts_with(b"RIFF") && bytes.len() >= 12)
+        || bytes.get(4..8) == Some(b"ftyp")
+}
+
+/// 以 { [ < 开头且不含二进制控制字符
+fn looks_like_structured_text(bytes: &[u8]) -> bool {
+    let starts_structured = bytes
+        .iter()
+        .find(|b| !b.is_ascii_whitespace())
+        .is_some_and(|b| matches!(b, b'{' | b'[' | b'<'));
+    starts_structured && !has_binary_data(bytes)
+}
+
+/// 开头 SNIFF_LEN 字节内是否有二进制控制字符
+fn has_binary_data(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(SNIFF_LEN)]
+        .iter()
+        .any(|&b| is_binary_data_byte(b))
+}
+
+/// WHATWG MIME 嗅探中的“二进制数据字节”
+fn is_binary_data_byte(byte: u8) -> bool {
+    matches!(byte, 0x00..=0x08 | 0x0B | 0x0E..=0x1A | 0x1C..=0x1F)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// "中文" 的 GBK 编码
+    const GBK_TEXT: &[u8] = b"\xD6\xD0\xCE\xC4";
+
+    #[test]
+    fn test_declared_charset() {
+        let decoded = decode_body(GBK_TEXT, "text/csv; charset=GBK");
+        assert!(!decoded.is_binary);
+        assert_eq!(decoded.body, "中文");
+        assert_eq!(decoded.encoding, Some("GBK"));
+
+        // 带引号、大小写不同的标签同样识别；无法识别的标签视为未声明
+        let decoded = decode_body("中文".as_bytes(), "text/plain; Charset=\"UTF-8\"");
+        assert_eq!(decoded.encoding, Some("UTF-8"));
+        let decoded = decode_body("中文".as_bytes(), "text/plain; charset=unknown-x");
+        assert_eq!((decoded.body.as_str(), decoded.encoding), ("中文", Some("UTF-8")));
+    }
+
+    #[test]
+    fn test_undeclared_charset_fallback() {
+        // 未声明 charset：合法 UTF-8 按 UTF-8，否则按回退编码（默认 GB18030）
+        let decoded = decode_body(GBK_TEXT, "text/csv");
+        assert_eq!(decoded.body, "中文");
+        assert_eq!(decoded.encoding, Some(fallback_encoding().name()));
+
+        let decoded = decode_body(b"{\"ok\":true}", "application/octet-stream");
+        assert!(!decoded.is_binary);
+        assert_eq!(decoded.body, "{\"ok\":true}");
+    }
+
+    #[test]
+    fn test_bom_and_binary() {
+        // BOM 优先于 charset 参数，且不出现在解码结果中
+        let mut bytes = b"\xEF\xBB\xBF".to_vec();
+        bytes.extend_from_slice("中文".as_bytes());
+        let decoded = decode_body(&bytes, "text/plain; charset=GBK");
+        assert_eq!((decoded.body.as_str(), decoded.encoding), ("中文", Some("UTF-8")));
+
+        let decoded = decode_body(b"\x89PNG\r\n\x1a\n\x00\x00", "text/plain");
+        assert!(decoded.is_binary);
+        assert_eq!(decoded.encoding, None);
+        assert!(decode_body(b"\x00\x01\x02", "application/octet-stream").is_binary);
+    }
+}
@@ -0,0 +1,264 @@
+/// Server-Sent Events 代理模块
+/// EventSource 的请求不经过 fetch 拦截，而 proxy_request 需要缓冲完整响应体，
+/// 无法处理永不结束的 text/event-stream。这里单独建立流式连接：
+/// 请求经过 send_following_redirects（签名、来源校验、熔断与重定向），
+/// 响应体按 HTML 规范逐行解析为事件，通过 Channel 逐条推给发起请求的页面；
+/// 连接断开后等待 retry 指定的时间，带 Last-Event-ID 重新连接
+use crate::dispatch::SentRequest;
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::proxy::{is_internal_url, AppState, ProxyRequest};
+use crate::redirect::{send_following_redirects, Followed, RedirectMode};
+use crate::resilience::backoff_delay;
+use crate::sse_parser::{EventParser, Parsed};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tauri::ipc::Channel;
+use tauri::State;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 服务端未指定 retry 时的重连等待时间（与浏览器一致）
+const DEFAULT_RETRY_MS: u64 = 3_000;
+
+/// 长时间没有任何数据（含注释心跳）视为连接已断，主动重连
+const IDLE_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SseConnectRequest {
+    /// 前端生成的连接 id，sse_close 据此关闭连接
+    pub id: String,
+    pub url: String,
+    #[serde(default)]
+    pub headers: HeaderList,
+    /// 页面重建连接时可带上已处理的最后一个事件 id
+    #[serde(default)]
+    pub last_event_id: Option<String>,
+}
+
+/// 推给页面的事件，按发生顺序到达
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SseEvent {
+    /// 收到 200 text/event-stream 响应头
+    Open { url: String },
+    /// 一条完整的事件；name 默认为 message
+    Message {
+        name: String,
+        data: String,
+        last_event_id: String,
+    },
+    /// 连接断开，delay_ms 后重连
+    Reconnecting { delay_ms: u64 },
+    /// 不再重连（状态码或 Content-Type 不符合要求、204、来源被拒等）
+    Failed { error: AppError },
+}
+
+/// 建立 SSE 连接；立即返回，后续状态全部通过 on_event 推送
+#[tauri::command]
+pub fn sse_connect(
+    app: tauri::AppHandle,
+    request: SseConnectRequest,
+    on_event: Channel<SseEvent>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    if is_internal_url(&request.url) {
+        return Err(AppError::InvalidRequest("Internal IPC request, skipping".to_string()));
+    }
+    // 来源先同步校验，页面可立即得到错误
+    state.origins.check(&request.url)?;
+
+    log!("📡 [SSE] 连接 {} ({})", request.url, request.id);
+    let state = Arc::clone(state.inner());
+    // 与普通请求共用取消登记：sse_close 即取消该 id
+    let guard = state.inflight.register(&request.id);
+    tauri::async_runtime::spawn(async move {
+        let token = guard.token();
+        tokio::select! {
+            _ = token.cancelled() => log!("🛑 [SSE] 已关闭: {}", request.url),
+            _ = run_event_source(&app, &state, &request, &on_event) => {}
+        }
+        drop(guard);
+    });
+    Ok(())
+}
+
+/// 关闭 SSE 连接；连接已结束时返回 false
+#[tauri::command]
+pub fn sse_close(id: String, state: State<'_, Arc<AppState>>) -> Result<bool, AppError> {
+    Ok(state.inflight.cancel(&id))
+}
+
+/// 连接主循环：连接 → 解析事件 → 断开后等待 retry 重连
+async fn run_event_source(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    request: &SseConnectRequest,
+    on_event: &Channel<SseEvent>,
+) {
+    let mut parser = EventParser::new(request.last_event_id.clone().unwrap_or_default());
+    let mut retry = Duration::from_millis(DEFAULT_RETRY_MS);
+    // 连续失败次数；收到过事件后清零
+    let mut failures = 0u32;
+
+    loop {
+        match connect(app, state, request, parser.last_event_id()).await {
+            Ok((response, url)) => {
+                if on_event.send(SseEvent::Open { url }).is_err() {
+                    return;
+                }
+                let mut body = response.bytes_stream();
+                loop {
+                    let chunk = match tokio::time::timeout(IDLE_TIMEOUT, body.next()).await {
+                        Ok(Some(Ok(chunk))) => chunk,
+                        Ok(Some(Err(e))) => {
+                            log!("⚠️  [SSE] 读取中断 {}: {}", request.url, e);
+                            break;
+                        }
+                        Ok(None) => {
+                            log!("📡 [SSE] 服务端结束响应: {}", request.url);
+                            break;
+                        }
+                        Err(_) => {
+                            log!("⚠️  [SSE] {:?} 内无数据，重新连接: {}", IDLE_TIMEOUT, request.url);
+                            break;
+                        }
+                    };
+                    for item in parser.feed(&chunk) {
+                        match item {
+                            Parsed::Retry(ms) => retry = Duration::from_millis(ms),
+                            Parsed::Event(event) => {
+                                failures = 0;
+                                if on_event.send(event).is_err() {
+                                    return;
+                                }
+                            }
+                        }
+                    }
+                }
+                // 断开时未结束的事件丢弃
+                parser.reset_pending();
+            }
+            Err(ConnectError::Fatal(error)) => {
+                log!("❌ [SSE] 连接失败，不再重连 {}: {}", request.url, error);
+                let _ = on_event.send(SseEvent::Failed { error });
+                return;
+            }
+            Err(ConnectError::Retry(error)) => {
+                log!("⚠️  [SSE] 连接失败 {}: {}", request.url, error);
+            }
+        }
+
+        // 连续失败时在 retry 基础上指数退避
+        let delay = if failures == 0 {
+            retry
+        } else {
+            retry.max(backoff_delay(failures))
+        };
+        failures = failures.saturating_add(1);
+        log!("🔁 [SSE] {:?} 后重连: {}", delay, request.url);
+        let event = SseEvent::Reconnecting {
+            delay_ms: delay.as_millis() as u64,
+        };
+        if on_event.send(event).is_err() {
+            return;
+        }
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/// 连接失败的处理方式
+enum ConnectError {
+    /// 按规范需要“fail the connection”，不再重连
+    Fatal(AppError),
+    /// 网络错误，稍后重连
+    Retry(AppError),
+}
+
+/// 发送请求并校验响应头，返回响应与最终地址
+async fn connect(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    request: &SseConnectRequest,
+    last_event_id: &str,
+) -> Result<(reqwest::Response, String), ConnectError> {
+    let mut headers = request.headers.clone();
+    headers.set("Accept", "text/event-stream");
+    headers.set("Cache-Control", "no-cache");
+    if !last_event_id.is_empty() {
+        headers.set("Last-Event-ID", last_event_id);
+    }
+    let proxy_request = ProxyRequest {
+        id: None,
+        method: "GET".to_string(),
+        url: request.url.clone(),
+        headers,
+        body: None,
+        form_data: None,
+        files: None,
+        parts: None,
+        timeouts: None,
+        max_retries: None,
+        redirect: RedirectMode::Follow,
+        coalesce: false,
+        webview: None,
+    };
+
+    let timeouts = state.policy.effective(None);
+    let deadline = Instant::now() + timeouts.total;
+    let followed = send_following_redirects(app, state, &proxy_request, timeouts, deadline)
+        .await
+        .map_err(|error| match error {
+            AppError::OriginDenied(_) | AppError::Redirect(_) | AppError::InvalidUrl(_) => {
+                ConnectError::Fatal(error)
+            }
+            other => ConnectError::Retry(other),
+        })?;
+    // 长连接不占用主机并发许可
+    let Followed { sent, request, .. } = followed;
+    let SentRequest { response, permit, .. } = sent;
+    drop(permit);
+    let url = request.url;
+
+    let status = response.status().as_u16();
+    if status != 200 {
+        // 204 表示服务端要求停止重连；其他非 200 状态同样不再重连
+        return Err(ConnectError::Fatal(AppError::HttpStatus(status)));
+    }
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    if !mime.eq_ignore_ascii_case("text/event-stream") {
+        return Err(ConnectError::Fatal(AppError::InvalidRequest(format!(
+            "Content-Type 不是 text/event-stream: {}",
+            content_type
+        ))));
+    }
+
+    log!("✅ [SSE] 已连接 {}", url);
+    Ok((response, url))
+}
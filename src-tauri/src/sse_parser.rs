@@ -0,0 +1,253 @@
+/// text/event-stream 解析
+/// 把 SSE 响应体按 HTML 规范逐行解析为事件：event / data / id / retry 字段、注释行、
+/// 流开头的 BOM 以及 CR / LF / CRLF 三种行结束符；连接与重连见 sse 模块
+use crate::sse::SseEvent;
+
+pub(crate) enum Parsed {
+    Event(SseEvent),
+    Retry(u64),
+}
+
+/// text/event-stream 增量解析器（HTML 规范 9.2.6）
+/// 按字节缓冲到行结束符再解码，UTF-8 多字节字符跨块时不会被截断
+pub(crate) struct EventParser {
+    buffer: Vec<u8>,
+    /// 上一块以 CR 结尾：下一块开头的 LF 属于同一个 CRLF
+    pending_cr: bool,
+    /// 是否还需要去掉流开头的 BOM
+    at_start: bool,
+    name: String,
+    data: String,
+    last_event_id: String,
+}
+
+impl EventParser {
+    pub fn new(last_event_id: String) -> Self {
+        Self {
+            buffer: Vec::new(),
+            pending_cr: false,
+            at_start: true,
+            name: String::new(),
+            data: String::new(),
+            last_event_id,
+        }
+    }
+
+    /// 最近一次 id 字段的值，重连时作为 Last-Event-ID 发送
+    pub fn last_event_id(&self) -> &str {
+        &self.last_event_id
+    }
+
+    /// 重连后从新的响应开始解析，last_event_id 保留
+    pub fn reset_pending(&mut self) {
+        self.buffer.clear();
+        self.pending_cr = false;
+        self.at_start = true;
+        self.name.clear();
+        self.data.clear();
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<Parsed> {
+        let mut out = Vec::new();
+        for &byte in chunk {
+            // CRLF 只算一个行结束符（CR 与 LF 可能落在两个块里）
+            if std::mem::take(&mut self.pending_cr) && byte == b'\n' {
+                continue;
+            }
+            match byte {
+                b'\r' | b'\n' => {
+                    let line = std::mem::take(&mut self.buffer);
+                    self.process_line(&line, &mut out);
+                    self.pending_cr = byte == b'\r';
+                }
+                _ => self.buffer.push(byte),
+            }
+        }
+        out
+    }
+
+    fn process_line(&mut self, line: &[u8], out: &mut Vec<Parsed>) {
+        let line = if self.at_start {
+            self.at_start = false;
+            line.strip_prefix("\u{FEFF}".as_bytes()).unwrap_or(line)
+        } else {
+            line
+        };
+        let line = String::from_utf8_lossy(line);
+
+        if line.is_empty() {
+            self.dispatch(out);
+            return;
+        }
+        if line.starts_with(':') {
+            return;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line.as_ref(), ""),
+        };
+        match field {
+            "event" => self.name = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => self.last_event_id = value.to_string(),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                if let Ok(ms) = value.parse() {
+                    out.push(Parsed::Retry(ms));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn dispatch(&mut self, out: &mut Vec<Parsed>) {
+        let name = std::mem::take(&mut self.name);
+        let mut data = std::mem::take(&mut self.data);
+        if data.is_empty() {
+            return;
+        }
+        data.pop();
+        out.push(Parsed::Event(SseEvent::Message {
+            name: if name.is_empty() {
+                "message".to_string()
+            } else {
+                name
+            },
+            data,
+            last_event_id: self.last_event_id.clone(),
+        }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 解析结果简化为 (事件名, 数据, last_event_id)，retry 记为 ("retry", 毫秒, "")
+    fn parse(parser: &mut EventParser, chunks: &[&[u8]]) -> Vec<(String, String, String)> {
+        chunks
+            .iter()
+            .flat_map(|chunk| parser.feed(chunk))
+            .map(|parsed| match parsed {
+                Parsed::Retry(ms) => ("retry".to_string(), ms.to_string(), String::new()),
+                Parsed::Event(SseEvent::Message {
+                    name,
+                    data,
+                    last_event_id,
+                }) => (name, data, last_event_id),
+                Parsed::Event(other) => panic!("unexpected event: {:?}", other),
+            })
+            .collect()
+    }
+
+    fn event(name: &str, data: &str, id: &str) -> (String, String, String) {
+        (name.to_string(), data.to_string(), id.to_string())
+    }
+
+    #[test]
+    fn test_data_and_event_fields() {
+        let mut parser = EventParser::new(String::new());
+        let events = parse(
+            &mut parser,
+            &[b"data: first\n\nevent: update\ndata: line 1\ndata:line 2\n\n"],
+        );
+        assert_eq!(
+            events,
+            vec![
+                event("message", "first", ""),
+                event("update", "line 1\nline 2", ""),
+            ]
+        );
+
+        // 没有 data 的事件不派发，事件名也不会带到下一条
+        let events = parse(&mut parser, &[b"event: ignored\n\ndata\n\n"]);
+        assert_eq!(events, vec![event("message", "", "")]);
+        // 行尾没有空行时事件尚未结束
+        assert!(parse(&mut parser, &[b"data: pending\n"]).is_empty());
+    }
+
+    #[test]
+    fn test_id_field() {
+        let mut parser = EventParser::new("resume-1".to_string());
+        let events = parse(
+            &mut parser,
+            &[b"data: a\n\nid: 7\ndata: b\n\ndata: c\n\nid: x\0y\ndata: d\n\nid\ndata: e\n\n"],
+        );
+        assert_eq!(
+            events,
+            vec![
+                // 断线重连时由页面传入的 Last-Event-ID
+                event("message", "a", "resume-1"),
+                event("message", "b", "7"),
+                // id 一直沿用到下一次设置
+                event("message", "c", "7"),
+                // 含 NUL 的 id 被忽略
+                event("message", "d", "7"),
+                // 空 id 清空
+                event("message", "e", ""),
+            ]
+        );
+
+        // 重连后丢弃未完成的事件，但保留 last_event_id
+        let mut parser = EventParser::new(String::new());
+        parse(&mut parser, &[b"id: 9\n\nevent: half\ndata: lost"]);
+        parser.reset_pending();
+        assert_eq!(
+            parse(&mut parser, &[b"data: fresh\n\n"]),
+            vec![event("message", "fresh", "9")]
+        );
+    }
+
+    #[test]
+    fn test_retry_field() {
+        let mut parser = EventParser::new(String::new());
+        let events = parse(
+            &mut parser,
+            &[b"retry: 5000\nretry: 5s\nretry:\nretry: -1\nretry:250\n"],
+        );
+        assert_eq!(
+            events,
+            vec![event("retry", "5000", ""), event("retry", "250", "")]
+        );
+    }
+
+    #[test]
+    fn test_comments_and_unknown_fields() {
+        let mut parser = EventParser::new(String::new());
+        let events = parse(
+            &mut parser,
+            &[b": keepalive\n\n:\ndata: kept\nfoo: bar\n: inside\n\n"],
+        );
+        assert_eq!(events, vec![event("message", "kept", "")]);
+    }
+
+    #[test]
+    fn test_bom_and_line_endings() {
+        // 只去掉流开头的 BOM，且 BOM 可以跨块到达
+        let mut parser = EventParser::new(String::new());
+        let events = parse(
+            &mut parser,
+            &[b"\xEF\xBB", b"\xBFdata: a\r\n\r\n\xEF\xBB\xBFdata: b\n\n"],
+        );
+        assert_eq!(events, vec![event("message", "a", "")]);
+
+        // CR、LF、CRLF 都是行结束符；CRLF 被拆到两个块里也只算一次
+        let mut parser = EventParser::new(String::new());
+        let events = parse(
+            &mut parser,
+            &[b"data: a\r", b"\ndata: b\r\r", b"data: c\n\r\n"],
+        );
+        assert_eq!(
+            events,
+            vec![event("message", "a\nb", ""), event("message", "c", "")]
+        );
+
+        // UTF-8 多字节字符跨块也能正确解码
+        let text = "data: 你好\n\n".as_bytes();
+        let mut parser = EventParser::new(String::new());
+        let events = parse(&mut parser, &[&text[..8], &text[8..]]);
+        assert_eq!(events, vec![event("message", "你好", "")]);
+    }
+}
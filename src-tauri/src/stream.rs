@@ -0,0 +1,284 @@
+/// 流式代理模块
+/// 响应头立即返回给前端，响应体按块通过 Tauri Channel 以原始字节推送（见 stream_body），
+/// 避免大文件（Excel 导出、图片等）整体缓冲 + base64 编码导致的内存膨胀和界面卡死
+use crate::cache_policy::{forces_revalidation, is_storable};
+use crate::cancel::run_cancellable;
+use crate::dispatch::{build_debug_info, ProxyDebugInfo, SentRequest};
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::http_cache::{CachedResponse, CACHE_STATUS_HEADER};
+use crate::proxy::{collect_response_headers, is_internal_url, AppState, ProxyRequest};
+use crate::redirect::{send_following_redirects, Followed, RedirectHop};
+use crate::resilience::EffectiveTimeouts;
+use crate::stream_body::{pump_body, pump_cached};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tauri::State;
+use tokio::sync::OwnedSemaphorePermit;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 流式响应头（命令返回值）
+#[derive(Debug, Serialize)]
+pub struct ProxyStreamHead {
+    pub status: u16,
+    /// 最终响应的地址（跟随重定向后可能与请求地址不同）
+    pub url: String,
+    pub redirected: bool,
+    /// 经过的重定向，按顺序
+    pub redirect_chain: Vec<RedirectHop>,
+    pub headers: HeaderList,
+    pub content_length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub debug_info: Option<ProxyDebugInfo>,
+}
+
+/// 响应体传输过程中的控制事件
+/// 数据块与事件走两个不同的 Channel，到达顺序不保证一致，
+/// 因此 finished / cancelled 事件携带已推送的块数，前端收齐后再关闭或中止 ReadableStream
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProxyStreamEvent {
+    Progress {
+        chunks: u64,
+        received: u64,
+        total: Option<u64>,
+    },
+    Finished {
+        chunks: u64,
+        received: u64,
+    },
+    /// 前端取消请求；chunks / received 为取消前已推送的数据
+    Cancelled {
+        chunks: u64,
+        received: u64,
+    },
+    Error {
+        chunks: u64,
+        error: AppError,
+    },
+}
+
+/// 流式代理请求：签名、发送后立即返回响应头，响应体在后台任务中逐块推送
+#[tauri::command]
+pub async fn proxy_request_stream(
+    app: tauri::AppHandle,
+    webview: tauri::Webview,
+    mut request: ProxyRequest,
+    on_chunk: Channel<InvokeResponseBody>,
+    on_event: Channel<ProxyStreamEvent>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ProxyStreamHead, AppError> {
+    if is_internal_url(&request.url) {
+        return Err(AppError::InvalidRequest(
+            "Internal IPC request, skipping".to_string(),
+        ));
+    }
+
+    request.webview = Some(webview.label().to_string());
+    log!("🌊 [PROXY STREAM] {} {}", request.method, request.url);
+
+    // 可缓存的 GET 先查本地缓存：新鲜副本直接从磁盘推送，过期副本带条件头重新验证
+    let cache_key = state.cache.key_for(&request, &state.cookies);
+    let cached = match &cache_key {
+        Some(key) => state.cache.lookup(&app, key, &request).await,
+        None => None,
+    };
+    let cached = match cached {
+        Some(entry) if entry.is_fresh() && !forces_revalidation(&request) => {
+            log!("💾 [PROXY STREAM] 缓存命中");
+            return Ok(send_cached(&request, entry, "HIT", on_chunk, on_event));
+        }
+        other => other,
+    };
+    let conditional = cached
+        .as_ref()
+        .map(|entry| request.with_headers(entry.conditional_headers()));
+    let outgoing = conditional.as_ref().unwrap_or(&request);
+
+    // 取消信号同时覆盖“等待响应头”和后台推送响应体两个阶段
+    let guard = request.id.as_deref().map(|id| state.inflight.register(id));
+    let token = guard.as_ref().map(|g| g.token());
+
+    // 总超时只约束到收到响应头；响应体可能很大（Excel 导出等），只受数据块间隔限制
+    let timeouts = state.policy.effective(request.timeouts.as_ref());
+    let deadline = Instant::now() + timeouts.total;
+
+    let result = run_cancellable(
+        token.clone(),
+        open_stream(&app, &state, outgoing, timeouts, deadline),
+    )
+    .await;
+
+    if let Some(parts) = &request.parts {
+        state.uploads.release_parts(parts).await;
+    }
+
+    let (resp, permit, mut head) = result?;
+    let total = head.content_length;
+
+    if head.status == 304 && !head.redirected {
+        if let Some(entry) = cached {
+            log!("💾 [PROXY STREAM] 缓存重新验证通过 (304)");
+            let entry = state.cache.revalidated(&app, entry, &head.headers).await;
+            return Ok(send_cached(
+                &request,
+                entry,
+                "REVALIDATED",
+                on_chunk,
+                on_event,
+            ));
+        }
+    }
+
+    // 可缓存的响应在推送的同时保留一份副本，完整读完后写入缓存；
+    // 重定向后的响应不缓存（缓存命中时无法还原重定向链）
+    let max_entry = state.cache.max_entry_bytes();
+    let sink = cache_key
+        .filter(|_| !head.redirected)
+        .filter(|_| is_storable(head.status, &head.headers))
+        .filter(|_| total.is_none_or(|len| len <= max_entry))
+        .map(|key| CacheSink {
+            key,
+            request: request.clone(),
+            status: head.status,
+            headers: head.headers.clone(),
+        });
+    if sink.is_some() {
+        head.headers.set(CACHE_STATUS_HEADER, "MISS");
+    }
+    let capture_limit = sink.as_ref().map(|_| max_entry);
+
+    let state = Arc::clone(state.inner());
+    tauri::async_runtime::spawn(async move {
+        let _permit = permit;
+        let _guard = guard;
+        let captured = pump_body(
+            resp,
+            timeouts.read,
+            capture_limit,
+            token,
+            on_chunk,
+            on_event,
+        )
+        .await;
+
+        if let (Some(sink), Some(body)) = (sink, captured) {
+            state
+                .cache
+                .store(
+                    &app,
+                    &sink.key,
+                    &sink.request,
+                    sink.status,
+                    &sink.headers,
+                    &body,
+                )
+                .await;
+        }
+    });
+
+    Ok(head)
+}
+
+/// 待写入缓存的响应信息
+struct CacheSink {
+    key: String,
+    request: ProxyRequest,
+    status: u16,
+    headers: HeaderList,
+}
+
+/// 从缓存条目构造响应头，并在后台任务中分块推送响应体
+fn send_cached(
+    request: &ProxyRequest,
+    entry: CachedResponse,
+    cache_status: &str,
+    on_chunk: Channel<InvokeResponseBody>,
+    on_event: Channel<ProxyStreamEvent>,
+) -> ProxyStreamHead {
+    let head = ProxyStreamHead {
+        status: entry.status(),
+        url: request.url.clone(),
+        redirected: false,
+        redirect_chain: Vec::new(),
+        headers: entry.headers(cache_status),
+        content_length: Some(entry.body.len() as u64),
+        debug_info: None,
+    };
+
+    tauri::async_runtime::spawn(pump_cached(entry.body, on_chunk, on_event));
+
+    head
+}
+
+/// 发送请求并等待响应头；返回响应、并发许可（随响应体一起释放）和响应头信息
+async fn open_stream(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    request: &ProxyRequest,
+    timeouts: EffectiveTimeouts,
+    deadline: Instant,
+) -> Result<(reqwest::Response, OwnedSemaphorePermit, ProxyStreamHead), AppError> {
+    let Followed {
+        sent:
+            SentRequest {
+                response: resp,
+                permit,
+                signed_headers,
+            },
+        request: final_request,
+        chain,
+    } = send_following_redirects(app, state, request, timeouts, deadline)
+        .await
+        .map_err(|e| {
+            log!("❌ [PROXY STREAM] 请求失败: {}", e);
+            e
+        })?;
+
+    let status = resp.status().as_u16();
+    let headers = collect_response_headers(&resp);
+    let content_length = resp.content_length();
+    let debug_info = build_debug_info(&final_request, &signed_headers, status, &headers);
+
+    log!(
+        "🌊 [PROXY STREAM] 响应状态: {}, 预估大小: {:?} bytes",
+        status,
+        content_length
+    );
+
+    Ok((
+        resp,
+        permit,
+        ProxyStreamHead {
+            status,
+            redirected: !chain.is_empty(),
+            url: final_request.url,
+            redirect_chain: chain,
+            headers,
+            content_length,
+            debug_info,
+        },
+    ))
+}
@@ -0,0 +1,156 @@
+/// 流式响应体推送
+/// 把响应体（网络或缓存副本）逐块写入前端 Channel，并按间隔发出进度事件；
+/// 结束、取消或出错时发出带已推送块数的控制事件（见 stream::ProxyStreamEvent）
+use futures_util::StreamExt;
+use std::time::{Duration, Instant};
+use tauri::ipc::{Channel, InvokeResponseBody};
+use tokio_util::sync::CancellationToken;
+
+use crate::cancel::cancelled;
+use crate::error::AppError;
+use crate::stream::ProxyStreamEvent;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 进度事件的最小推送间隔，避免事件风暴
+const PROGRESS_INTERVAL_MS: u128 = 200;
+
+/// 从缓存推送响应体时的分块大小
+const CACHED_CHUNK_SIZE: usize = 64 * 1024;
+
+/// 分块推送缓存中的响应体；前端 Channel 失效时提前结束
+pub(crate) async fn pump_cached(
+    body: Vec<u8>,
+    on_chunk: Channel<InvokeResponseBody>,
+    on_event: Channel<ProxyStreamEvent>,
+) {
+    let mut chunks: u64 = 0;
+    for chunk in body.chunks(CACHED_CHUNK_SIZE) {
+        if on_chunk
+            .send(InvokeResponseBody::Raw(chunk.to_vec()))
+            .is_err()
+        {
+            return;
+        }
+        chunks += 1;
+    }
+    let _ = on_event.send(ProxyStreamEvent::Finished {
+        chunks,
+        received: body.len() as u64,
+    });
+}
+
+/// 逐块读取响应体并推送到前端；前端 Channel 失效（页面关闭/跳转）时提前结束，
+/// 数据块间隔超过 read_timeout 时以超时错误结束（不受总超时限制，大文件可以传很久）。
+/// 请求被取消时发出带已推送块数的 cancelled 事件后结束。
+/// 指定 capture_limit 时同时保留响应体副本，完整读完且未超限才返回
+pub(crate) async fn pump_body(
+    resp: reqwest::Response,
+    read_timeout: Duration,
+    capture_limit: Option<u64>,
+    token: Option<CancellationToken>,
+    on_chunk: Channel<InvokeResponseBody>,
+    on_event: Channel<ProxyStreamEvent>,
+) -> Option<Vec<u8>> {
+    let total = resp.content_length();
+    let mut captured = capture_limit.map(|_| Vec::new());
+    let mut stream = resp.bytes_stream();
+    let mut chunks: u64 = 0;
+    let mut received: u64 = 0;
+    let mut last_emit = Instant::now();
+
+    loop {
+        let next = tokio::select! {
+            _ = cancelled(token.as_ref()) => {
+                log!("🛑 [PROXY STREAM] 已取消: {} bytes / {} chunks", received, chunks);
+                let _ = on_event.send(ProxyStreamEvent::Cancelled { chunks, received });
+                return None;
+            }
+            next = tokio::time::timeout(read_timeout, stream.next()) => next,
+        };
+        let item = match next {
+            Ok(Some(item)) => item,
+            Ok(None) => break,
+            Err(_) => {
+                log!(
+                    "⏱️  [PROXY STREAM] 读取响应体超时: {} ms",
+                    read_timeout.as_millis()
+                );
+                let _ = on_event.send(ProxyStreamEvent::Error {
+                    chunks,
+                    error: AppError::Timeout(format!(
+                        "读取响应体时 {} ms 内未收到数据",
+                        read_timeout.as_millis()
+                    )),
+                });
+                return None;
+            }
+        };
+        let bytes = match item {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                log!("❌ [PROXY STREAM] 读取响应体失败: {}", e);
+                let _ = on_event.send(ProxyStreamEvent::Error {
+                    chunks,
+                    error: e.into(),
+                });
+                return None;
+            }
+        };
+
+        if bytes.is_empty() {
+            continue;
+        }
+
+        received += bytes.len() as u64;
+        if capture_limit.is_some_and(|limit| received > limit) {
+            captured = None;
+        } else if let Some(buffer) = captured.as_mut() {
+            buffer.extend_from_slice(&bytes);
+        }
+        if on_chunk
+            .send(InvokeResponseBody::Raw(bytes.to_vec()))
+            .is_err()
+        {
+            log!("⚠️  [PROXY STREAM] 前端 Channel 已关闭，停止推送");
+            return None;
+        }
+        chunks += 1;
+
+        if last_emit.elapsed().as_millis() >= PROGRESS_INTERVAL_MS {
+            let _ = on_event.send(ProxyStreamEvent::Progress {
+                chunks,
+                received,
+                total,
+            });
+            last_emit = Instant::now();
+        }
+    }
+
+    log!(
+        "✅ [PROXY STREAM] 完成: {} bytes / {} chunks",
+        received,
+        chunks
+    );
+    let _ = on_event.send(ProxyStreamEvent::Finished { chunks, received });
+    captured
+}
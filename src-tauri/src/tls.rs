@@ -0,0 +1,463 @@
+/// TLS 信任与证书固定
+/// 测试/UAT 环境的后端证书由内部 CA 签发；生产环境要求固定后端公钥，防止中间人设备读到
+/// proxy_request 附加的签名头。按环境在构建时配置：
+/// - TAURI_TLS_CA_BUNDLE：额外信任的根证书（PEM，构建时嵌入），与系统根证书一起生效
+/// - TAURI_TLS_PINS：环境来源（TAURI_ENV_URL 的主机）的 SPKI 固定值，逗号分隔，格式 sha256/<base64>
+///
+/// 配置任一项后 reqwest 客户端改用 rustls，由这里的校验器在发送任何请求头之前检查证书；
+/// WebSocket 仍走 native-tls，在握手前按同一规则检查服务端证书。
+/// 固定值不匹配时连接中止，返回 AppError::PinMismatch 并发出 tls-pin-mismatch 事件
+/// 客户端证书（mTLS）由 client_identity 管理，生效时同样改用 rustls 出示
+use base64::{engine::general_purpose, Engine as _};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{CertificateError, ClientConfig, RootCertStore, ServerName};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::SystemTime;
+use tokio::net::TcpStream;
+use tokio_native_tls::TlsStream;
+use x509_cert::der::{Decode, Encode};
+
+use crate::client_identity::ClientIdentity;
+use crate::error::AppError;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// build.rs 合并的 TAURI_TLS_CA_BUNDLE，未配置时为空
+const CA_BUNDLE: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/tls_ca_bundle.pem"));
+
+/// 固定值不匹配时发给前端的事件
+pub const PIN_MISMATCH_EVENT: &str = "tls-pin-mismatch";
+
+/// 证书固定校验失败的详情（同时作为事件负载）
+#[derive(Debug, Clone, Serialize)]
+pub struct PinMismatch {
+    pub host: String,
+    /// 服务端证书公钥的固定值；证书无法解析时为空
+    pub actual: String,
+    pub expected: Vec<String>,
+}
+
+impl fmt::Display for PinMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} 的证书公钥 {} 不在固定列表中",
+            self.host,
+            if self.actual.is_empty() { "<无法解析>" } else { &self.actual }
+        )
+    }
+}
+
+impl std::error::Error for PinMismatch {}
+
+type MismatchListener = Box<dyn Fn(&PinMismatch) + Send + Sync>;
+
+pub struct TlsPolicy {
+    /// 额外信任的根证书（DER）
+    extra_roots: Vec<Vec<u8>>,
+    /// 固定公钥的主机（环境来源）
+    pinned_host: Option<String>,
+    pins: Vec<String>,
+    listener: OnceLock<MismatchListener>,
+}
+
+impl TlsPolicy {
+    pub fn from_env(env_url: &str) -> Self {
+        let extra_roots = rustls_pemfile::certs(&mut &CA_BUNDLE[..]).unwrap_or_else(|e| {
+            log!("⚠️  [TLS] TAURI_TLS_CA_BUNDLE 解析失败，忽略: {}", e);
+            Vec::new()
+        });
+        Self::new(
+            env_url,
+            extra_roots,
+            option_env!("TAURI_TLS_PINS").unwrap_or_default(),
+        )
+    }
+
+    /// pins 为逗号分隔的固定值
+    fn new(env_url: &str, extra_roots: Vec<Vec<u8>>, pins: &str) -> Self {
+        let pins: Vec<String> = pins.split(',').filter_map(normalize_pin).collect();
+        let pinned_host = reqwest::Url::parse(env_url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_ascii_lowercase));
+
+        if !extra_roots.is_empty() {
+            log!("🔐 [TLS] 额外信任 {} 个根证书", extra_roots.len());
+        }
+        if !pins.is_empty() {
+            log!("📌 [TLS] {:?} 固定 {} 个公钥", pinned_host, pins.len());
+        }
+        Self {
+            extra_roots,
+            pinned_host,
+            pins,
+            listener: OnceLock::new(),
+        }
+    }
+
+    /// 是否需要替换默认的 TLS 配置
+    pub fn is_customized(&self) -> bool {
+        !self.extra_roots.is_empty() || !self.pins.is_empty()
+    }
+
+    /// 注册固定值不匹配的回调（用于通知前端）
+    pub fn set_listener(&self, listener: impl Fn(&PinMismatch) + Send + Sync + 'static) {
+        let _ = self.listener.set(Box::new(listener));
+    }
+
+    /// 按固定列表检查服务端证书；非固定主机直接通过
+    pub fn check_pin(&self, host: &str, cert_der: &[u8]) -> Result<(), PinMismatch> {
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+        if self.pins.is_empty() || self.pinned_host.as_deref() != Some(host.as_str()) {
+            return Ok(());
+        }
+        let actual = spki_pin(cert_der).unwrap_or_default();
+        if !actual.is_empty() && self.pins.contains(&actual) {
+            return Ok(());
+        }
+
+        let mismatch = PinMismatch {
+            host,
+            actual,
+            expected: self.pins.clone(),
+        };
+        log!("🚨 [TLS] 证书固定不匹配: {}", mismatch);
+        if let Some(listener) = self.listener.get() {
+            listener(&mismatch);
+        }
+        Err(mismatch)
+    }
+
+    /// reqwest 使用的 rustls 配置：系统根证书 + 额外根证书，并校验固定值；有客户端证书时一并出示
+    pub fn rustls_config(
+        self: &Arc<Self>,
+        identity: Option<&ClientIdentity>,
+    ) -> Result<ClientConfig, AppError> {
+        let mut roots = RootCertStore::empty();
+        match rustls_native_certs::load_native_certs() {
+            Ok(certs) => {
+                for cert in certs {
+                    let _ = roots.add(&rustls::Certificate(cert.0));
+                }
+            }
+            Err(e) => log!("⚠️  [TLS] 读取系统根证书失败: {}", e),
+        }
+        for der in &self.extra_roots {
+            roots
+                .add(&rustls::Certificate(der.clone()))
+                .map_err(|e| AppError::Tls(format!("无效的根证书: {}", e)))?;
+        }
+
+        let verifier = PinningVerifier {
+            inner: WebPkiVerifier::new(roots, None),
+            policy: Arc::clone(self),
+        };
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_custom_certificate_verifier(Arc::new(verifier));
+        let mut config = match identity {
+            Some(identity) => builder
+                .with_client_auth_cert(
+                    identity.chain().iter().cloned().map(rustls::Certificate).collect(),
+                    rustls::PrivateKey(identity.key().to_vec()),
+                )
+                .map_err(|e| AppError::Tls(format!("客户端证书不可用: {}", e)))?,
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(config)
+    }
+
+    /// WebSocket 的 TLS 握手（native-tls），握手完成后、发送任何数据之前检查固定值
+    pub async fn connect_native(
+        &self,
+        host: &str,
+        tcp: TcpStream,
+        identity: Option<&ClientIdentity>,
+    ) -> Result<TlsStream<TcpStream>, AppError> {
+        let connector = tokio_native_tls::TlsConnector::from(self.native_connector(identity)?);
+        let stream = connector
+            .connect(host, tcp)
+            .await
+            .map_err(|e| AppError::Tls(e.to_string()))?;
+        let cert = stream
+            .get_ref()
+            .peer_certificate()
+            .ok()
+            .flatten()
+            .and_then(|cert| cert.to_der().ok())
+            .unwrap_or_default();
+        self.check_pin(host, &cert)?;
+        Ok(stream)
+    }
+
+    /// WebSocket 使用的 native-tls 连接器
+    fn native_connector(
+        &self,
+        identity: Option<&ClientIdentity>,
+    ) -> Result<native_tls::TlsConnector, AppError> {
+        let mut builder = native_tls::TlsConnector::builder();
+        if let Some(identity) = identity {
+            let (chain, key) = identity.to_pem();
+            let identity = native_tls::Identity::from_pkcs8(chain.as_bytes(), key.as_bytes())
+                .map_err(|e| AppError::Tls(format!("客户端证书不可用: {}", e)))?;
+            builder.identity(identity);
+        }
+        for der in &self.extra_roots {
+            let cert = native_tls::Certificate::from_der(der)
+                .map_err(|e| AppError::Tls(format!("无效的根证书: {}", e)))?;
+            builder.add_root_certificate(cert);
+        }
+        builder.build().map_err(|e| AppError::Tls(e.to_string()))
+    }
+}
+
+/// 统一为 sha256/<base64>；允许省略前缀
+fn normalize_pin(pin: &str) -> Option<String> {
+    let pin = pin.trim();
+    if pin.is_empty() {
+        return None;
+    }
+    let digest = pin.strip_prefix("sha256/").unwrap_or(pin);
+    Some(format!("sha256/{}", digest))
+}
+
+/// 证书 SubjectPublicKeyInfo 的 SHA-256（与 HPKP / curl --pinnedpubkey 相同的格式）
+fn spki_pin(cert_der: &[u8]) -> Option<String> {
+    let cert = x509_cert::Certificate::from_der(cert_der).ok()?;
+    let spki = cert.tbs_certificate.subject_public_key_info.to_der().ok()?;
+    Some(format!(
+        "sha256/{}",
+        general_purpose::STANDARD.encode(Sha256::digest(&spki))
+    ))
+}
+
+/// 先按 WebPKI 校验证书链，再检查固定值
+struct PinningVerifier {
+    inner: WebPkiVerifier,
+    policy: Arc<TlsPolicy>,
+}
+
+impl ServerCertVerifier for PinningVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        intermediates: &[rustls::Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        let host = match server_name {
+            ServerName::DnsName(name) => name.as_ref().to_string(),
+            ServerName::IpAddress(ip) => ip.to_string(),
+            _ => return Ok(verified),
+        };
+        self.policy.check_pin(&host, &end_entity.0).map_err(|mismatch| {
+            rustls::Error::InvalidCertificate(CertificateError::Other(Arc::new(mismatch)))
+        })?;
+        Ok(verified)
+    }
+}
+
+/// 在错误链中查找证书固定不匹配（rustls 错误被包在 io::Error 里，需要逐层展开）
+pub fn find_pin_mismatch(error: &(dyn std::error::Error + 'static)) -> Option<PinMismatch> {
+    let mut source = Some(error);
+    while let Some(cause) = source {
+        if let Some(mismatch) = cause.downcast_ref::<PinMismatch>() {
+            return Some(mismatch.clone());
+        }
+        if let Some(rustls::Error::InvalidCertificate(CertificateError::Other(other))) =
+            cause.downcast_ref::<rustls::Error>()
+        {
+            if let Some(mismatch) = other.downcast_ref::<PinMismatch>() {
+                return Some(mismatch.clone());
+            }
+        }
+        if let Some(inner) = cause
+            .downcast_ref::<std::io::Error>()
+            .and_then(|io| io.get_ref())
+        {
+            if let Some(mismatch) = find_pin_mismatch(inner) {
+                return Some(mismatch);
+            }
+        }
+        source = cause.source();
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    const HOST: &str = "localhost";
+
+    /// rcgen 生成的测试 CA 与它签发的服务端证书
+    struct TestPki {
+        ca_der: Vec<u8>,
+        leaf_der: Vec<u8>,
+        leaf_pem: String,
+        key_pem: String,
+    }
+
+    fn pki() -> TestPki {
+        let mut ca_params = rcgen::CertificateParams::new(Vec::new());
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "test ca");
+        let ca = rcgen::Certificate::from_params(ca_params).unwrap();
+
+        let mut leaf_params = rcgen::CertificateParams::new(vec![HOST.to_string()]);
+        leaf_params.extended_key_usages = vec![rcgen::ExtendedKeyUsagePurpose::ServerAuth];
+        let leaf = rcgen::Certificate::from_params(leaf_params).unwrap();
+        TestPki {
+            ca_der: ca.serialize_der().unwrap(),
+            leaf_der: leaf.serialize_der_with_signer(&ca).unwrap(),
+            leaf_pem: leaf.serialize_pem_with_signer(&ca).unwrap(),
+            key_pem: leaf.serialize_private_key_pem(),
+        }
+    }
+
+    fn policy(pki: &TestPki, pins: &str) -> Arc<TlsPolicy> {
+        Arc::new(TlsPolicy::new(
+            &format!("https://{}/base_api", HOST),
+            vec![pki.ca_der.clone()],
+            pins,
+        ))
+    }
+
+    fn verify(policy: &Arc<TlsPolicy>, pki: &TestPki) -> Result<ServerCertVerified, rustls::Error> {
+        let mut roots = RootCertStore::empty();
+        roots.add(&rustls::Certificate(pki.ca_der.clone())).unwrap();
+        let verifier = PinningVerifier {
+            inner: WebPkiVerifier::new(roots, None),
+            policy: Arc::clone(policy),
+        };
+        verifier.verify_server_cert(
+            &rustls::Certificate(pki.leaf_der.clone()),
+            &[],
+            &ServerName::try_from(HOST).unwrap(),
+            &mut std::iter::empty(),
+            &[],
+            SystemTime::now(),
+        )
+    }
+
+    #[test]
+    fn test_normalize_pin() {
+        assert_eq!(
+            normalize_pin(" sha256/abc= "),
+            Some("sha256/abc=".to_string())
+        );
+        assert_eq!(normalize_pin("abc="), Some("sha256/abc=".to_string()));
+        assert_eq!(normalize_pin("  "), None);
+
+        // 带或不带前缀的配置都能匹配同一个证书
+        let pki = pki();
+        let pin = spki_pin(&pki.leaf_der).unwrap();
+        let bare = pin.strip_prefix("sha256/").unwrap();
+        for configured in [pin.as_str(), bare] {
+            assert!(policy(&pki, configured)
+                .check_pin(HOST, &pki.leaf_der)
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn test_matching_pin_accepted() {
+        let pki = pki();
+        let pin = spki_pin(&pki.leaf_der).unwrap();
+        let policy = policy(&pki, &format!("sha256/unrelated=, {}", pin));
+        assert!(verify(&policy, &pki).is_ok());
+    }
+
+    #[test]
+    fn test_mismatched_pin_rejected() {
+        let pki = pki();
+        let policy = policy(&pki, "sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=");
+        let notified = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&notified);
+        policy.set_listener(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        });
+
+        let error = verify(&policy, &pki).unwrap_err();
+        assert_eq!(notified.load(Ordering::Relaxed), 1);
+        // rustls 错误被包在 io::Error 里时也能找到不匹配详情
+        let wrapped = std::io::Error::new(std::io::ErrorKind::InvalidData, error);
+        let mismatch = find_pin_mismatch(&wrapped).expect("应识别为固定不匹配");
+        assert_eq!(mismatch.host, HOST);
+        assert_eq!(mismatch.actual, spki_pin(&pki.leaf_der).unwrap());
+
+        // 非固定主机不检查
+        assert!(policy.check_pin("other.example.com", &pki.leaf_der).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_native_tls_rejects_mismatched_pin() {
+        let pki = pki();
+        let identity =
+            native_tls::Identity::from_pkcs8(pki.leaf_pem.as_bytes(), pki.key_pem.as_bytes())
+                .unwrap();
+        let acceptor =
+            tokio_native_tls::TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = acceptor.accept(socket).await;
+                });
+            }
+        });
+
+        let pin = spki_pin(&pki.leaf_der).unwrap();
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        assert!(policy(&pki, &pin)
+            .connect_native(HOST, tcp, None)
+            .await
+            .is_ok());
+
+        let tcp = TcpStream::connect(addr).await.unwrap();
+        let error = policy(&pki, "sha256/AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=")
+            .connect_native(HOST, tcp, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::PinMismatch(_)), "{:?}", error);
+    }
+}
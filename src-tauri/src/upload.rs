@@ -0,0 +1,251 @@
+/// 文件上传模块
+/// 文件内容只能来自两种来源：小文件的 base64 内容，或分块上传句柄
+/// （前端把 File 切块写入临时文件，代理请求只引用句柄 ID）。不接受本地路径，
+/// 页面无法借代理读取任意本地文件。
+/// 句柄归创建它的 webview 所有，其他窗口不能写入或引用；创建后长时间未被请求使用的句柄
+/// 会在下次创建句柄时清理。请求体的构建见 multipart 模块
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::State;
+use tokio::io::AsyncWriteExt;
+
+use crate::error::AppError;
+use crate::proxy::AppState;
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 分块上传时携带句柄 ID 的请求头
+const UPLOAD_ID_HEADER: &str = "x-upload-id";
+
+/// 句柄最后一次写入后，超过这个时间仍未被请求使用即视为遗弃
+const UPLOAD_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// multipart 表单中的一项，按数组顺序写入请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FormPart {
+    Text {
+        name: String,
+        value: String,
+    },
+    File {
+        name: String,
+        file_name: String,
+        content_type: String,
+        #[serde(flatten)]
+        source: FileSource,
+    },
+}
+
+/// 文件内容来源
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSource {
+    /// base64 编码的内容（兼容旧调用方式，仅适合小文件）
+    Data(String),
+    /// upload_begin 返回的分块上传句柄
+    Upload(String),
+}
+
+struct UploadHandle {
+    path: PathBuf,
+    /// 创建句柄的 webview
+    owner: String,
+    touched: Instant,
+    /// 已被请求引用；请求结束时由 release_parts 释放，不参与过期清理
+    leased: bool,
+}
+
+/// 分块上传句柄 → 临时文件
+#[derive(Default)]
+pub struct UploadStore {
+    handles: Mutex<HashMap<String, UploadHandle>>,
+}
+
+impl UploadStore {
+    fn checkout(&self, upload_id: &str, owner: &str, lease: bool) -> Result<PathBuf, AppError> {
+        let mut handles = self
+            .handles
+            .lock()
+            .map_err(|_| AppError::Internal("Upload store poisoned".to_string()))?;
+        let handle = handles
+            .get_mut(upload_id)
+            .filter(|handle| handle.owner == owner)
+            .ok_or_else(|| AppError::InvalidRequest(format!("上传句柄不存在: {}", upload_id)))?;
+        handle.touched = Instant::now();
+        handle.leased |= lease;
+        Ok(handle.path.clone())
+    }
+
+    /// 由请求引用句柄：只能引用同一 webview 创建的句柄，引用后不再过期
+    pub(crate) fn lease(&self, upload_id: &str, owner: &str) -> Result<PathBuf, AppError> {
+        self.checkout(upload_id, owner, true)
+    }
+
+    fn insert(&self, upload_id: String, path: PathBuf, owner: String) -> Result<(), AppError> {
+        self.handles
+            .lock()
+            .map_err(|_| AppError::Internal("Upload store poisoned".to_string()))?
+            .insert(
+                upload_id,
+                UploadHandle {
+                    path,
+                    owner,
+                    touched: Instant::now(),
+                    leased: false,
+                },
+            );
+        Ok(())
+    }
+
+    /// 移除句柄并删除临时文件
+    pub async fn release(&self, upload_id: &str) {
+        let handle = match self.handles.lock() {
+            Ok(mut handles) => handles.remove(upload_id),
+            Err(_) => None,
+        };
+        if let Some(handle) = handle {
+            let _ = tokio::fs::remove_file(&handle.path).await;
+        }
+    }
+
+    /// 请求结束后释放其引用的所有上传句柄
+    pub async fn release_parts(&self, parts: &[FormPart]) {
+        for part in parts {
+            if let FormPart::File {
+                source: FileSource::Upload(upload_id),
+                ..
+            } = part
+            {
+                self.release(upload_id).await;
+            }
+        }
+    }
+
+    /// 删除超过 UPLOAD_TTL 未被使用的句柄（前端上传到一半页面关闭等情况）
+    async fn sweep(&self) {
+        let expired: Vec<PathBuf> = match self.handles.lock() {
+            Ok(mut handles) => {
+                let now = Instant::now();
+                let ids: Vec<String> = handles
+                    .iter()
+                    .filter(|(_, handle)| {
+                        !handle.leased && now.duration_since(handle.touched) > UPLOAD_TTL
+                    })
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                ids.iter()
+                    .filter_map(|id| handles.remove(id))
+                    .map(|handle| handle.path)
+                    .collect()
+            }
+            Err(_) => Vec::new(),
+        };
+        if !expired.is_empty() {
+            log!("🧹 [Upload] 清理 {} 个过期句柄", expired.len());
+        }
+        for path in expired {
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+    }
+}
+
+fn upload_dir() -> PathBuf {
+    std::env::temp_dir().join("backstage68-uploads")
+}
+
+/// 创建分块上传句柄
+#[tauri::command]
+pub async fn upload_begin(
+    webview: tauri::Webview,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, AppError> {
+    state.uploads.sweep().await;
+
+    let dir = upload_dir();
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| AppError::Io(format!("创建上传临时目录失败: {}", e)))?;
+
+    let upload_id = uuid::Uuid::new_v4().to_string();
+    let path = dir.join(format!("{}.part", upload_id));
+    tokio::fs::File::create(&path)
+        .await
+        .map_err(|e| AppError::Io(format!("创建上传临时文件失败: {}", e)))?;
+
+    state
+        .uploads
+        .insert(upload_id.clone(), path, webview.label().to_string())?;
+    log!("📤 [Upload] 创建句柄: {}", upload_id);
+    Ok(upload_id)
+}
+
+/// 追加一个数据块：请求体为原始字节，句柄 ID 放在 X-Upload-Id 头中
+#[tauri::command]
+pub async fn upload_chunk(
+    webview: tauri::Webview,
+    request: tauri::ipc::Request<'_>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<u64, AppError> {
+    let upload_id = request
+        .headers()
+        .get(UPLOAD_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .ok_or_else(|| AppError::InvalidRequest("缺少 X-Upload-Id 请求头".to_string()))?
+        .to_string();
+
+    let tauri::ipc::InvokeBody::Raw(data) = request.body() else {
+        return Err(AppError::InvalidRequest(
+            "上传数据块必须是二进制".to_string(),
+        ));
+    };
+
+    let path = state.uploads.checkout(&upload_id, webview.label(), false)?;
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .await
+        .map_err(|e| AppError::Io(format!("打开上传临时文件失败: {}", e)))?;
+    file.write_all(data)
+        .await
+        .map_err(|e| AppError::Io(format!("写入上传临时文件失败: {}", e)))?;
+    file.flush()
+        .await
+        .map_err(|e| AppError::Io(format!("写入上传临时文件失败: {}", e)))?;
+
+    Ok(data.len() as u64)
+}
+
+/// 放弃分块上传（例如用户取消），删除临时文件
+#[tauri::command]
+pub async fn upload_discard(
+    webview: tauri::Webview,
+    upload_id: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    state.uploads.checkout(&upload_id, webview.label(), false)?;
+    state.uploads.release(&upload_id).await;
+    Ok(())
+}
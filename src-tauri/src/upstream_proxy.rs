@@ -0,0 +1,285 @@
+/// 上游代理设置
+/// 部分办公网络只能经 HTTP/SOCKS5 代理出网。这里统一决定每个出站地址走哪个上游代理：
+/// - system：沿用系统设置（reqwest 读取环境变量与系统代理；WebSocket 读取环境变量）
+/// - direct：一律直连
+/// - manual：HTTP / HTTPS / SOCKS5 代理（可带认证）+ no_proxy 排除列表
+/// - pac：下载 PAC 脚本，按 FindProxyForURL 的第一个结果选择（需要 pac 特性）
+///
+/// 设置可在运行时修改（set_network_proxy），修改后重建所有 HTTP 客户端，并加密保存到应用数据目录
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+use crate::error::AppError;
+use crate::pac::PacScript;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProxyMode {
+    #[default]
+    System,
+    Direct,
+    Manual,
+    Pac,
+}
+
+/// 前端可见的代理设置；密码只写不读
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxySettings {
+    pub mode: ProxyMode,
+    /// http 地址使用的代理，如 http://10.0.0.1:8080
+    pub http: Option<String>,
+    /// https 地址使用的代理；未设置时使用 http 代理
+    pub https: Option<String>,
+    /// socks5://host:port 或 socks5h://host:port（由代理解析域名），设置后优先于 http/https
+    pub socks5: Option<String>,
+    pub username: Option<String>,
+    /// 不返回给前端；设置时省略表示沿用已保存的密码，空串表示清除
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    /// 不走代理的地址：域名（含子域名）、.后缀、IP、CIDR 或 *
+    pub no_proxy: Vec<String>,
+    pub pac_url: Option<String>,
+}
+
+impl ProxySettings {
+    /// 编译期默认值：TAURI_PROXY_UPSTREAM / TAURI_PROXY_NO_PROXY / TAURI_PROXY_PAC_URL
+    pub fn from_env() -> Self {
+        let no_proxy = option_env!("TAURI_PROXY_NO_PROXY")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        if let Some(pac_url) = option_env!("TAURI_PROXY_PAC_URL").filter(|v| !v.is_empty()) {
+            return Self {
+                mode: ProxyMode::Pac,
+                pac_url: Some(pac_url.to_string()),
+                no_proxy,
+                ..Self::default()
+            };
+        }
+        match option_env!("TAURI_PROXY_UPSTREAM").filter(|v| !v.is_empty()) {
+            Some(upstream) if upstream.starts_with("socks5") => Self {
+                mode: ProxyMode::Manual,
+                socks5: Some(upstream.to_string()),
+                no_proxy,
+                ..Self::default()
+            },
+            Some(upstream) => Self {
+                mode: ProxyMode::Manual,
+                http: Some(upstream.to_string()),
+                no_proxy,
+                ..Self::default()
+            },
+            None => Self {
+                no_proxy,
+                ..Self::default()
+            },
+        }
+    }
+
+    /// 新设置省略密码时沿用旧密码
+    pub fn inherit_password(mut self, previous: &ProxySettings) -> Self {
+        match &self.password {
+            None => self.password = previous.password.clone(),
+            Some(password) if password.is_empty() => self.password = None,
+            Some(_) => {}
+        }
+        self
+    }
+}
+
+/// 按设置解析目标地址的上游代理；创建后不可变，设置变化时整体替换
+pub struct ProxyResolver {
+    mode: ProxyMode,
+    http: Option<reqwest::Url>,
+    https: Option<reqwest::Url>,
+    socks5: Option<reqwest::Url>,
+    no_proxy: Vec<NoProxyEntry>,
+    pac: Option<PacScript>,
+}
+
+impl ProxyResolver {
+    /// 校验设置并构建；PAC 模式需先下载脚本（见 pac::fetch_pac）
+    pub fn new(settings: &ProxySettings, pac_source: Option<String>) -> Result<Self, AppError> {
+        let credentials = |url: Option<&String>| -> Result<Option<reqwest::Url>, AppError> {
+            let Some(url) = url.filter(|url| !url.trim().is_empty()) else {
+                return Ok(None);
+            };
+            let mut parsed = reqwest::Url::parse(url.trim())
+                .map_err(|e| AppError::InvalidUrl(format!("代理地址 {}: {}", url, e)))?;
+            if parsed.host_str().is_none() {
+                return Err(AppError::InvalidUrl(format!("代理地址缺少主机: {}", url)));
+            }
+            if let Some(username) = settings.username.as_deref().filter(|u| !u.is_empty()) {
+                let _ = parsed.set_username(username);
+                let _ = parsed.set_password(settings.password.as_deref());
+            }
+            Ok(Some(parsed))
+        };
+
+        let socks5 = credentials(settings.socks5.as_ref())?;
+        if let Some(url) = &socks5 {
+            if !matches!(url.scheme(), "socks5" | "socks5h") {
+                return Err(AppError::InvalidUrl(format!("SOCKS 代理须为 socks5:// 或 socks5h://: {}", url)));
+            }
+        }
+        let pac = match (settings.mode, pac_source) {
+            (ProxyMode::Pac, Some(source)) => Some(PacScript::new(source)),
+            (ProxyMode::Pac, None) => {
+                return Err(AppError::InvalidRequest("PAC 模式缺少脚本".to_string()))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            mode: settings.mode,
+            http: credentials(settings.http.as_ref())?,
+            https: credentials(settings.https.as_ref())?,
+            socks5,
+            no_proxy: settings.no_proxy.iter().filter_map(|e| NoProxyEntry::parse(e)).collect(),
+            pac,
+        })
+    }
+
+    pub fn mode(&self) -> ProxyMode {
+        self.mode
+    }
+
+    /// 发送前调用：PAC 模式下在阻塞线程池求值并缓存（dnsResolve 等会阻塞），
+    /// 之后连接器里的 proxy_for 直接命中缓存
+    pub async fn resolve(&self, url: &reqwest::Url) -> Option<reqwest::Url> {
+        match &self.pac {
+            Some(pac) if self.mode == ProxyMode::Pac && !self.bypass(url) => pac.resolve(url).await,
+            _ => self.proxy_for(url),
+        }
+    }
+
+    /// 目标地址应使用的代理，None 表示直连（ws/wss 需先换成 http/https）
+    pub fn proxy_for(&self, url: &reqwest::Url) -> Option<reqwest::Url> {
+        match self.mode {
+            ProxyMode::Direct => None,
+            ProxyMode::System => system_proxy_for(url),
+            ProxyMode::Manual => {
+                if self.bypass(url) {
+                    return None;
+                }
+                if let Some(socks5) = &self.socks5 {
+                    return Some(socks5.clone());
+                }
+                match url.scheme() {
+                    "https" => self.https.clone().or_else(|| self.http.clone()),
+                    _ => self.http.clone(),
+                }
+            }
+            ProxyMode::Pac => {
+                if self.bypass(url) {
+                    return None;
+                }
+                self.pac.as_ref().and_then(|pac| pac.proxy_for(url))
+            }
+        }
+    }
+
+    fn bypass(&self, url: &reqwest::Url) -> bool {
+        let Some(host) = url.host_str() else {
+            return true;
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+        self.no_proxy.iter().any(|entry| entry.matches(&host))
+    }
+}
+
+/// no_proxy 列表中的一项
+enum NoProxyEntry {
+    All,
+    /// 域名本身及其子域名
+    Domain(String),
+    Ip(IpAddr),
+    Cidr(IpAddr, u8),
+}
+
+impl NoProxyEntry {
+    fn parse(entry: &str) -> Option<Self> {
+        let entry = entry.trim().to_ascii_lowercase();
+        if entry.is_empty() {
+            return None;
+        }
+        if entry == "*" {
+            return Some(Self::All);
+        }
+        if let Some((ip, bits)) = entry.split_once('/') {
+            return Some(Self::Cidr(ip.parse().ok()?, bits.parse().ok()?));
+        }
+        if let Ok(ip) = entry.trim_start_matches('[').trim_end_matches(']').parse() {
+            return Some(Self::Ip(ip));
+        }
+        let domain = entry.trim_start_matches("*.").trim_start_matches('.');
+        Some(Self::Domain(domain.to_string()))
+    }
+
+    fn matches(&self, host: &str) -> bool {
+        match self {
+            Self::All => true,
+            Self::Domain(domain) => {
+                host == domain
+                    || host
+                        .strip_suffix(domain.as_str())
+                        .is_some_and(|prefix| prefix.ends_with('.'))
+            }
+            Self::Ip(ip) => host.parse::<IpAddr>().is_ok_and(|h| h == *ip),
+            Self::Cidr(network, bits) => host
+                .parse::<IpAddr>()
+                .is_ok_and(|h| ip_in_network(h, *network, *bits)),
+        }
+    }
+}
+
+fn ip_in_network(ip: IpAddr, network: IpAddr, bits: u8) -> bool {
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(bits.min(32))).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(bits.min(128))).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
+
+/// system 模式下 WebSocket 使用的代理：读取 HTTPS_PROXY / HTTP_PROXY / ALL_PROXY / NO_PROXY
+fn system_proxy_for(url: &reqwest::Url) -> Option<reqwest::Url> {
+    let var = |name: &str| {
+        std::env::var(name)
+            .or_else(|_| std::env::var(name.to_ascii_lowercase()))
+            .ok()
+            .filter(|v| !v.trim().is_empty())
+    };
+    let host = url.host_str()?.to_ascii_lowercase();
+    if let Some(no_proxy) = var("NO_PROXY") {
+        if no_proxy
+            .split(',')
+            .filter_map(NoProxyEntry::parse)
+            .any(|entry| entry.matches(&host))
+        {
+            return None;
+        }
+    }
+    let value = match url.scheme() {
+        "https" => var("HTTPS_PROXY").or_else(|| var("HTTP_PROXY")),
+        _ => var("HTTP_PROXY"),
+    }
+    .or_else(|| var("ALL_PROXY"))?;
+    // 环境变量里的代理常省略 scheme
+    let value = if value.contains("://") {
+        value
+    } else {
+        format!("http://{}", value)
+    };
+    reqwest::Url::parse(&value).ok()
+}
@@ -0,0 +1,335 @@
+/// WebSocket 代理模块
+/// 页面直接 new WebSocket 时，握手请求不经过 proxy_request，带不上设备指纹与签名头。
+/// 这里由 Rust 建立连接：握手附加与 HTTP 代理一致的验证头和代理侧 Cookie，
+/// 收到的帧按顺序通过同一个 Channel 推给页面（二进制帧 base64 编码，保证与文本帧的先后顺序）。
+/// 连接保持心跳（Ping），异常断开时按指数退避自动重连；页面主动关闭或服务端正常关闭不重连。
+/// 重连期间 ws_send 返回错误（与浏览器 CONNECTING 状态下 send 抛错一致），不静默丢弃。
+/// 握手、隧道与帧转发见 ws_transport 模块
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use base64::{engine::general_purpose, Engine as _};
+use serde::{Deserialize, Serialize};
+use tauri::ipc::Channel;
+use tauri::State;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::error::AppError;
+use crate::headers::HeaderList;
+use crate::proxy::{is_internal_url, AppState};
+use crate::resilience::backoff_delay;
+use crate::ws_transport::{http_equivalent, open, relay, Ended, CLOSE_ABNORMAL};
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 连续重连失败的上限，超过后放弃并通知页面关闭
+const MAX_RECONNECT_ATTEMPTS: u32 = 10;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WsConnectRequest {
+    /// 前端生成的连接 id，ws_send / ws_close 据此找到连接
+    pub id: String,
+    pub url: String,
+    #[serde(default)]
+    pub protocols: Vec<String>,
+    #[serde(default)]
+    pub headers: HeaderList,
+    /// 异常断开后是否自动重连
+    #[serde(default = "default_reconnect")]
+    pub reconnect: bool,
+}
+
+fn default_reconnect() -> bool {
+    true
+}
+
+/// 页面发送的消息
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum WsOutgoing {
+    Text { data: String },
+    /// base64 编码
+    Binary { data: String },
+}
+
+/// 推给页面的连接事件，按发生顺序到达
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WsEvent {
+    Open {
+        /// 服务端选定的子协议，未协商时为空串
+        protocol: String,
+        /// 是否为自动重连后的连接
+        reconnected: bool,
+    },
+    Text {
+        data: String,
+    },
+    /// base64 编码
+    Binary {
+        data: String,
+    },
+    /// 连接异常断开，delay_ms 后发起第 attempt 次重连
+    Reconnecting {
+        attempt: u32,
+        delay_ms: u64,
+        code: u16,
+        reason: String,
+    },
+    /// 连接最终关闭（之后不会再有事件）
+    Close {
+        code: u16,
+        reason: String,
+        was_clean: bool,
+    },
+    Error {
+        error: AppError,
+    },
+}
+
+pub(crate) enum WsCommand {
+    Send(Message),
+    Close { code: u16, reason: String },
+}
+
+struct Connection {
+    /// 登记序号，区分同 id 的新旧连接
+    generation: u64,
+    commands: UnboundedSender<WsCommand>,
+    /// 握手完成、正在转发帧；重连等待期间为 false
+    open: Arc<AtomicBool>,
+}
+
+/// 进行中的连接：id -> 连接
+#[derive(Default)]
+pub struct WebSocketConnections {
+    connections: Mutex<HashMap<String, Connection>>,
+    next_generation: AtomicU64,
+}
+
+impl WebSocketConnections {
+    /// 登记连接；同 id 的旧连接因发送端被丢弃而自行结束
+    fn register(&self, id: &str) -> (u64, UnboundedReceiver<WsCommand>, Arc<AtomicBool>) {
+        let (tx, rx) = unbounded_channel();
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let open = Arc::new(AtomicBool::new(false));
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.insert(
+                id.to_string(),
+                Connection {
+                    generation,
+                    commands: tx,
+                    open: Arc::clone(&open),
+                },
+            );
+        }
+        (generation, rx, open)
+    }
+
+    fn unregister(&self, id: &str, generation: u64) {
+        if let Ok(mut connections) = self.connections.lock() {
+            if connections.get(id).map(|connection| connection.generation) == Some(generation) {
+                connections.remove(id);
+            }
+        }
+    }
+
+    fn send(&self, id: &str, command: WsCommand) -> Result<(), AppError> {
+        let connections = self
+            .connections
+            .lock()
+            .map_err(|e| AppError::Internal(e.to_string()))?;
+        let connection = connections
+            .get(id)
+            .ok_or_else(|| AppError::InvalidRequest(format!("WebSocket 连接不存在: {}", id)))?;
+        if matches!(command, WsCommand::Send(_)) && !connection.open.load(Ordering::Acquire) {
+            return Err(AppError::InvalidRequest(format!(
+                "WebSocket 正在重连，消息未发送: {}",
+                id
+            )));
+        }
+        connection
+            .commands
+            .send(command)
+            .map_err(|_| AppError::InvalidRequest(format!("WebSocket 连接已结束: {}", id)))
+    }
+}
+
+/// 建立 WebSocket 连接；立即返回，后续状态全部通过 on_event 推送
+#[tauri::command]
+pub fn ws_connect(
+    request: WsConnectRequest,
+    on_event: Channel<WsEvent>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    if is_internal_url(&request.url) {
+        return Err(AppError::InvalidRequest("Internal IPC request, skipping".to_string()));
+    }
+    // 地址与来源先同步校验，页面可立即得到错误
+    http_equivalent(&request.url)
+        .and_then(|url| state.origins.check(url.as_str()))
+        .inspect_err(|_| log!("⛔ [WS] 来源不在代理允许范围内: {}", request.url))?;
+
+    log!("🔌 [WS] 连接 {} ({})", request.url, request.id);
+    let state = Arc::clone(state.inner());
+    let (generation, commands, connected) = state.websockets.register(&request.id);
+    tauri::async_runtime::spawn(async move {
+        run_connection(&state, &request, commands, &connected, &on_event).await;
+        state.websockets.unregister(&request.id, generation);
+    });
+    Ok(())
+}
+
+/// 发送一条消息；连接已结束或正在重连时返回错误
+#[tauri::command]
+pub fn ws_send(
+    id: String,
+    message: WsOutgoing,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    let message = match message {
+        WsOutgoing::Text { data } => Message::Text(data),
+        WsOutgoing::Binary { data } => Message::Binary(
+            general_purpose::STANDARD
+                .decode(data)
+                .map_err(|e| AppError::InvalidRequest(format!("二进制消息解码失败: {}", e)))?,
+        ),
+    };
+    state.websockets.send(&id, WsCommand::Send(message))
+}
+
+/// 以指定状态码关闭连接（默认 1000）
+#[tauri::command]
+pub fn ws_close(
+    id: String,
+    code: Option<u16>,
+    reason: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), AppError> {
+    state.websockets.send(
+        &id,
+        WsCommand::Close {
+            code: code.unwrap_or(1000),
+            reason: reason.unwrap_or_default(),
+        },
+    )
+}
+
+/// 连接主循环：建立连接 → 转发帧 → 异常断开时退避重连
+async fn run_connection(
+    state: &AppState,
+    request: &WsConnectRequest,
+    mut commands: UnboundedReceiver<WsCommand>,
+    connected: &AtomicBool,
+    on_event: &Channel<WsEvent>,
+) {
+    let mut attempt = 0u32;
+    let mut opened = false;
+
+    loop {
+        let (code, reason, error) = match open(state, request).await {
+            Ok((stream, protocol)) => {
+                log!("✅ [WS] 已连接 {} (protocol: {:?})", request.url, protocol);
+                attempt = 0;
+                let event = WsEvent::Open {
+                    protocol,
+                    reconnected: opened,
+                };
+                if on_event.send(event).is_err() {
+                    return;
+                }
+                opened = true;
+                connected.store(true, Ordering::Release);
+                let ended = relay(stream, &mut commands, on_event).await;
+                connected.store(false, Ordering::Release);
+                match ended {
+                    Ended::Closed {
+                        code,
+                        reason,
+                        was_clean,
+                    } => {
+                        log!("🔌 [WS] 已关闭 {} ({} {})", request.url, code, reason);
+                        let _ = on_event.send(WsEvent::Close {
+                            code,
+                            reason,
+                            was_clean,
+                        });
+                        return;
+                    }
+                    Ended::Abandoned => return,
+                    Ended::Dropped { code, reason } => (code, reason, None),
+                }
+            }
+            Err(error) => {
+                log!("⚠️  [WS] 连接失败 {}: {}", request.url, error);
+                (CLOSE_ABNORMAL, String::new(), Some(error))
+            }
+        };
+
+        // 首次连接就因不可重试的原因失败（证书、401、来源被拒等），重连也无济于事
+        let hopeless = !opened && error.as_ref().is_some_and(|e| !e.retryable());
+        if !request.reconnect || hopeless || attempt >= MAX_RECONNECT_ATTEMPTS {
+            if let Some(error) = error {
+                let _ = on_event.send(WsEvent::Error { error });
+            }
+            let _ = on_event.send(WsEvent::Close {
+                code,
+                reason,
+                was_clean: false,
+            });
+            return;
+        }
+
+        let delay = backoff_delay(attempt);
+        attempt += 1;
+        log!("🔁 [WS] {:?} 后第 {} 次重连: {}", delay, attempt, request.url);
+        let event = WsEvent::Reconnecting {
+            attempt,
+            delay_ms: delay.as_millis() as u64,
+            code,
+            reason,
+        };
+        if on_event.send(event).is_err() {
+            return;
+        }
+
+        // 等待期间页面可能主动关闭；ws_send 此时直接返回错误，
+        // 只有断开瞬间已进入队列的消息会在这里丢弃
+        let wake = Instant::now() + delay;
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep_until(wake) => break,
+                command = commands.recv() => match command {
+                    Some(WsCommand::Send(_)) => log!("⚠️  [WS] 重连中，丢弃发送: {}", request.url),
+                    Some(WsCommand::Close { code, reason }) => {
+                        let _ = on_event.send(WsEvent::Close { code, reason, was_clean: false });
+                        return;
+                    }
+                    None => return,
+                },
+            }
+        }
+    }
+}
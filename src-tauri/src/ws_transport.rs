@@ -0,0 +1,338 @@
+/// WebSocket 连接传输层
+/// 握手（附加验证头与代理侧 Cookie）、经上游代理的隧道与自定义 TLS、建立后的双向帧转发与心跳。
+/// 连接的登记、命令与重连策略见 websocket 模块
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine as _};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::cookie::CookieStore as _;
+use tauri::ipc::Channel;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedReceiver;
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::handshake::client::{Request, Response};
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use crate::canonical::EMPTY_BODY_HASH;
+use crate::client_identity::ClientIdentity;
+use crate::error::{AppError, DnsError};
+use crate::proxy::AppState;
+use crate::proxy_tunnel::tunnel;
+use crate::signing::SigningRequest;
+use crate::tls::TlsPolicy;
+use crate::websocket::{WsCommand, WsConnectRequest, WsEvent};
+
+// 编译时判断是否启用日志（使用字节比较避免 const 限制）
+#[cfg(debug_assertions)]
+const ENABLE_LOGS: bool = true;
+
+#[cfg(not(debug_assertions))]
+const ENABLE_LOGS: bool = {
+    match option_env!("TAURI_ENABLE_LOGS") {
+        Some(val) => matches!(val.as_bytes(), b"true"),
+        None => false,
+    }
+};
+
+// 日志宏
+macro_rules! log {
+    ($($arg:tt)*) => {
+        if ENABLE_LOGS {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 心跳间隔；超过两个间隔没有收到任何帧视为连接已断
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 主动关闭后等待服务端回应 Close 帧的时间
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 未收到 Close 帧就断开（RFC 6455 保留码，只用于通知页面）
+pub(crate) const CLOSE_ABNORMAL: u16 = 1006;
+/// 收到的 Close 帧没有状态码
+const CLOSE_NO_STATUS: u16 = 1005;
+
+pub(crate) type WsStream = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// 一次连接的结束方式
+pub(crate) enum Ended {
+    /// 双方交换了 Close 帧，或页面主动关闭
+    Closed { code: u16, reason: String, was_clean: bool },
+    /// 异常断开或服务端要求稍后重连
+    Dropped { code: u16, reason: String },
+    /// 页面已不再接收事件（窗口关闭、同 id 连接被替换）
+    Abandoned,
+}
+
+/// 握手：转发页面请求头，附加代理侧 Cookie 与验证头（仅签名来源）
+pub(crate) async fn open(state: &AppState, request: &WsConnectRequest) -> Result<(WsStream, String), AppError> {
+    let http_url = http_equivalent(&request.url)?;
+    let sign = state.origins.check(http_url.as_str())?;
+
+    let mut handshake = request.url.as_str().into_client_request()?;
+    let headers = handshake.headers_mut();
+
+    // 握手相关头由 tungstenite 生成，页面传入的同名头一律忽略
+    for (name, value) in request.headers.forwardable_request().iter_bytes() {
+        if name.to_ascii_lowercase().starts_with("sec-websocket-") {
+            continue;
+        }
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_bytes(&value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    if !request.protocols.is_empty() {
+        let protocols = HeaderValue::from_str(&request.protocols.join(", "))
+            .map_err(|e| AppError::InvalidRequest(format!("无效的子协议: {}", e)))?;
+        headers.insert("Sec-WebSocket-Protocol", protocols);
+    }
+    if !headers.contains_key("cookie") {
+        if let Some(cookie) = state.cookies.cookies(&http_url) {
+            if let Ok(cookie) = HeaderValue::from_bytes(cookie.as_bytes()) {
+                headers.insert("cookie", cookie);
+            }
+        }
+    }
+    if sign {
+        state.challenge.ensure(state).await?;
+        state.sessions.ensure(state).await;
+    }
+    // 签名按 URL 路径计算，与 scheme 无关
+    let signed_headers = state.signers.sign(&SigningRequest {
+        id: Some(&request.id),
+        method: "GET",
+        url: &request.url,
+        headers: &request.headers,
+        body_hash: EMPTY_BODY_HASH,
+        signed: sign,
+    })?;
+    for (name, value) in signed_headers.iter_bytes() {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_bytes(&value),
+        ) {
+            headers.insert(name, value);
+        }
+    }
+
+    let timeout = state.policy.effective(None).connect;
+    let upstream = state.http.resolver().resolve(&http_url).await;
+    let identity = state.http.client_identity();
+    let connecting = connect(
+        handshake,
+        &http_url,
+        upstream,
+        state.http.tls(),
+        identity.as_deref(),
+    );
+    let sent_at = chrono::Utc::now();
+    let (stream, response) = tokio::time::timeout(timeout, connecting)
+        .await
+        .map_err(|_| AppError::Timeout(format!("WebSocket 握手超时: {}", request.url)))??;
+
+    // 签名来源握手响应的 Date 头同样参与服务器时差估算
+    if sign {
+        let mut date = reqwest::header::HeaderMap::new();
+        for name in [reqwest::header::DATE, reqwest::header::AGE] {
+            if let Some(value) = response
+                .headers()
+                .get(name.as_str())
+                .and_then(|value| reqwest::header::HeaderValue::from_bytes(value.as_bytes()).ok())
+            {
+                date.insert(name, value);
+            }
+        }
+        state.clock.observe(&date, sent_at, chrono::Utc::now());
+    }
+
+    // 握手响应中的 Set-Cookie 与普通响应一样写入代理 Cookie 罐
+    let set_cookies: Vec<reqwest::header::HeaderValue> = response
+        .headers()
+        .get_all("set-cookie")
+        .iter()
+        .filter_map(|value| reqwest::header::HeaderValue::from_bytes(value.as_bytes()).ok())
+        .collect();
+    if !set_cookies.is_empty() {
+        state.cookies.set_cookies(&mut set_cookies.iter(), &http_url);
+    }
+
+    let protocol = response
+        .headers()
+        .get("sec-websocket-protocol")
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    Ok((stream, protocol))
+}
+
+/// 建立连接并握手；有上游代理时先经代理建立到目标的隧道。
+/// 配置了自定义 CA、证书固定或客户端证书时自行完成 TLS，握手（带签名头）之前先检查服务端证书
+async fn connect(
+    handshake: Request,
+    http_url: &reqwest::Url,
+    upstream: Option<reqwest::Url>,
+    tls: &TlsPolicy,
+    identity: Option<&ClientIdentity>,
+) -> Result<(WsStream, Response), AppError> {
+    let custom_tls = http_url.scheme() == "https" && (tls.is_customized() || identity.is_some());
+    let host = http_url
+        .host_str()
+        .ok_or_else(|| AppError::InvalidUrl(http_url.to_string()))?;
+    let port = http_url.port_or_known_default().unwrap_or(443);
+    let tcp = match upstream {
+        Some(proxy) => {
+            log!(
+                "🌐 [WS] 经上游代理 {}://{} 连接 {}:{}",
+                proxy.scheme(),
+                proxy.host_str().unwrap_or_default(),
+                host,
+                port
+            );
+            tunnel(&proxy, host, port).await?
+        }
+        None => connect_direct(host, port).await?,
+    };
+    if !custom_tls {
+        return Ok(tokio_tungstenite::client_async_tls(handshake, tcp).await?);
+    }
+
+    let stream = tls.connect_native(host, tcp, identity).await?;
+    Ok(tokio_tungstenite::client_async(handshake, MaybeTlsStream::NativeTls(stream)).await?)
+}
+
+/// 直连目标；自行解析域名，解析失败返回 DnsError，与连接失败区分开
+async fn connect_direct(host: &str, port: u16) -> Result<TcpStream, AppError> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let addrs: Vec<_> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|source| DnsError {
+            host: host.to_string(),
+            source,
+        })?
+        .collect();
+    Ok(TcpStream::connect(addrs.as_slice()).await?)
+}
+
+/// 双向转发帧，直到连接结束
+pub(crate) async fn relay(
+    stream: WsStream,
+    commands: &mut UnboundedReceiver<WsCommand>,
+    on_event: &Channel<WsEvent>,
+) -> Ended {
+    let (mut sink, mut source) = stream.split();
+    let mut ping = tokio::time::interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+    let mut last_seen = Instant::now();
+    // 页面主动关闭后等待服务端回应的截止时间，以及页面给出的关闭码
+    let mut closing: Option<(Instant, u16, String)> = None;
+
+    loop {
+        let close_deadline = closing.as_ref().map_or(last_seen + CLOSE_TIMEOUT, |(at, _, _)| *at);
+        tokio::select! {
+            frame = source.next() => {
+                let message = match frame {
+                    Some(Ok(message)) => message,
+                    Some(Err(e)) => {
+                        return match closing {
+                            Some((_, code, reason)) => Ended::Closed { code, reason, was_clean: false },
+                            None => Ended::Dropped { code: CLOSE_ABNORMAL, reason: e.to_string() },
+                        };
+                    }
+                    None => {
+                        return match closing {
+                            Some((_, code, reason)) => Ended::Closed { code, reason, was_clean: false },
+                            None => Ended::Dropped { code: CLOSE_ABNORMAL, reason: String::new() },
+                        };
+                    }
+                };
+                last_seen = Instant::now();
+                let event = match message {
+                    Message::Text(data) => WsEvent::Text { data },
+                    Message::Binary(data) => WsEvent::Binary {
+                        data: general_purpose::STANDARD.encode(data),
+                    },
+                    // Ping 由 tungstenite 自动回复 Pong
+                    Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => continue,
+                    Message::Close(frame) => {
+                        let (code, reason) = frame
+                            .map(|frame| (u16::from(frame.code), frame.reason.into_owned()))
+                            .unwrap_or((CLOSE_NO_STATUS, String::new()));
+                        // tungstenite 收到 Close 后会自动回应，这里把剩余的关闭握手走完
+                        let _ = sink.flush().await;
+                        if closing.is_none() && should_reconnect(code) {
+                            return Ended::Dropped { code, reason };
+                        }
+                        return Ended::Closed { code, reason, was_clean: true };
+                    }
+                };
+                if on_event.send(event).is_err() {
+                    let _ = sink.send(Message::Close(None)).await;
+                    return Ended::Abandoned;
+                }
+            }
+            command = commands.recv(), if closing.is_none() => match command {
+                Some(WsCommand::Send(message)) => {
+                    if let Err(e) = sink.send(message).await {
+                        log!("⚠️  [WS] 发送失败: {}", e);
+                        return Ended::Dropped { code: CLOSE_ABNORMAL, reason: e.to_string() };
+                    }
+                }
+                Some(WsCommand::Close { code, reason }) => {
+                    let frame = CloseFrame {
+                        code: CloseCode::from(code),
+                        reason: reason.clone().into(),
+                    };
+                    let _ = sink.send(Message::Close(Some(frame))).await;
+                    closing = Some((Instant::now() + CLOSE_TIMEOUT, code, reason));
+                }
+                None => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    return Ended::Abandoned;
+                }
+            },
+            _ = tokio::time::sleep_until(close_deadline), if closing.is_some() => {
+                let (_, code, reason) = closing.unwrap_or((Instant::now(), 1000, String::new()));
+                return Ended::Closed { code, reason, was_clean: false };
+            }
+            _ = ping.tick(), if closing.is_none() => {
+                if last_seen.elapsed() > PING_INTERVAL * 2 {
+                    return Ended::Dropped { code: CLOSE_ABNORMAL, reason: "心跳超时".to_string() };
+                }
+                if let Err(e) = sink.send(Message::Ping(Vec::new())).await {
+                    return Ended::Dropped { code: CLOSE_ABNORMAL, reason: e.to_string() };
+                }
+            }
+        }
+    }
+}
+
+/// 服务端以这些状态码关闭时视为临时中断：1001 离开、1011 内部错误、1012 重启、1013 稍后重试
+fn should_reconnect(code: u16) -> bool {
+    matches!(code, 1001 | 1011..=1013)
+}
+
+/// ws/wss 对应的 http/https 地址，用于来源校验与 Cookie 匹配
+pub(crate) fn http_equivalent(url: &str) -> Result<reqwest::Url, AppError> {
+    let mut parsed =
+        reqwest::Url::parse(url).map_err(|e| AppError::InvalidUrl(format!("{}: {}", url, e)))?;
+    let scheme = match parsed.scheme() {
+        "ws" => "http",
+        "wss" => "https",
+        other => {
+            return Err(AppError::InvalidUrl(format!("不支持的 WebSocket 协议: {}", other)));
+        }
+    };
+    parsed
+        .set_scheme(scheme)
+        .map_err(|_| AppError::InvalidUrl(url.to_string()))?;
+    Ok(parsed)
+}
@@ -5,8 +5,13 @@
  * ├── logger.js - 日志工具
  * ├── utils/
  * │   ├── dom.js - DOM 工具函数
- * │   └── storage.js - 存储工具
+ * │   ├── storage.js - 存储工具
+ * │   └── tauri-events.js - Tauri 事件监听
  * ├── proxy.js - HTTP 代理拦截 (Fetch + XMLHttpRequest)
+ * ├── proxy-stream.js - 流式代理响应（ReadableStream + 进度）
+ * ├── proxy-upload.js - 分块上传 FormData 文件（上传句柄 + 进度）
+ * ├── proxy-websocket.js - WebSocket 代理（签名握手 + 自动重连）
+ * ├── proxy-eventsource.js - EventSource (SSE) 代理（签名请求 + Last-Event-ID 重连）
  * ├── zoom.js - 页面缩放控制
  * ├── window.js - 多窗口支持和标题同步
  * ├── linux-fixes.js - Linux 特定问题修复
@@ -23,6 +28,8 @@
 import { initLogger } from './modules/logger.js';
 import { isInIframe } from './modules/utils/dom.js';
 import { initProxy } from './modules/proxy.js';
+import { initWebSocketProxy } from './modules/proxy-websocket.js';
+import { initEventSourceProxy } from './modules/proxy-eventsource.js';
 import { initZoom } from './modules/zoom.js';
 import { initWindow } from './modules/window.js';
 import { initTabs } from './modules/tabs/manager.js';
@@ -48,6 +55,8 @@ import { initDownload } from './modules/download.js';
     log("⚠️  当前处于 iframe，上线轻量模式：仅启用代理模块");
     try {
       initProxy(log, invoke);
+      initWebSocketProxy(log, invoke);
+      initEventSourceProxy(log, invoke);
       log("✅ iframe 代理模块已启用");
     } catch (err) {
       console.error("❌ iframe 代理模块初始化失败:", err);
@@ -59,6 +68,8 @@ import { initDownload } from './modules/download.js';
   try {
     // 1. 代理拦截（拦截所有 /base_api/ 请求，添加安全头）
     initProxy(log, invoke);
+    initWebSocketProxy(log, invoke);
+    initEventSourceProxy(log, invoke);
 
     // 2. 页面缩放（Cmd +/-/0，滚轮缩放）
     initZoom(log);
@@ -7,6 +7,7 @@
  */
 
 import { isLinux } from './utils/dom.js';
+import { canListenTauriEvents, listenTauriEvent } from './utils/tauri-events.js';
 
 let downloadDir = null;
 let osType = null;
@@ -65,28 +66,14 @@ export async function initDownload(log, invoke) {
 
 /**
  * 监听 Rust 端下载事件，驱动实时进度 UI
- * 注意：外部 URL 模式下 window.__TAURI__.event 不存在，
- * 需要用底层 __TAURI_INTERNALS__ API 注册事件监听
  */
 function initDownloadProgressListener(log) {
-  const internals = window.__TAURI_INTERNALS__;
-  if (!internals || !internals.invoke || !internals.transformCallback) {
+  if (!canListenTauriEvents()) {
     log('⚠️ Tauri internals API 不可用，实时进度将不可用（基础反馈仍然可用）');
     return;
   }
 
-  function tauriListen(eventName, handler) {
-    const callbackId = internals.transformCallback((event) => {
-      handler(event);
-    });
-    return internals.invoke('plugin:event|listen', {
-      event: eventName,
-      target: { kind: 'Any' },
-      handler: callbackId,
-    }).catch(err => {
-      log(`⚠️ 注册事件 ${eventName} 失败: ${err}`);
-    });
-  }
+  const tauriListen = (eventName, handler) => listenTauriEvent(eventName, handler, log);
 
   tauriListen('download-progress', (event) => {
     const { id, filename, downloaded, total_size, percent, speed_bps } = event.payload;
@@ -272,9 +272,10 @@ function fixDownloadInDocument(doc, log, invoke) {
               }
             })
             .catch(err => {
-              log(`❌ [Linux Fix] Rust 下载失败: ${err}`);
+              const errMsg = err?.message || String(err);
+              log(`❌ [Linux Fix] Rust 下载失败: [${err?.code}] ${errMsg}`);
               if (window.tauriDownload?.showDownloadError) {
-                window.tauriDownload.showDownloadError(localId, displayName, String(err));
+                window.tauriDownload.showDownloadError(localId, displayName, errMsg);
               }
               
               // 备用方案：尝试 window.open
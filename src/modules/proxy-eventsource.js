@@ -0,0 +1,116 @@
+/**
+ * EventSource (SSE) 代理模块
+ *
+ * /base_api/ 下的 EventSource 由 Rust 端建立连接（sse_connect），请求附加签名与设备头；
+ * 事件解析在 Rust 端完成，通过 Channel 逐条推送。断线重连（Last-Event-ID、retry）也由 Rust 端负责，
+ * 这里只按浏览器语义维护 readyState 并派发 open / message / 自定义事件 / error。
+ * 其他地址仍使用原生 EventSource
+ */
+
+import { generateRequestId } from './proxy-upload.js';
+
+const CONNECTING = 0;
+const OPEN = 1;
+const CLOSED = 2;
+
+export function initEventSourceProxy(log, invoke) {
+  const NativeEventSource = window.EventSource;
+  if (!NativeEventSource || NativeEventSource.__tauriProxy) return;
+
+  class ProxyEventSource extends EventTarget {
+    constructor(url, init) {
+      super();
+
+      const resolved = new URL(url, window.location.href);
+      // 只代理 /base_api/ 下的连接，其余走原生实现
+      if (!resolved.pathname.includes('/base_api/')) {
+        return new NativeEventSource(url, init);
+      }
+
+      this.url = resolved.toString();
+      this.withCredentials = !!init?.withCredentials;
+      this.readyState = CONNECTING;
+      this.onopen = null;
+      this.onmessage = null;
+      this.onerror = null;
+
+      this._id = generateRequestId();
+      this._origin = resolved.origin;
+
+      const { Channel } = window.__TAURI__.core;
+      const onEvent = new Channel();
+      onEvent.onmessage = (event) => this._handleEvent(event);
+
+      log(`📡 [SSE] 代理连接: ${this.url}`);
+      invoke('sse_connect', {
+        request: { id: this._id, url: this.url },
+        onEvent
+      }).catch((err) => {
+        console.error('❌ [SSE] 连接失败:', err);
+        this._fail();
+      });
+    }
+
+    close() {
+      if (this.readyState === CLOSED) return;
+      this.readyState = CLOSED;
+      invoke('sse_close', { id: this._id }).catch(() => {});
+    }
+
+    _dispatch(event) {
+      const handler = this[`on${event.type}`];
+      if (typeof handler === 'function') {
+        try {
+          handler.call(this, event);
+        } catch (err) {
+          console.error(err);
+        }
+      }
+      this.dispatchEvent(event);
+    }
+
+    _fail() {
+      if (this.readyState === CLOSED) return;
+      this.readyState = CLOSED;
+      this._dispatch(new Event('error'));
+    }
+
+    _handleEvent(event) {
+      // close() 之后到达的事件一律丢弃
+      if (this.readyState === CLOSED) return;
+
+      switch (event.event) {
+        case 'open':
+          this.readyState = OPEN;
+          this._dispatch(new Event('open'));
+          break;
+        case 'message':
+          this._dispatch(new MessageEvent(event.name, {
+            data: event.data,
+            lastEventId: event.last_event_id,
+            origin: this._origin
+          }));
+          break;
+        case 'reconnecting':
+          // 与浏览器一致：断线时派发 error，readyState 回到 CONNECTING
+          log(`🔁 [SSE] 连接断开，${event.delay_ms}ms 后重连: ${this.url}`);
+          this.readyState = CONNECTING;
+          this._dispatch(new Event('error'));
+          break;
+        case 'failed':
+          console.error('❌ [SSE] 连接失败，不再重连:', event.error);
+          this._fail();
+          break;
+      }
+    }
+  }
+
+  for (const [name, value] of Object.entries({ CONNECTING, OPEN, CLOSED })) {
+    Object.defineProperty(ProxyEventSource, name, { value, enumerable: true });
+    Object.defineProperty(ProxyEventSource.prototype, name, { value, enumerable: true });
+  }
+  ProxyEventSource.__tauriProxy = true;
+
+  window.EventSource = ProxyEventSource;
+  log('✅ EventSource 代理已启用（/base_api/）');
+}
@@ -0,0 +1,163 @@
+/**
+ * 流式代理辅助模块
+ *
+ * Rust 端 proxy_request_stream 先返回响应头，响应体通过两个 Channel 推送：
+ * - onChunk: 原始字节块（ArrayBuffer）
+ * - onEvent: progress / finished / cancelled / error 控制事件
+ * 两个 Channel 的到达顺序不保证一致，finished / cancelled 事件携带已推送的块数，收齐后才关闭或中止流
+ */
+
+/**
+ * Rust 端命令统一返回 { code, message, retryable, ... } 结构的错误
+ */
+export function isCancelledError(err) {
+  return err?.code === 'CANCELLED';
+}
+
+/**
+ * 等待响应头、读取响应体或总超时
+ */
+export function isTimeoutError(err) {
+  return err?.code === 'TIMEOUT';
+}
+
+/**
+ * 把 Rust 端错误转换为页面可识别的网络错误，保留 code/retryable 便于排查
+ */
+export function createNetworkError(err) {
+  const error = new TypeError(err?.message || 'Network error');
+  if (err?.code) {
+    error.code = err.code;
+    error.retryable = !!err.retryable;
+    error.detail = err.detail;
+  }
+  return error;
+}
+
+export function createAbortError() {
+  return new DOMException('The operation was aborted.', 'AbortError');
+}
+
+/**
+ * AbortSignal 触发时通知 Rust 端中断请求，返回解绑函数
+ */
+export function bindAbortSignal(signal, invoke, requestId) {
+  if (!signal) return () => {};
+  const onAbort = () => {
+    invoke('cancel_proxy_request', { id: requestId }).catch(() => {});
+  };
+  signal.addEventListener('abort', onAbort, { once: true });
+  return () => signal.removeEventListener('abort', onAbort);
+}
+
+/**
+ * 发起流式代理请求
+ * @returns {Promise<{ head: object, body: ReadableStream<Uint8Array> }>}
+ */
+export async function invokeProxyStream(invoke, request, onProgress) {
+  const { Channel } = window.__TAURI__.core;
+  const onChunk = new Channel();
+  const onEvent = new Channel();
+
+  let controller = null;
+  let head = null;
+  let received = 0;
+  let receivedChunks = 0;
+  let expectedChunks = null;
+  let cancelled = false;
+  let done = false;
+
+  const body = new ReadableStream({
+    start(c) {
+      controller = c;
+    },
+    cancel() {
+      done = true;
+      if (request.id) {
+        invoke('cancel_proxy_request', { id: request.id }).catch(() => {});
+      }
+    }
+  });
+
+  const tryClose = () => {
+    if (!done && expectedChunks !== null && receivedChunks >= expectedChunks) {
+      done = true;
+      if (cancelled) {
+        controller.error(createAbortError());
+      } else {
+        controller.close();
+      }
+    }
+  };
+
+  onChunk.onmessage = (data) => {
+    if (done) return;
+    const bytes = data instanceof ArrayBuffer ? new Uint8Array(data) : new Uint8Array(data || []);
+    received += bytes.byteLength;
+    receivedChunks += 1;
+    controller.enqueue(bytes);
+    if (onProgress) onProgress(received, head?.content_length ?? null);
+    tryClose();
+  };
+
+  onEvent.onmessage = (event) => {
+    if (done) return;
+    switch (event.event) {
+      case 'progress':
+        break;
+      case 'finished':
+        expectedChunks = event.chunks;
+        tryClose();
+        break;
+      case 'cancelled':
+        cancelled = true;
+        expectedChunks = event.chunks;
+        tryClose();
+        break;
+      case 'error':
+        done = true;
+        controller.error(isCancelledError(event.error)
+          ? createAbortError()
+          : createNetworkError(event.error));
+        break;
+      default:
+        break;
+    }
+  };
+
+  head = await invoke('proxy_request_stream', { request, onChunk, onEvent });
+  return { head, body };
+}
+
+/**
+ * 读取整个流（XHR 的 blob/arraybuffer 响应使用）
+ */
+export async function readStreamToBuffer(stream) {
+  const reader = stream.getReader();
+  const parts = [];
+  let total = 0;
+
+  for (;;) {
+    const { done, value } = await reader.read();
+    if (done) break;
+    parts.push(value);
+    total += value.byteLength;
+  }
+
+  const merged = new Uint8Array(total);
+  let offset = 0;
+  for (const part of parts) {
+    merged.set(part, offset);
+    offset += part.byteLength;
+  }
+  return merged.buffer;
+}
+
+/**
+ * 广播下载进度，页面可通过 window.addEventListener('tauri-proxy-progress', ...) 监听
+ */
+export function emitProxyProgress(url, loaded, total) {
+  window.dispatchEvent(new CustomEvent('tauri-proxy-progress', {
+    detail: { url, loaded, total }
+  }));
+}
@@ -0,0 +1,82 @@
+/**
+ * 代理上传辅助模块
+ *
+ * FormData 中的文件不再整体 base64 编码，而是切块写入 Rust 端临时文件（upload_begin / upload_chunk），
+ * 代理请求只携带上传句柄；Rust 端以流的形式写入 multipart，并推送 upload-progress 事件
+ */
+
+import { canListenTauriEvents, currentWebviewTarget, listenTauriEvent } from './utils/tauri-events.js';
+
+const UPLOAD_CHUNK_SIZE = 4 * 1024 * 1024;
+
+// 请求 ID -> 上传进度回调
+const progressHandlers = new Map();
+let progressListenerReady = false;
+
+export function generateRequestId() {
+  if (window.crypto && typeof window.crypto.randomUUID === 'function') {
+    return window.crypto.randomUUID();
+  }
+  return `req-${Date.now()}-${Math.random().toString(16).slice(2)}`;
+}
+
+/**
+ * 注册 upload-progress 事件监听（只注册一次）；Rust 端只把进度发给发起请求的 webview
+ */
+export function initUploadProgressListener(log) {
+  if (progressListenerReady || !canListenTauriEvents()) return;
+  progressListenerReady = true;
+
+  listenTauriEvent('upload-progress', (event) => {
+    const { id, uploaded, total_size } = event.payload;
+    const handler = progressHandlers.get(id);
+    if (handler) handler(uploaded, total_size);
+  }, log, currentWebviewTarget());
+}
+
+export function onUploadProgress(requestId, handler) {
+  progressHandlers.set(requestId, handler);
+  return () => progressHandlers.delete(requestId);
+}
+
+/**
+ * 把 File/Blob 分块写入 Rust 端临时文件，返回上传句柄
+ */
+async function uploadBlob(invoke, blob) {
+  const uploadId = await invoke('upload_begin');
+  try {
+    for (let offset = 0; offset < blob.size; offset += UPLOAD_CHUNK_SIZE) {
+      const chunk = blob.slice(offset, offset + UPLOAD_CHUNK_SIZE);
+      const bytes = new Uint8Array(await chunk.arrayBuffer());
+      await invoke('upload_chunk', bytes, { headers: { 'X-Upload-Id': uploadId } });
+    }
+  } catch (err) {
+    invoke('upload_discard', { uploadId }).catch(() => {});
+    throw err;
+  }
+  return uploadId;
+}
+
+/**
+ * 按原始顺序把 FormData 序列化为 multipart 表单项
+ */
+export async function serializeFormDataParts(invoke, formData, isFile) {
+  const parts = [];
+
+  for (const [key, value] of formData.entries()) {
+    if (isFile(value)) {
+      const uploadId = await uploadBlob(invoke, value);
+      parts.push({
+        kind: 'file',
+        name: key,
+        file_name: value.name,
+        content_type: value.type || 'application/octet-stream',
+        upload: uploadId
+      });
+    } else {
+      parts.push({ kind: 'text', name: key, value: value?.toString() ?? '' });
+    }
+  }
+
+  return parts;
+}
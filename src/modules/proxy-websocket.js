@@ -0,0 +1,205 @@
+/**
+ * WebSocket 代理模块
+ *
+ * /base_api/ 下的 WebSocket 由 Rust 端建立连接（ws_connect），握手附加签名与设备头；
+ * 帧通过 Channel 按顺序推送（二进制帧为 base64）。异常断开时 Rust 端自动重连：
+ * 期间 readyState 回到 CONNECTING 并派发 reconnecting 事件，重连成功后再次派发 open，
+ * 页面可在 open 中重新订阅。其他地址仍使用原生 WebSocket
+ */
+
+import { generateRequestId } from './proxy-upload.js';
+
+const CONNECTING = 0;
+const OPEN = 1;
+const CLOSING = 2;
+const CLOSED = 3;
+
+function arrayBufferToBase64(buffer) {
+  const bytes = new Uint8Array(buffer);
+  let binary = '';
+  const chunkSize = 0x8000;
+  for (let i = 0; i < bytes.length; i += chunkSize) {
+    binary += String.fromCharCode.apply(null, bytes.subarray(i, i + chunkSize));
+  }
+  return btoa(binary);
+}
+
+function base64ToArrayBuffer(data) {
+  const binaryString = atob(data);
+  const bytes = new Uint8Array(binaryString.length);
+  for (let i = 0; i < binaryString.length; i++) {
+    bytes[i] = binaryString.charCodeAt(i);
+  }
+  return bytes.buffer;
+}
+
+/**
+ * 把页面发送的数据转换为 ws_send 的消息格式
+ */
+async function toOutgoingMessage(data) {
+  if (typeof data === 'string') {
+    return { type: 'text', data };
+  }
+  if (data instanceof Blob) {
+    return { type: 'binary', data: arrayBufferToBase64(await data.arrayBuffer()) };
+  }
+  if (data instanceof ArrayBuffer) {
+    return { type: 'binary', data: arrayBufferToBase64(data) };
+  }
+  if (ArrayBuffer.isView(data)) {
+    const view = new Uint8Array(data.buffer, data.byteOffset, data.byteLength);
+    return { type: 'binary', data: arrayBufferToBase64(view.slice().buffer) };
+  }
+  return { type: 'text', data: String(data) };
+}
+
+export function initWebSocketProxy(log, invoke) {
+  const NativeWebSocket = window.WebSocket;
+  if (!NativeWebSocket || NativeWebSocket.__tauriProxy) return;
+
+  class ProxyWebSocket extends EventTarget {
+    constructor(url, protocols) {
+      super();
+
+      const resolved = new URL(url, window.location.href);
+      if (resolved.protocol === 'http:') resolved.protocol = 'ws:';
+      if (resolved.protocol === 'https:') resolved.protocol = 'wss:';
+
+      // 只代理 /base_api/ 下的连接，其余走原生实现
+      if (!resolved.pathname.includes('/base_api/')) {
+        return protocols === undefined
+          ? new NativeWebSocket(url)
+          : new NativeWebSocket(url, protocols);
+      }
+
+      this.url = resolved.toString();
+      this.protocol = '';
+      this.extensions = '';
+      this.binaryType = 'blob';
+      this.bufferedAmount = 0;
+      this.readyState = CONNECTING;
+      this.onopen = null;
+      this.onmessage = null;
+      this.onerror = null;
+      this.onclose = null;
+
+      this._id = generateRequestId();
+      // 保证异步转换（Blob）的消息按调用顺序发出
+      this._sendQueue = Promise.resolve();
+
+      const list = protocols === undefined ? [] : [].concat(protocols);
+      const { Channel } = window.__TAURI__.core;
+      const onEvent = new Channel();
+      onEvent.onmessage = (event) => this._handleEvent(event);
+
+      log(`🔌 [WS] 代理连接: ${this.url}`);
+      invoke('ws_connect', {
+        request: {
+          id: this._id,
+          url: this.url,
+          protocols: list,
+          headers: [['Origin', window.location.origin]]
+        },
+        onEvent
+      }).catch((err) => {
+        console.error('❌ [WS] 连接失败:', err);
+        this._finish(1006, '', false, true);
+      });
+    }
+
+    send(data) {
+      if (this.readyState === CONNECTING) {
+        throw new DOMException("Failed to execute 'send' on 'WebSocket': Still in CONNECTING state.", 'InvalidStateError');
+      }
+      if (this.readyState !== OPEN) return;
+
+      this._sendQueue = this._sendQueue
+        .then(() => toOutgoingMessage(data))
+        .then((message) => invoke('ws_send', { id: this._id, message }))
+        .catch((err) => log(`⚠️  [WS] 发送失败: ${err?.message || err}`));
+    }
+
+    close(code, reason) {
+      if (code !== undefined && code !== 1000 && (code < 3000 || code > 4999)) {
+        throw new DOMException(`Failed to execute 'close' on 'WebSocket': The code must be either 1000, or between 3000 and 4999. ${code} is neither.`, 'InvalidAccessError');
+      }
+      if (this.readyState === CLOSING || this.readyState === CLOSED) return;
+
+      this.readyState = CLOSING;
+      invoke('ws_close', { id: this._id, code: code ?? 1000, reason: reason ?? '' })
+        .catch(() => this._finish(code ?? 1000, reason ?? '', false, false));
+    }
+
+    _dispatch(event) {
+      const handler = this[`on${event.type}`];
+      if (typeof handler === 'function') {
+        try {
+          handler.call(this, event);
+        } catch (err) {
+          console.error(err);
+        }
+      }
+      this.dispatchEvent(event);
+    }
+
+    _finish(code, reason, wasClean, withError) {
+      if (this.readyState === CLOSED) return;
+      this.readyState = CLOSED;
+      if (withError) {
+        this._dispatch(new Event('error'));
+      }
+      this._dispatch(new CloseEvent('close', { code, reason, wasClean }));
+    }
+
+    _handleEvent(event) {
+      switch (event.event) {
+        case 'open':
+          if (this.readyState === CLOSING || this.readyState === CLOSED) return;
+          this.readyState = OPEN;
+          this.protocol = event.protocol || '';
+          if (event.reconnected) {
+            log(`🔁 [WS] 已重连: ${this.url}`);
+          }
+          this._dispatch(new Event('open'));
+          break;
+        case 'text':
+          this._dispatch(new MessageEvent('message', { data: event.data, origin: new URL(this.url).origin }));
+          break;
+        case 'binary': {
+          const buffer = base64ToArrayBuffer(event.data);
+          const data = this.binaryType === 'arraybuffer' ? buffer : new Blob([buffer]);
+          this._dispatch(new MessageEvent('message', { data, origin: new URL(this.url).origin }));
+          break;
+        }
+        case 'reconnecting': {
+          if (this.readyState === CLOSING || this.readyState === CLOSED) return;
+          log(`🔁 [WS] 连接断开 (${event.code})，${event.delay_ms}ms 后第 ${event.attempt} 次重连`);
+          this.readyState = CONNECTING;
+          const reconnecting = new Event('reconnecting');
+          reconnecting.attempt = event.attempt;
+          reconnecting.delay = event.delay_ms;
+          reconnecting.code = event.code;
+          reconnecting.reason = event.reason;
+          this._dispatch(reconnecting);
+          break;
+        }
+        case 'error':
+          console.error('❌ [WS] 连接错误:', event.error);
+          this._dispatch(new Event('error'));
+          break;
+        case 'close':
+          this._finish(event.code, event.reason, event.was_clean, false);
+          break;
+      }
+    }
+  }
+
+  for (const [name, value] of Object.entries({ CONNECTING, OPEN, CLOSING, CLOSED })) {
+    Object.defineProperty(ProxyWebSocket, name, { value, enumerable: true });
+    Object.defineProperty(ProxyWebSocket.prototype, name, { value, enumerable: true });
+  }
+  ProxyWebSocket.__tauriProxy = true;
+
+  window.WebSocket = ProxyWebSocket;
+  log('✅ WebSocket 代理已启用（/base_api/）');
+}
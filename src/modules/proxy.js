@@ -2,6 +2,30 @@
  * HTTP 代理拦截模块 (Fetch + XMLHttpRequest)
  */
 
+import {
+  invokeProxyStream,
+  readStreamToBuffer,
+  emitProxyProgress,
+  isCancelledError,
+  isTimeoutError,
+  createAbortError,
+  createNetworkError,
+  bindAbortSignal
+} from './proxy-stream.js';
+import {
+  generateRequestId,
+  initUploadProgressListener,
+  onUploadProgress,
+  serializeFormDataParts
+} from './proxy-upload.js';
+import {
+  toHeaderList,
+  getHeader,
+  setHeader,
+  removeHeader,
+  formatAllHeaders
+} from './utils/headers.js';
+
 const toString = Object.prototype.toString;
 
 function isRequest(value) {
@@ -10,12 +34,6 @@ function isRequest(value) {
   return toString.call(value) === '[object Request]';
 }
 
-function isHeaders(value) {
-  if (!value) return false;
-  if (typeof Headers !== 'undefined' && value instanceof Headers) return true;
-  return toString.call(value) === '[object Headers]';
-}
-
 function isFormData(value) {
   if (!value) return false;
   if (typeof FormData !== 'undefined' && value instanceof FormData) return true;
@@ -28,58 +46,31 @@ function isFile(value) {
   return toString.call(value) === '[object File]';
 }
 
-function normalizeHeaders(source) {
-  if (!source) return {};
-  const headers = {};
-
-  if (isHeaders(source)) {
-    source.forEach((v, k) => headers[k] = v);
-    return headers;
+// 流式响应只能打印请求信息和响应头
+function printDebugInfo(debug, bodyLabel) {
+  console.groupCollapsed(
+    `%c${debug.request_method} %c${debug.response_status} %c${debug.request_url}`,
+    'color: #0066cc; font-weight: bold',
+    debug.response_status >= 200 && debug.response_status < 300 ? 'color: #00cc00; font-weight: bold' : 'color: #cc0000; font-weight: bold',
+    'color: #666'
+  );
+  console.log('📍 Request URL:', debug.request_url);
+  console.log('🔧 Request Method:', debug.request_method);
+  console.log('📤 Request Headers:', debug.request_headers);
+  if (debug.request_body) {
+    console.log('📦 Request Body:', debug.request_body);
   }
-
-  if (Array.isArray(source)) {
-    source.forEach(([k, v]) => {
-      if (k) headers[k] = v;
-    });
-    return headers;
-  }
-
-  return { ...source };
-}
-
-async function serializeFormData(formData) {
-  const fields = [];
-  const files = [];
-
-  for (const [key, value] of formData.entries()) {
-    if (isFile(value)) {
-      const reader = new FileReader();
-      const base64 = await new Promise((resolve) => {
-        reader.onload = () => {
-          const result = reader.result;
-          const chunk = typeof result === 'string' ? result.split(',')[1] : '';
-          resolve(chunk || '');
-        };
-        reader.onerror = () => resolve('');
-        reader.readAsDataURL(value);
-      });
-
-      files.push({
-        field_name: key,
-        file_name: value.name,
-        content_type: value.type || 'application/octet-stream',
-        data: base64
-      });
-    } else {
-      fields.push([key, value?.toString() ?? '']);
-    }
-  }
-
-  return { fields, files };
+  console.log('📊 Response Status:', debug.response_status);
+  console.log('📥 Response Headers:', debug.response_headers);
+  console.log(bodyLabel);
+  console.groupEnd();
 }
 
 export function initProxy(log, invoke) {
   log("🚀 初始化代理模块...");
+  initUploadProgressListener(log);
+  // 代理错误的提示文字跟随页面语言
+  invoke('set_locale', { locale: navigator.language || '' }).catch(() => {});
   
   // --- Override window.fetch ---
   const originalFetch = window.fetch;
@@ -94,7 +85,10 @@ export function initProxy(log, invoke) {
         requestInit = {
           method: input.method,
           headers: input.headers,
-          body: input.body
+          body: input.body,
+          signal: input.signal,
+          cache: input.cache,
+          redirect: input.redirect
         };
       }
     }
@@ -117,22 +111,31 @@ export function initProxy(log, invoke) {
 
     log("🔄 [Fetch] Intercepted:", url);
 
-    let headers = requestInit ? normalizeHeaders(requestInit.headers) : {};
+    const signal = requestInit ? requestInit.signal : null;
+    if (signal && signal.aborted) {
+      throw createAbortError();
+    }
+
+    let headers = requestInit ? toHeaderList(requestInit.headers) : [];
+    // fetch 的 cache 选项映射为 Cache-Control，由 Rust 端 HTTP 缓存处理
+    const cacheMode = requestInit ? requestInit.cache : undefined;
+    if (cacheMode === 'no-store') {
+      headers = setHeader(headers, 'Cache-Control', 'no-store');
+    } else if (cacheMode === 'reload' || cacheMode === 'no-cache') {
+      headers = setHeader(headers, 'Cache-Control', 'no-cache');
+    }
     let body = null;
-    let formData = null;
-    let files = null;
+    let parts = null;
+    const requestId = generateRequestId();
     
     if (requestInit && requestInit.body) {
       if (typeof requestInit.body === 'string') {
         body = requestInit.body;
       } else if (isFormData(requestInit.body)) {
         log("📦 检测到 FormData");
-        const serialized = await serializeFormData(requestInit.body);
-        formData = serialized.fields;
-        files = serialized.files.length > 0 ? serialized.files : null;
+        parts = await serializeFormDataParts(invoke, requestInit.body, isFile);
         
-        delete headers['Content-Type'];
-        delete headers['content-type'];
+        headers = removeHeader(headers, 'Content-Type');
       } else {
         try {
           body = JSON.stringify(requestInit.body);
@@ -143,71 +146,67 @@ export function initProxy(log, invoke) {
     }
 
     const reqData = {
+      id: requestId,
       method: (requestInit && requestInit.method) ? requestInit.method.toUpperCase() : 'GET',
       url: url.toString(),
       headers: headers,
       body: body,
-      form_data: formData,
-      files: files
+      parts,
+      // follow / error / manual，与原生 fetch 一致，由 Rust 端逐跳处理
+      redirect: (requestInit && requestInit.redirect) || 'follow'
     };
 
+    // fetch 没有上传进度 API，通过 window 事件广播
+    const stopUploadProgress = parts ? onUploadProgress(requestId, (loaded, total) => {
+      window.dispatchEvent(new CustomEvent('tauri-proxy-upload-progress', {
+        detail: { url: reqData.url, loaded, total }
+      }));
+    }) : null;
+    // 成功返回后保持绑定：读取响应体期间 abort 仍需中断 Rust 端推送
+    const unbindAbort = bindAbortSignal(signal, invoke, requestId);
+
     try {
-      const response = await invoke('proxy_request', { request: reqData });
-      
+      // 流式模式：响应头先返回，响应体以 ReadableStream 逐块读取
+      const { head, body: stream } = await invokeProxyStream(invoke, reqData, (loaded, total) => {
+        emitProxyProgress(reqData.url, loaded, total);
+      });
+
       // 如果有调试信息，在控制台打印（类似 Network 面板）
-      if (response.debug_info) {
-        const debug = response.debug_info;
-        console.groupCollapsed(
-          `%c${debug.request_method} %c${debug.response_status} %c${debug.request_url}`,
-          'color: #0066cc; font-weight: bold',
-          debug.response_status >= 200 && debug.response_status < 300 ? 'color: #00cc00; font-weight: bold' : 'color: #cc0000; font-weight: bold',
-          'color: #666'
-        );
-        console.log('📍 Request URL:', debug.request_url);
-        console.log('🔧 Request Method:', debug.request_method);
-        console.log('📤 Request Headers:', debug.request_headers);
-        if (debug.request_body) {
-          console.log('📦 Request Body:', JSON.parse(debug.request_body));
-        }
-        console.log('📊 Response Status:', debug.response_status);
-        console.log('📥 Response Headers:', debug.response_headers);
-        if (response.is_binary) {
-          console.log('📦 Response Type: Binary (base64 encoded)');
-        } else {
-          console.log('📄 Response Body:', JSON.parse(response.body));
-        }
-        console.groupEnd();
+      if (head.debug_info) {
+        printDebugInfo(head.debug_info, '📦 Response Body: (streamed)');
       }
-      
-      if (response.status === 403) {
+
+      if (head.status === 403) {
         log.error("⚠️ 403 Forbidden!");
       }
-      
-      // 处理响应体：如果是二进制，解码 base64
-      let responseBody;
-      if (response.is_binary) {
-        // 二进制响应：解码 base64 -> Uint8Array
-        log("📦 解码二进制响应");
-        const binaryString = atob(response.body);
-        const bytes = new Uint8Array(binaryString.length);
-        for (let i = 0; i < binaryString.length; i++) {
-          bytes[i] = binaryString.charCodeAt(i);
-        }
-        responseBody = bytes;
-      } else {
-        // 文本响应：直接使用
-        responseBody = response.body;
+
+      // 204/304 等状态不允许携带 body
+      const nullBodyStatus = [101, 204, 205, 304].includes(head.status);
+      if (nullBodyStatus) {
+        stream.cancel().catch(() => {});
       }
-      
-      return new Response(responseBody, {
-        status: response.status,
-        statusText: response.status === 200 ? 'OK' : 'Error',
-        headers: new Headers(response.headers)
+
+      const response = new Response(nullBodyStatus ? null : stream, {
+        status: head.status,
+        statusText: head.status === 200 ? 'OK' : 'Error',
+        headers: new Headers(head.headers)
       });
-      
+      // Response 构造函数无法设置 url/redirected，在实例上覆盖只读 getter
+      Object.defineProperty(response, 'url', { value: head.url || reqData.url });
+      Object.defineProperty(response, 'redirected', { value: !!head.redirected });
+      return response;
+
     } catch (err) {
+      unbindAbort();
+      if (isCancelledError(err)) {
+        log("🛑 [Fetch] 请求已取消:", url);
+        throw createAbortError();
+      }
       log.error("❌ Proxy Request Failed:", err);
-      throw err;
+      // 与原生 fetch 一致：网络错误以 TypeError 抛出
+      throw err instanceof Error ? err : createNetworkError(err);
+    } finally {
+      if (stopUploadProgress) stopUploadProgress();
     }
   };
 
@@ -215,15 +214,23 @@ export function initProxy(log, invoke) {
   const OriginalXHR = window.XMLHttpRequest;
   
   function ProxyXHR() {
-    this.headers = {};
-    this.responseHeaders = {};
+    this.headers = [];
+    this.responseHeaders = [];
     this.onreadystatechange = null;
     this.onload = null;
     this.onerror = null;
+    this.onabort = null;
+    this.ontimeout = null;
+    // 毫秒，0 表示使用 Rust 端默认总超时
+    this.timeout = 0;
     this.status = 0;
     this.readyState = 0;
+    this.responseURL = "";
     this.responseText = "";
     this.response = "";
+    this.responseType = "";
+    // 上传进度（仅 FormData 请求会触发）
+    this.upload = { onprogress: null };
   }
 
   ProxyXHR.prototype.open = function(method, url, async, user, password) {
@@ -233,8 +240,9 @@ export function initProxy(log, invoke) {
     if (this.onreadystatechange) this.onreadystatechange();
   };
 
+  // 同名头多次设置时逐条保留，与原生 XHR 合并值的语义一致
   ProxyXHR.prototype.setRequestHeader = function(header, value) {
-    this.headers[header] = value;
+    this.headers.push([header, String(value)]);
   };
 
   ProxyXHR.prototype.send = function(data) {
@@ -246,11 +254,12 @@ export function initProxy(log, invoke) {
     if (!url.includes('/base_api/')) {
       const originalXHR = new OriginalXHR();
       originalXHR.open(this.method, this.url, true);
-      for (const [key, value] of Object.entries(this.headers)) {
+      for (const [key, value] of this.headers) {
         originalXHR.setRequestHeader(key, value);
       }
       originalXHR.onload = () => {
         this.status = originalXHR.status;
+        this.responseURL = originalXHR.responseURL;
         this.responseText = originalXHR.responseText;
         this.response = originalXHR.response;
         this.readyState = 4;
@@ -265,46 +274,54 @@ export function initProxy(log, invoke) {
     }
     
     const self = this;
+    const requestId = generateRequestId();
+    this._requestId = requestId;
+    this._aborted = false;
+    // XHR.timeout 映射为 Rust 端的总超时
+    const timeouts = this.timeout > 0 ? { total_ms: this.timeout } : null;
+
+    // 被 abort() 中断的请求只触发 onabort
+    const handleError = (label, err) => {
+      if (self._aborted || isCancelledError(err)) {
+        log("🛑 [XHR] 请求已取消:", url);
+        return;
+      }
+      if (isTimeoutError(err) && self.ontimeout) {
+        log.error("⏱️ [XHR] 请求超时:", url);
+        self.ontimeout(err);
+        return;
+      }
+      log.error(label, err);
+      if (self.onerror) self.onerror(err);
+    };
     
     if (isFormData(data)) {
       (async () => {
         try {
-          const formDataArray = [];
-          const filesArray = [];
-          
-          for (const [key, value] of data.entries()) {
-            if (isFile(value)) {
-              const reader = new FileReader();
-              const base64 = await new Promise((resolve, reject) => {
-                reader.onload = () => resolve(reader.result.split(',')[1]);
-                reader.onerror = () => reject(reader.error);
-                reader.readAsDataURL(value);
-              });
-              
-              filesArray.push({
-                field_name: key,
-                file_name: value.name,
-                content_type: value.type || 'application/octet-stream',
-                data: base64
-              });
-            } else {
-              formDataArray.push([key, value.toString()]);
-            }
-          }
-          
+          const parts = await serializeFormDataParts(invoke, data, isFile);
+
           const reqData = {
+            id: requestId,
             method: self.method,
             url: url,
-            headers: self.headers,
+            // multipart 边界由 Rust 端生成
+            headers: removeHeader(self.headers, 'Content-Type'),
             body: null,
-            form_data: formDataArray.length > 0 ? formDataArray : null,
-            files: filesArray.length > 0 ? filesArray : null
+            parts,
+            timeouts
           };
           
-          delete reqData.headers['Content-Type'];
-          delete reqData.headers['content-type'];
-          
-          const response = await invoke('proxy_request', { request: reqData });
+          const stopProgress = onUploadProgress(requestId, (loaded, total) => {
+            if (self.upload.onprogress) {
+              self.upload.onprogress({ loaded, total, lengthComputable: total > 0 });
+            }
+          });
+          let response;
+          try {
+            response = await invoke('proxy_request', { request: reqData });
+          } finally {
+            stopProgress();
+          }
           
           // 打印调试信息
           if (response.debug_info) {
@@ -326,6 +343,7 @@ export function initProxy(log, invoke) {
             if (response.is_binary) {
               console.log('📦 Response Type: Binary (base64 encoded)');
             } else {
+              console.log('🔤 Response Encoding:', response.encoding);
               console.log('📄 Response Body:', JSON.parse(response.body));
             }
             console.groupEnd();
@@ -346,31 +364,72 @@ export function initProxy(log, invoke) {
             responseBody = response.body;
           }
           
+          if (self._aborted) return;
+          
           self.status = response.status;
           self.statusText = response.status === 200 ? "OK" : "";
           self.responseText = response.is_binary ? "" : response.body; // 二进制时不设置 responseText
           self.response = responseBody;
           self.readyState = 4;
           self.responseHeaders = response.headers;
+          self.responseURL = response.url || url;
           
           if (self.onreadystatechange) self.onreadystatechange();
           if (self.onload) self.onload();
           
         } catch (err) {
-          log.error("XHR FormData Error:", err);
-          if (self.onerror) self.onerror(err);
+          handleError("XHR FormData Error:", err);
         }
       })();
       return;
     }
     
     const reqData = {
+      id: requestId,
       method: this.method,
       url: url,
       headers: this.headers,
-      body: data ? data.toString() : null
+      body: data ? data.toString() : null,
+      timeouts
     };
-    
+
+    // 二进制下载（blob/arraybuffer）走流式通道，支持 onprogress，避免 base64 膨胀
+    if (this.responseType === 'blob' || this.responseType === 'arraybuffer') {
+      (async () => {
+        try {
+          const { head, body: stream } = await invokeProxyStream(invoke, reqData, (loaded, total) => {
+            emitProxyProgress(url, loaded, total);
+            if (self.onprogress) {
+              self.onprogress({ loaded, total: total || 0, lengthComputable: !!total });
+            }
+          });
+
+          if (head.debug_info) {
+            printDebugInfo(head.debug_info, `📦 Response Type: ${self.responseType} (streamed)`);
+          }
+
+          const buffer = await readStreamToBuffer(stream);
+          const contentType = getHeader(head.headers, 'content-type') || '';
+
+          if (self._aborted) return;
+
+          self.status = head.status;
+          self.statusText = head.status === 200 ? "OK" : "";
+          self.responseText = "";
+          self.response = self.responseType === 'blob' ? new Blob([buffer], { type: contentType }) : buffer;
+          self.readyState = 4;
+          self.responseHeaders = head.headers;
+          self.responseURL = head.url || url;
+
+          if (self.onreadystatechange) self.onreadystatechange();
+          if (self.onload) self.onload();
+        } catch (err) {
+          handleError("XHR Stream Error:", err);
+        }
+      })();
+      return;
+    }
+
     invoke('proxy_request', { request: reqData })
       .then(response => {
         // 打印调试信息
@@ -393,6 +452,7 @@ export function initProxy(log, invoke) {
           if (response.is_binary) {
             console.log('📦 Response Type: Binary (base64 encoded)');
           } else {
+            console.log('🔤 Response Encoding:', response.encoding);
             console.log('📄 Response Body:', JSON.parse(response.body));
           }
           console.groupEnd();
@@ -413,32 +473,43 @@ export function initProxy(log, invoke) {
           responseBody = response.body;
         }
         
+        if (self._aborted) return;
+        
         self.status = response.status;
         self.statusText = response.status === 200 ? "OK" : "";
         self.responseText = response.is_binary ? "" : response.body;
         self.response = responseBody;
         self.readyState = 4;
         self.responseHeaders = response.headers;
+        self.responseURL = response.url || url;
 
         if (self.onreadystatechange) self.onreadystatechange();
         if (self.onload) self.onload();
       })
       .catch(err => {
-        log.error("XHR Proxy Error", err);
-        if (self.onerror) self.onerror(err);
+        handleError("XHR Proxy Error", err);
       });
   };
   
+  ProxyXHR.prototype.abort = function() {
+    if (!this._requestId || this.readyState === 4) return;
+    this._aborted = true;
+    invoke('cancel_proxy_request', { id: this._requestId }).catch(() => {});
+    this.readyState = 4;
+    this.status = 0;
+    if (this.onreadystatechange) this.onreadystatechange();
+    if (this.onabort) this.onabort();
+    this.readyState = 0;
+  };
+
   ProxyXHR.prototype.getAllResponseHeaders = function() {
-    let res = "";
-    for (const [k, v] of Object.entries(this.responseHeaders)) {
-      res += `${k}: ${v}\r\n`;
-    }
-    return res;
+    return formatAllHeaders(this.responseHeaders);
   };
   
   ProxyXHR.prototype.getResponseHeader = function(name) {
-    return this.responseHeaders[name] || null;
+    const lower = String(name).toLowerCase();
+    if (lower === 'set-cookie' || lower === 'set-cookie2') return null;
+    return getHeader(this.responseHeaders, lower);
   };
 
   window.XMLHttpRequest = ProxyXHR;
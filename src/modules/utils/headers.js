@@ -0,0 +1,69 @@
+/**
+ * 请求/响应头工具模块
+ * 与 Rust 端之间以 [[name, value], ...] 传输，保留顺序与重复项
+ */
+
+const toString = Object.prototype.toString;
+
+function isHeaders(value) {
+  if (!value) return false;
+  if (typeof Headers !== 'undefined' && value instanceof Headers) return true;
+  return toString.call(value) === '[object Headers]';
+}
+
+/**
+ * 把 Headers / 数组 / 普通对象统一转换为 [[name, value], ...]
+ */
+export function toHeaderList(source) {
+  if (!source) return [];
+
+  if (isHeaders(source)) {
+    const list = [];
+    source.forEach((v, k) => list.push([k, v]));
+    return list;
+  }
+
+  if (Array.isArray(source)) {
+    return source.filter(pair => pair && pair[0]).map(([k, v]) => [String(k), String(v)]);
+  }
+
+  return Object.entries(source).map(([k, v]) => [k, String(v)]);
+}
+
+/**
+ * 取同名头的值（大小写不敏感），多个值按 ", " 拼接；不存在返回 null
+ */
+export function getHeader(list, name) {
+  const lower = name.toLowerCase();
+  const values = list.filter(([k]) => k.toLowerCase() === lower).map(([, v]) => v);
+  return values.length ? values.join(', ') : null;
+}
+
+/**
+ * 删除同名头，返回新列表
+ */
+export function removeHeader(list, name) {
+  const lower = name.toLowerCase();
+  return list.filter(([k]) => k.toLowerCase() !== lower);
+}
+
+/**
+ * 替换同名头，返回新列表
+ */
+export function setHeader(list, name, value) {
+  return [...removeHeader(list, name), [name, value]];
+}
+
+/**
+ * 按 XHR getAllResponseHeaders 的格式输出：名称小写、按名称排序、
+ * 同名值以 ", " 合并，不暴露 Set-Cookie
+ */
+export function formatAllHeaders(list) {
+  const merged = new Map();
+  for (const [k, v] of list) {
+    const lower = k.toLowerCase();
+    if (lower === 'set-cookie' || lower === 'set-cookie2') continue;
+    merged.set(lower, merged.has(lower) ? `${merged.get(lower)}, ${v}` : v);
+  }
+  return [...merged.keys()].sort().map(k => `${k}: ${merged.get(k)}\r\n`).join('');
+}
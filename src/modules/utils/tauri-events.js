@@ -0,0 +1,32 @@
+/**
+ * Tauri 事件监听工具
+ * 注意：外部 URL 模式下 window.__TAURI__.event 不存在，
+ * 需要用底层 __TAURI_INTERNALS__ API 注册事件监听
+ */
+
+export function canListenTauriEvents() {
+  const internals = window.__TAURI_INTERNALS__;
+  return !!(internals && internals.invoke && internals.transformCallback);
+}
+
+/**
+ * 只接收发给当前 webview 的事件（Rust 端 emit_to 到该 webview）；取不到标签时退回到任意目标
+ */
+export function currentWebviewTarget() {
+  const label = window.__TAURI_INTERNALS__?.metadata?.currentWebview?.label;
+  return label ? { kind: 'Webview', label } : { kind: 'Any' };
+}
+
+export function listenTauriEvent(eventName, handler, log, target = { kind: 'Any' }) {
+  const internals = window.__TAURI_INTERNALS__;
+  const callbackId = internals.transformCallback((event) => {
+    handler(event);
+  });
+  return internals.invoke('plugin:event|listen', {
+    event: eventName,
+    target,
+    handler: callbackId,
+  }).catch(err => {
+    if (log) log(`⚠️ 注册事件 ${eventName} 失败: ${err}`);
+  });
+}